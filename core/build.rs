@@ -0,0 +1,227 @@
+//! Turns the device definitions in `definitions/` into const tables, which `src/devices/bundled.rs` includes
+//!
+//! The files use the same format as the definitions the TUI loads at runtime. Values are only converted to their
+//! types here, every definition is checked by `DeviceDefinition::validate` when the crate is compiled, just like the
+//! TUI checks the definitions it loads.
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use toml::{Table, Value};
+
+const DEFINITIONS_DIR: &str = "definitions";
+
+const U8_MAX: i64 = u8::MAX as i64;
+/// Family and member of an Identity Reply are two 7 bit bytes, LSB first
+const IDENTITY_WORD_MAX: i64 = 0x7F7F;
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", DEFINITIONS_DIR);
+
+    let mut paths = fs::read_dir(DEFINITIONS_DIR)
+        .expect("definitions directory is missing")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<PathBuf>>();
+
+    // generated code doesn't depend on the order of the file system
+    paths.sort();
+
+    let mut out = String::new();
+
+    for path in &paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let definition = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| text.parse::<Table>().map_err(|e| e.to_string()))
+            .and_then(|table| write_definition(&mut out, &table, path));
+
+        if let Err(e) = definition {
+            panic!("{}: {}", path.display(), e);
+        }
+    }
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("bundled.rs");
+    fs::write(out_path, out).unwrap();
+}
+
+fn write_definition(out: &mut String, table: &Table, path: &Path) -> Result<(), String> {
+    let id = text(table, "id")?;
+    let name = text(table, "name")?;
+    let manufacturer = text(table, "manufacturer")?;
+    let port_name = match table.get("port_name") {
+        Some(_) => text(table, "port_name")?,
+        None => name,
+    };
+    // channel 0 turns into an invalid one
+    let channel = (number(table, "channel", 0, U8_MAX)? as u8).wrapping_sub(1);
+    let notes = entries(table, "notes")?;
+    let params = entries(table, "params")?;
+
+    let prefix = id.to_uppercase().replace('-', "_");
+
+    writeln!(out, "/// {} {}", manufacturer, name).unwrap();
+    writeln!(
+        out,
+        "pub const {}: DeviceDefinition = DeviceDefinition {{",
+        prefix
+    )
+    .unwrap();
+    writeln!(out, "    id: {:?},", id).unwrap();
+    writeln!(out, "    name: {:?},", name).unwrap();
+    writeln!(out, "    manufacturer: {:?},", manufacturer).unwrap();
+    writeln!(out, "    port_name: {:?},", port_name).unwrap();
+    writeln!(out, "    channel: {},", channel).unwrap();
+    writeln!(out, "    notes: &{}_NOTES,", prefix).unwrap();
+    writeln!(out, "    params: &{}_PARAMS,", prefix).unwrap();
+    writeln!(out, "    identity: {},", identity(table)?).unwrap();
+    writeln!(out, "}};\n").unwrap();
+
+    writeln!(
+        out,
+        "const _: () = assert!({}.validate().is_ok(), {:?});\n",
+        prefix,
+        format!("{} is not a valid definition", path.display())
+    )
+    .unwrap();
+
+    writeln!(
+        out,
+        "/// Notes of `{}` in the order of its definition",
+        prefix
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "pub const {}_NOTES: [NoteDefinition; {}] = [",
+        prefix,
+        notes.len()
+    )
+    .unwrap();
+    for note in &notes {
+        writeln!(
+            out,
+            "    NoteDefinition {{ note: {}, name: {:?} }},",
+            number(note, "note", 0, U8_MAX)?,
+            text(note, "name")?
+        )
+        .unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    writeln!(
+        out,
+        "/// Parameters of `{}` in the order of its definition",
+        prefix
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "pub const {}_PARAMS: [ParamDefinition; {}] = [",
+        prefix,
+        params.len()
+    )
+    .unwrap();
+    for param in &params {
+        let min = optional_number(param, "min", 0)?;
+        let max = optional_number(param, "max", 127)?;
+        let group = match param.get("group") {
+            Some(_) => text(param, "group")?,
+            None => "",
+        };
+
+        writeln!(
+            out,
+            "    ParamDefinition {{ cc: {}, name: {:?}, group: {:?}, min: {}, max: {}, default: {} }},",
+            number(param, "cc", 0, U8_MAX)?,
+            text(param, "name")?,
+            group,
+            min,
+            max,
+            optional_number(param, "default", min)?,
+        )
+        .unwrap();
+    }
+    writeln!(out, "];\n").unwrap();
+
+    Ok(())
+}
+
+fn identity(table: &Table) -> Result<String, String> {
+    let Some(identity) = table.get("identity") else {
+        return Ok("None".to_string());
+    };
+    let identity = identity.as_table().ok_or("identity has to be a table")?;
+
+    let manufacturer = identity
+        .get("manufacturer")
+        .and_then(Value::as_array)
+        .ok_or("identity needs the manufacturer bytes")?
+        .iter()
+        .map(|byte| byte.as_integer().filter(|byte| (0..=U8_MAX).contains(byte)))
+        .collect::<Option<Vec<i64>>>()
+        .ok_or("manufacturer has to be bytes")?;
+
+    let manufacturer = match manufacturer[..] {
+        [id] if id != 0 => format!("ManufacturerId::Short(0x{:02X})", id),
+        [0, id_1, id_2] => format!("ManufacturerId::Extended(0x{:02X}, 0x{:02X})", id_1, id_2),
+        _ => return Err("manufacturer needs to be one byte or three bytes starting with 0".into()),
+    };
+
+    let member = match identity.get("member") {
+        Some(_) => format!(
+            "Some(0x{:04X})",
+            number(identity, "member", 0, IDENTITY_WORD_MAX)?
+        ),
+        None => "None".to_string(),
+    };
+
+    Ok(format!(
+        "Some(IdentityMatch {{ manufacturer: {}, family: 0x{:04X}, member: {} }})",
+        manufacturer,
+        number(identity, "family", 0, IDENTITY_WORD_MAX)?,
+        member
+    ))
+}
+
+fn text<'a>(table: &'a Table, key: &str) -> Result<&'a str, String> {
+    table
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or(format!("{} has to be text", key))
+}
+
+fn number(table: &Table, key: &str, min: i64, max: i64) -> Result<i64, String> {
+    table
+        .get(key)
+        .and_then(Value::as_integer)
+        .filter(|value| (min..=max).contains(value))
+        .ok_or(format!(
+            "{} has to be a number from {} to {}",
+            key, min, max
+        ))
+}
+
+fn optional_number(table: &Table, key: &str, default: i64) -> Result<i64, String> {
+    match table.get(key) {
+        Some(_) => number(table, key, 0, U8_MAX),
+        None => Ok(default),
+    }
+}
+
+/// Array of tables, like `[[params]]`
+fn entries<'a>(table: &'a Table, key: &str) -> Result<Vec<&'a Table>, String> {
+    match table.get(key) {
+        Some(Value::Array(entries)) => entries
+            .iter()
+            .map(|entry| entry.as_table().ok_or(format!("{} have to be tables", key)))
+            .collect(),
+        Some(_) => Err(format!("{} have to be tables", key)),
+        None => Ok(Vec::new()),
+    }
+}
@@ -0,0 +1,164 @@
+/// MIDI clock resolution in pulses per quarter note
+pub const PPQN: u32 = 24;
+
+/// Filter gain for the phase error, i.e. how fast the estimate is pulled towards incoming pulses
+const PHASE_GAIN: f32 = 0.1;
+/// Filter gain for the period error, chosen for a critically damped loop with `PHASE_GAIN`
+const PERIOD_GAIN: f32 = PHASE_GAIN * PHASE_GAIN / (2.0 - PHASE_GAIN);
+
+/// Number of raw intervals used to detect a tempo change
+const FAST_AVG_LEN: usize = 4;
+/// Relative deviation of the fast average from the locked period that counts as a tempo change
+const TEMPO_CHANGE_THRESHOLD: f32 = 0.04;
+/// Consecutive pulses that have to deviate before the estimator relocks onto the new tempo
+const TEMPO_CHANGE_PULSES: u8 = 3;
+/// Lock is lost if no pulse arrived within this many periods
+const LOCK_TIMEOUT_PERIODS: f32 = 4.0;
+
+/// Phase-locked tempo estimator for incoming MIDI clock
+///
+/// Raw pulse timestamps jitter by hundreds of µs. Instead of averaging intervals, this tracks the time of the
+/// last pulse and the period in between pulses with a second order loop filter (alpha-beta filter). Each pulse
+/// is compared to its predicted arrival time and the error nudges both phase and period. Large and persistent
+/// deviations are treated as a tempo change and the filter relocks immediately.
+///
+/// All times are in µs.
+#[derive(Clone, Debug)]
+pub struct TempoEstimator {
+    /// filtered period between two pulses
+    period: Option<f32>,
+    /// filtered time of the last pulse
+    last_pulse: u64,
+    /// unfiltered time of the last pulse
+    last_raw_pulse: Option<u64>,
+
+    /// ring buffer of the most recent raw intervals
+    fast_intervals: [u32; FAST_AVG_LEN],
+    n_fast_intervals: usize,
+    n_deviating_pulses: u8,
+}
+
+impl Default for TempoEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TempoEstimator {
+    pub const fn new() -> Self {
+        Self {
+            period: None,
+            last_pulse: 0,
+            last_raw_pulse: None,
+
+            fast_intervals: [0; FAST_AVG_LEN],
+            n_fast_intervals: 0,
+            n_deviating_pulses: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Must be called for every incoming MIDI clock pulse
+    pub fn pulse(&mut self, now: u64) {
+        // the interval of a gap, e.g. while the master has been stopped, says nothing about the tempo
+        if self.period.is_some() && !self.is_locked(now) {
+            self.last_raw_pulse = Some(now);
+            self.n_fast_intervals = 0;
+            self.relock_at(now);
+            return;
+        }
+
+        let Some(last_raw) = self.last_raw_pulse.replace(now) else {
+            self.last_pulse = now;
+            return;
+        };
+
+        let interval = now.saturating_sub(last_raw) as u32;
+        self.fast_intervals[self.n_fast_intervals % FAST_AVG_LEN] = interval;
+        self.n_fast_intervals += 1;
+
+        let Some(period) = self.period else {
+            self.period = Some(interval as f32);
+            self.last_pulse = now;
+            return;
+        };
+
+        if self.detect_tempo_change(period) {
+            self.relock(now);
+            return;
+        }
+
+        // deviation from the predicted arrival time, kept relative to avoid f32 precision loss on absolute time
+        let error = now.wrapping_sub(self.last_pulse) as i64 as f32 - period;
+        let step = period + PHASE_GAIN * error;
+
+        self.last_pulse = self.last_pulse.saturating_add_signed((step + 0.5) as i64);
+        self.period = Some(period + PERIOD_GAIN * error);
+    }
+
+    /// Filtered tempo in quarter notes per minute
+    pub fn bpm(&self) -> Option<f32> {
+        self.period
+            .map(|period| 60_000_000.0 / (period * PPQN as f32))
+    }
+
+    /// Position in between the last and the next expected pulse from 0.0 to just below 1.0
+    ///
+    /// The phase never reaches the next pulse on its own, so position only moves forward with the master.
+    pub fn phase(&self, now: u64) -> f32 {
+        match self.period {
+            Some(period) => {
+                let elapsed = now.saturating_sub(self.last_pulse) as f32;
+                (elapsed / period).clamp(0.0, 1.0 - f32::EPSILON)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Whether the estimator has a tempo and pulses keep arriving
+    pub fn is_locked(&self, now: u64) -> bool {
+        match (self.period, self.last_raw_pulse) {
+            (Some(period), Some(last_raw)) => {
+                (now.saturating_sub(last_raw) as f32) < period * LOCK_TIMEOUT_PERIODS
+            }
+            _ => false,
+        }
+    }
+
+    fn fast_average(&self) -> Option<f32> {
+        if self.n_fast_intervals < FAST_AVG_LEN {
+            return None;
+        }
+
+        let sum: u32 = self.fast_intervals.iter().sum();
+        Some(sum as f32 / FAST_AVG_LEN as f32)
+    }
+
+    fn detect_tempo_change(&mut self, period: f32) -> bool {
+        let Some(fast) = self.fast_average() else {
+            return false;
+        };
+
+        if (fast - period).abs() / period > TEMPO_CHANGE_THRESHOLD {
+            self.n_deviating_pulses += 1;
+        } else {
+            self.n_deviating_pulses = 0;
+        }
+
+        self.n_deviating_pulses >= TEMPO_CHANGE_PULSES
+    }
+
+    fn relock(&mut self, now: u64) {
+        self.period = self.fast_average();
+        self.relock_at(now);
+    }
+
+    /// Restarts the phase at `now`
+    fn relock_at(&mut self, now: u64) {
+        self.last_pulse = now;
+        self.n_deviating_pulses = 0;
+    }
+}
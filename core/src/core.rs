@@ -1,21 +1,52 @@
 use super::{
-    devices::{DeviceInterface, SupportedDevice},
-    looper::Looper,
+    clock::{TempoEstimator, PPQN},
+    devices::{DefinedDevice, DefinitionError, DeviceDefinition, DeviceInterface, SupportedDevice},
+    looper::{
+        LoopEvent, Looper, LOOPER_MAX_LEN, LOOPER_MIN_LEN, LOOP_EVENTS_LEN, SUBTICKS_PER_TICK,
+    },
     midi::*,
+    session::{SessionError, SessionReader, SessionWriter},
+    smf::{read_smf, Smf, SmfError, SmfEvent, SmfFormat, SmfImport},
 };
-use heapless::Vec;
-
-const TX_MIDI_Q_LEN: usize = 16;
-const LOOPER_MIN_LEN: u16 = 4;
-const LOOPER_MAX_LEN: u16 = 256;
+use heapless::{Deque, Vec};
+use strum::IntoEnumIterator;
+
+/// Payload capacity of received and sent SysEx messages
+pub const SYSEX_LEN: usize = 256;
+/// Number of SysEx messages that can wait to be sent or picked up
+const SYSEX_Q_LEN: usize = 4;
+/// Number of clock and transport messages that can wait to be forwarded
+const CLOCK_THRU_Q_LEN: usize = 8;
+/// room for the longest message group of every lane, poly aftertouch and a note on and off of every note, transport,
+/// clock, forwarded clock and one SysEx
+const TX_MIDI_Q_LEN: usize =
+    4 * 3 * N_LANES + 3 * 3 * N_NOTES + 4 + CLOCK_THRU_Q_LEN + SYSEX_LEN + 2;
+const MIN_BPM: f32 = 40.0;
+const MAX_BPM: f32 = 200.0;
+
+pub type SysEx = SysExBuffer<SYSEX_LEN>;
+
+/// Most devices that can be added from definitions at runtime
+pub const MAX_DEVICE_DEFINITIONS: usize = 32;
+
+/// Where the MIDI clock comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSource {
+    /// uncycle is the clock master and sends MIDI clock at its own `bpm`
+    Internal,
+    /// uncycle follows incoming MIDI clock and transport messages
+    External,
+}
 
 pub struct UncycleCore {
-    /// allocate space for all possible values
-    active_notes: [Option<u8>; N_NOTES],
-    /// allocate space for all possible values
-    last_cc: [Option<u8>; N_CC_NUMBERS],
+    /// allocate space for all possible values of every channel
+    active_notes: [[Option<u8>; N_NOTES]; N_CHANNELS],
+    /// allocate space for all possible values of every channel
+    last_cc: [[Option<u8>; N_CC_NUMBERS]; N_CHANNELS],
 
     pub device: Option<SupportedDevice>,
+    /// devices known besides the built-in ones
+    device_definitions: Vec<&'static DeviceDefinition, MAX_DEVICE_DEFINITIONS>,
     pub looper: Looper,
 
     /// time that passed since program start in µs
@@ -28,6 +59,26 @@ pub struct UncycleCore {
     bpm: f32,
     last_clock_time: u64, // in microseconds
     clock_pulse_count: u32,
+    /// clock pulses since program start, never reset so that the looper timeline keeps running
+    clock_ticks: u64,
+    /// last musical position handed to the looper in sub-ticks
+    song_position: u64,
+
+    clock_source: ClockSource,
+    ext_clock: TempoEstimator,
+    /// the external clock arrives on another port than the one of the device and is forwarded to it
+    clock_thru: bool,
+    clock_thru_q: Deque<u8, CLOCK_THRU_Q_LEN>,
+
+    rx_parser: MidiParser,
+    /// recognise 14 bit CC and (N)RPN instead of recording every CC on its own
+    high_res_params: bool,
+    param_decoders: [ParamDecoder; N_CHANNELS],
+    rx_sysex: SysEx,
+    received_sysex: Deque<SysEx, SYSEX_Q_LEN>,
+    tx_sysex_q: Deque<SysEx, SYSEX_Q_LEN>,
+    /// bytes of the last `midi_tx_callback`, kept here so that they aren't copied when they are handed out
+    tx_q: Vec<u8, TX_MIDI_Q_LEN>,
 
     pub kill_rx_conn: bool,
     pub kill_tx_conn: bool,
@@ -36,11 +87,12 @@ pub struct UncycleCore {
 impl UncycleCore {
     pub fn new(bpm: f32) -> Self {
         Self {
-            active_notes: [None; N_NOTES],
-            last_cc: [None; N_CC_NUMBERS],
+            active_notes: [[None; N_NOTES]; N_CHANNELS],
+            last_cc: [[None; N_CC_NUMBERS]; N_CHANNELS],
 
             device: None,
-            looper: Looper::new(bpm),
+            device_definitions: Vec::new(),
+            looper: Looper::new(),
 
             now: 0,
 
@@ -49,6 +101,21 @@ impl UncycleCore {
             bpm,
             last_clock_time: 0,
             clock_pulse_count: 0,
+            clock_ticks: 0,
+            song_position: 0,
+
+            clock_source: ClockSource::Internal,
+            ext_clock: TempoEstimator::new(),
+            clock_thru: false,
+            clock_thru_q: Deque::new(),
+
+            rx_parser: MidiParser::new(),
+            high_res_params: false,
+            param_decoders: [const { ParamDecoder::new() }; N_CHANNELS],
+            rx_sysex: SysEx::new(),
+            received_sysex: Deque::new(),
+            tx_sysex_q: Deque::new(),
+            tx_q: Vec::new(),
 
             kill_rx_conn: false,
             kill_tx_conn: false,
@@ -63,56 +130,159 @@ impl UncycleCore {
         self.device = None;
     }
 
+    /// Makes a device known that is described by a definition, e.g. one that has been loaded from a file
+    pub fn add_device_definition(
+        &mut self,
+        definition: &'static DeviceDefinition,
+    ) -> Result<(), DefinitionError> {
+        definition.validate()?;
+
+        if self.find_device(definition.id).is_some() {
+            return Err(DefinitionError::DuplicateId);
+        }
+
+        self.device_definitions
+            .push(definition)
+            .map_err(|_| DefinitionError::Full)
+    }
+
+    /// Built-in devices followed by the ones added from definitions, in their default state
+    pub fn available_devices(&self) -> impl Iterator<Item = SupportedDevice> + '_ {
+        SupportedDevice::iter().chain(
+            self.device_definitions
+                .iter()
+                .map(|definition| SupportedDevice::Defined(DefinedDevice::new(definition))),
+        )
+    }
+
+    /// Available device with this `id_to_str`
+    pub fn find_device(&self, id: &str) -> Option<SupportedDevice> {
+        self.available_devices()
+            .find(|device| device.id_to_str().eq(id))
+    }
+
+    /// Available device that answers an Identity Request like this
+    pub fn identify(&self, identity: &Identity) -> Option<SupportedDevice> {
+        self.available_devices().find(|device| {
+            device
+                .identity()
+                .is_some_and(|expected| expected.matches(identity))
+        })
+    }
+
+    pub fn set_clock_source(&mut self, source: ClockSource) {
+        if self.clock_source != source {
+            self.clock_source = source;
+            self.ext_clock.reset();
+            self.last_clock_time = self.now;
+        }
+    }
+
+    pub fn get_clock_source(&self) -> ClockSource {
+        self.clock_source
+    }
+
+    /// For a clock master on another port, see `clock_rx_callback`
+    ///
+    /// Its clock and transport are sent on to the device, whose own clock is ignored.
+    pub fn set_clock_thru(&mut self, enabled: bool) {
+        self.clock_thru = enabled;
+        self.clock_thru_q.clear();
+    }
+
+    pub fn get_clock_thru(&self) -> bool {
+        self.clock_thru
+    }
+
+    /// Off by default, since devices like the TR-8 use the LSB controllers of 14 bit CC for other parameters
+    pub fn set_high_res_params(&mut self, enabled: bool) {
+        self.high_res_params = enabled;
+    }
+
+    pub fn get_high_res_params(&self) -> bool {
+        self.high_res_params
+    }
+
     /// Call this function periodically in ms, but preferrably 100µs intervals to keep time on track
+    ///
+    /// Time never runs backwards, so calls from several threads with slightly stale timestamps are ignored.
     pub fn update_time(&mut self, now: u64) {
-        self.now = now;
+        self.now = self.now.max(now);
+    }
+
+    pub fn update_note(&mut self, channel: u8, note: u8, velocity: u8) {
+        self.active_notes[channel as usize][note as usize] = Some(velocity);
     }
 
-    pub fn update_note(&mut self, note: u8, velocity: u8) {
-        self.active_notes[note as usize] = Some(velocity);
+    pub fn update_cc(&mut self, channel: u8, cc_num: u8, cc_val: u8) {
+        self.last_cc[channel as usize][cc_num as usize] = Some(cc_val);
     }
 
-    pub fn update_cc(&mut self, cc_num: u8, cc_val: u8) {
-        self.last_cc[cc_num as usize] = Some(cc_val);
+    pub fn remove_note(&mut self, channel: u8, note: u8) {
+        self.active_notes[channel as usize][note as usize] = None;
     }
 
-    pub fn remove_note(&mut self, note: u8) {
-        self.active_notes[note as usize] = None;
+    pub fn find_active_note(&mut self, channel: u8, note: u8) -> bool {
+        self.active_notes[channel as usize][note as usize].is_some()
     }
 
-    pub fn find_active_note(&mut self, note: u8) -> bool {
-        self.active_notes[note as usize].is_some()
+    /// Last value received, before that the default of the device parameter on this channel or 0
+    pub fn get_cc_val_of(&mut self, channel: u8, cc_num: u8) -> u8 {
+        self.last_cc[channel as usize][cc_num as usize].unwrap_or_else(|| {
+            self.device
+                .as_ref()
+                .filter(|device| device.tx_channel() == channel)
+                .and_then(|device| device.param(cc_num))
+                .map_or(0, |param| param.default)
+        })
     }
 
-    pub fn get_cc_val_of(&mut self, cc_num: u8) -> u8 {
-        self.last_cc[cc_num as usize].unwrap_or(0)
+    /// Last value of every CC number on `channel`, `None` if nothing has been received or played back yet
+    pub fn last_cc_of(&self, channel: u8) -> &[Option<u8>; N_CC_NUMBERS] {
+        &self.last_cc[channel as usize]
     }
 
+    /// Has no effect while following an external clock
     pub fn increase_bpm_by(&mut self, amount: f32) {
+        if self.clock_source == ClockSource::External {
+            return;
+        }
+
         self.bpm += amount;
 
-        if self.bpm >= 200.0 {
-            self.bpm = 200.0;
+        if self.bpm >= MAX_BPM {
+            self.bpm = MAX_BPM;
         }
-
-        self.looper.update_loop_len(self.bpm);
     }
 
+    /// Any number of steps in between `LOOPER_MIN_LEN` and `LOOPER_MAX_LEN`, recorded content is adapted
     pub fn set_loop_step_len(&mut self, n_steps: u16) {
-        self.looper.set_loop_steps(n_steps);
+        let n_steps = n_steps.clamp(LOOPER_MIN_LEN, LOOPER_MAX_LEN);
+
+        if n_steps != self.looper.loop_steps {
+            self.looper.set_loop_steps(n_steps);
+        }
     }
 
+    /// Has no effect while following an external clock
     pub fn decrease_bpm_by(&mut self, amount: f32) {
+        if self.clock_source == ClockSource::External {
+            return;
+        }
+
         self.bpm -= amount;
 
-        if self.bpm <= 40.0 {
-            self.bpm = 40.0;
+        if self.bpm <= MIN_BPM {
+            self.bpm = MIN_BPM;
         }
-
-        self.looper.update_loop_len(self.bpm);
     }
 
+    /// Has no effect while following an external clock, since the clock master owns the transport
     pub fn start_stop_sequence(&mut self) {
+        if self.clock_source == ClockSource::External {
+            return;
+        }
+
         if let Some(device) = &self.device {
             if device.is_running() {
                 self.stop_flag = true;
@@ -141,61 +311,511 @@ impl UncycleCore {
     pub fn start_recording(&mut self) {
         if let Some(device) = &self.device {
             if device.is_running() {
-                self.looper.start_recording(self.now);
+                self.looper.start_recording(self.last_pulse_position());
             }
         }
     }
 
+    /// Song position of the most recent MIDI clock pulse, so that loops and their quantisation grid line up with
+    /// the clock
+    fn last_pulse_position(&self) -> u64 {
+        self.clock_ticks * SUBTICKS_PER_TICK as u64
+    }
+
+    /// Musical time since program start in sub-ticks, which is what the looper runs on
+    ///
+    /// In between two pulses the position is interpolated from the tempo. It never passes the next pulse and never
+    /// runs backwards, so tempo changes only stretch or squeeze the loop.
+    fn song_position(&mut self) -> u64 {
+        let phase = match self.clock_source {
+            ClockSource::Internal => {
+                let elapsed = self.now.saturating_sub(self.last_clock_time) as f32;
+                (elapsed / self.clock_interval() as f32).min(1.0 - f32::EPSILON)
+            }
+            ClockSource::External => self.ext_clock.phase(self.now),
+        };
+
+        let position = self.last_pulse_position() + (phase * SUBTICKS_PER_TICK as f32) as u64;
+        self.song_position = self.song_position.max(position);
+
+        self.song_position
+    }
+
+    /// Time in between two internal clock pulses in µs
+    fn clock_interval(&self) -> u64 {
+        (60_000_000.0 / (self.bpm * PPQN as f32)) as u64
+    }
+
+    pub fn quantize_recording(&mut self) {
+        if self.device.is_some() {
+            self.looper.quantize_recording();
+        }
+    }
+
     pub fn delete_recording(&mut self) {
         if self.device.is_some() {
             self.looper.delete_recording();
         }
     }
 
-    pub fn half_loop_len(&mut self) {
-        if self.looper.loop_steps > LOOPER_MIN_LEN {
-            self.looper.set_loop_steps(self.looper.loop_steps / 2);
+    pub fn undo_recording(&mut self) {
+        if self.device.is_some() {
+            self.looper.undo();
         }
     }
 
-    pub fn double_loop_len(&mut self) {
-        if self.looper.loop_steps < LOOPER_MAX_LEN {
-            self.looper.set_loop_steps(self.looper.loop_steps * 2);
+    pub fn redo_recording(&mut self) {
+        if self.device.is_some() {
+            self.looper.redo();
+        }
+    }
+
+    pub fn clear_lane(&mut self, cc_num: u8) {
+        if self.device.is_some() {
+            self.looper.clear_lane(cc_num);
+        }
+    }
+
+    pub fn toggle_mute_lane(&mut self, cc_num: u8) {
+        if self.device.is_some() {
+            self.looper.toggle_mute_lane(cc_num);
         }
     }
 
-    fn handle_looper_playback(&mut self, tx_q: &mut Vec<u8, TX_MIDI_Q_LEN>) {
-        self.looper.handle_eol(self.now);
+    pub fn toggle_solo_lane(&mut self, cc_num: u8) {
+        if self.device.is_some() {
+            self.looper.toggle_solo_lane(cc_num);
+        }
+    }
 
-        for bytes in self.looper.play_back_recording(self.now) {
-            for byte in bytes {
-                tx_q.push(*byte).ok();
+    pub fn rerecord_lane(&mut self, cc_num: u8) {
+        if let Some(device) = &self.device {
+            if device.is_running() {
+                self.looper
+                    .rerecord_lane(self.last_pulse_position(), cc_num);
             }
+        }
+    }
 
-            self.last_cc[bytes[1] as usize] = Some(bytes[2]);
+    /// Writes one cycle of the loop as Standard MIDI File at the current tempo, on the channel the device listens on
+    pub fn export_smf(&self, format: SmfFormat, out: &mut dyn FnMut(&[u8])) {
+        let channel = self.device.as_ref().map_or(0, |device| device.rx_channel());
+        let events = self.looper.loop_events();
+
+        Smf {
+            format,
+            bpm: self.bpm,
+            channel,
+            loop_len: self.looper.loop_len(),
+            events: &events,
         }
+        .write(out);
     }
 
-    /// `now` is time elapsed since beginning of program start in microseconds
-    pub fn midi_rx_callback(&mut self, message: &[u8]) {
-        if let Some(in_type) = parse_midi_message(message) {
-            let bytes: MidiMsg = [message[0], message[1], message[2]];
-
-            match in_type {
-                MIDI_NOTE_ON => self.update_note(bytes[1], bytes[2]),
-                MIDI_NOTE_OFF => self.remove_note(bytes[1]),
-                MIDI_CONTORL_CHANGE => {
-                    self.update_cc(bytes[1], bytes[2]);
-                    self.looper.record_cc(self.now, &bytes);
+    /// Replaces the recording with the channel messages of a Standard MIDI File, the loop starts right away
+    ///
+    /// The loop gets as long as the file, rounded up to whole steps. Files longer than the longest loop are cut off.
+    pub fn import_smf(&mut self, bytes: &[u8]) -> Result<SmfImport, SmfError> {
+        let max_len = (LOOPER_MAX_LEN as u32 * PPQN / 4 * SUBTICKS_PER_TICK) as u64;
+
+        let mut events: Vec<(u32, LoopEvent), LOOP_EVENTS_LEN> = Vec::new();
+        let mut report = SmfImport::default();
+        let mut end = 0;
+        let is_note_off = |event: &LoopEvent| matches!(event, LoopEvent::NoteOff { .. });
+
+        read_smf(bytes, self.bpm, &mut |event| {
+            let time = match event {
+                SmfEvent::Message { time, .. }
+                | SmfEvent::SysEx { time }
+                | SmfEvent::EndOfTrack { time } => time,
+            };
+
+            if time >= max_len {
+                report.truncated = true;
+                end = max_len;
+                return;
+            }
+
+            end = end.max(time);
+
+            let SmfEvent::Message { message, .. } = event else {
+                if let SmfEvent::SysEx { .. } = event {
+                    report.n_unsupported += 1;
                 }
-                _ => {}
+                return;
             };
+
+            match LoopEvent::from_message(&message) {
+                Some(event) => {
+                    // a note is counted once, by its note on
+                    if events.push((time as u32, event)).is_err() && !is_note_off(&event) {
+                        report.n_dropped += 1;
+                    }
+                }
+                None => report.n_unsupported += 1,
+            }
+        })?;
+
+        // tracks follow each other in the file
+        events.sort_unstable_by_key(|(time, event)| (*time, !is_note_off(event)));
+
+        let step_len = (PPQN / 4 * SUBTICKS_PER_TICK) as u64;
+        let n_steps = end
+            .div_ceil(step_len)
+            .clamp(LOOPER_MIN_LEN as u64, LOOPER_MAX_LEN as u64) as u16;
+
+        // the previous recording is kept if the file has nothing to loop
+        let n_rejected = self
+            .looper
+            .load_recording(self.last_pulse_position(), n_steps, &events)
+            .ok_or(SmfError::Empty)?;
+
+        report.n_dropped += n_rejected;
+        report.n_events = events
+            .iter()
+            .filter(|(_, event)| !is_note_off(event))
+            .count()
+            - n_rejected;
+        report.loop_steps = n_steps;
+
+        Ok(report)
+    }
+
+    /// Writes tempo, clock, device and the complete looper state including undone passes
+    pub fn save_session(&self, out: &mut dyn FnMut(&[u8])) {
+        let mut w = SessionWriter::new(out);
+
+        w.f32(self.bpm);
+        w.u8(match self.clock_source {
+            ClockSource::Internal => 0,
+            ClockSource::External => 1,
+        });
+        w.bool(self.high_res_params);
+        match &self.device {
+            Some(device) => {
+                w.bytes(device.id_to_str().as_bytes());
+                w.u8(device.tx_channel());
+            }
+            None => {
+                w.bytes(&[]);
+                w.u8(0);
+            }
+        }
+
+        self.looper.write_session(&mut w);
+    }
+
+    /// Restores a session written by `save_session`, the loop starts right away
+    ///
+    /// Nothing changes if the session can't be read. A device that isn't available is left unset.
+    pub fn load_session(&mut self, bytes: &[u8]) -> Result<(), SessionError> {
+        let mut r = SessionReader::new(bytes)?;
+
+        let bpm = r.f32()?;
+        if !(MIN_BPM..=MAX_BPM).contains(&bpm) {
+            return Err(SessionError::Malformed);
+        }
+        let clock_source = match r.u8()? {
+            0 => ClockSource::Internal,
+            1 => ClockSource::External,
+            _ => return Err(SessionError::Malformed),
+        };
+        let high_res_params = r.bool()?;
+        let mut device = core::str::from_utf8(r.bytes()?)
+            .map_err(|_| SessionError::Malformed)
+            .map(|id| self.find_device(id))?;
+        // since version 2, only the generic device can change its channel
+        let channel = if r.version() >= 2 { r.u8()? } else { 0 };
+        if channel >= 16 {
+            return Err(SessionError::Malformed);
+        }
+        if let Some(SupportedDevice::Generic(generic)) = &mut device {
+            generic.set_channel(channel);
+        }
+
+        self.looper
+            .read_session(&mut r, self.last_pulse_position())?;
+
+        self.bpm = bpm;
+        self.set_clock_source(clock_source);
+        self.set_high_res_params(high_res_params);
+        match device {
+            Some(device) => self.set_device(device),
+            None => self.unset_device(),
+        }
+
+        Ok(())
+    }
+
+    pub fn half_loop_len(&mut self) {
+        self.set_loop_step_len(self.looper.loop_steps / 2);
+    }
+
+    pub fn double_loop_len(&mut self) {
+        self.set_loop_step_len(self.looper.loop_steps.saturating_mul(2));
+    }
+
+    pub fn increase_loop_len(&mut self) {
+        self.set_loop_step_len(self.looper.loop_steps.saturating_add(1));
+    }
+
+    pub fn decrease_loop_len(&mut self) {
+        self.set_loop_step_len(self.looper.loop_steps.saturating_sub(1));
+    }
+
+    fn handle_looper_playback(&mut self) {
+        let position = self.song_position();
+        self.looper.handle_eol(position);
+
+        // the device sees the recording on the channel it listens on and reports its new state on the one it sends on
+        let (rx_channel, tx_channel) = self
+            .device
+            .as_ref()
+            .map_or((0, 0), |device| (device.rx_channel(), device.tx_channel()));
+
+        for event in self.looper.play_back_recording(position) {
+            for message in event.to_messages(rx_channel) {
+                self.tx_q.extend_from_slice(&message.to_bytes()).ok();
+
+                if let MidiMessage::ControlChange { control, value, .. } = message {
+                    self.last_cc[tx_channel as usize][control as usize] = Some(value);
+                }
+            }
+        }
+    }
+
+    /// Handles incoming clock and transport bytes, but only if following an external clock
+    fn handle_ext_clock(&mut self, status: u8) {
+        if self.clock_source != ClockSource::External {
+            return;
+        }
+
+        match status {
+            MIDI_CLOCK => {
+                self.ext_clock.pulse(self.now);
+                self.clock_pulse_count = self.clock_pulse_count.wrapping_add(1);
+                self.clock_ticks += 1;
+
+                if let Some(bpm) = self.ext_clock.bpm() {
+                    self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+                }
+            }
+
+            MIDI_START => {
+                self.clock_pulse_count = 0;
+                self.ext_clock.reset();
+
+                // the next pulse is the first step of the clock master
+                self.looper
+                    .restart((self.clock_ticks + 1) * SUBTICKS_PER_TICK as u64);
+
+                if let Some(device) = &mut self.device {
+                    device.run();
+                }
+            }
+
+            MIDI_CONTINUE => {
+                if let Some(device) = &mut self.device {
+                    device.run();
+                }
+            }
+
+            MIDI_STOP => {
+                if let Some(device) = &mut self.device {
+                    device.stop();
+                }
+
+                self.looper.release_notes();
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Takes incoming bytes in any chunks, from complete messages to single bytes of a serial connection
+    pub fn midi_rx_callback(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            for event in self.rx_parser.push(*byte) {
+                match event {
+                    MidiEvent::Message(message) => self.handle_midi_message(message),
+                    _ => {
+                        if self.rx_sysex.push(&event) {
+                            self.handle_sysex();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Must be called for everything received from a clock master on another port than the device
+    ///
+    /// Only clock and transport are handled, they are forwarded to the device if clock thru is enabled.
+    pub fn clock_rx_callback(&mut self, bytes: &[u8]) {
+        // real time messages are single bytes that may show up in between the bytes of others
+        for status in bytes.iter().copied().filter(|byte| *byte >= MIDI_CLOCK) {
+            self.handle_ext_clock(status);
+
+            let is_clock = matches!(status, MIDI_CLOCK | MIDI_START | MIDI_CONTINUE | MIDI_STOP);
+
+            if self.clock_thru && is_clock && self.clock_source == ClockSource::External {
+                if self.clock_thru_q.is_full() {
+                    self.clock_thru_q.pop_front();
+                }
+
+                self.clock_thru_q.push_back(status).ok();
+            }
+        }
+    }
+
+    /// Keeps a received SysEx until it is picked up, the oldest one is dropped if nobody does
+    fn handle_sysex(&mut self) {
+        if self.received_sysex.is_full() {
+            self.received_sysex.pop_front();
+        }
+
+        self.received_sysex.push_back(self.rx_sysex.clone()).ok();
+    }
+
+    /// Received SysEx messages in the order they arrived
+    pub fn pop_received_sysex(&mut self) -> Option<SysEx> {
+        self.received_sysex.pop_front()
+    }
+
+    /// Queues a SysEx message, `payload` excludes `0xF0` and `0xF7`
+    ///
+    /// Returns false if the payload is too long, contains status bytes or too many messages are waiting already.
+    pub fn send_sysex(&mut self, payload: &[u8]) -> bool {
+        match SysEx::from_payload(payload) {
+            Some(sysex) => self.tx_sysex_q.push_back(sysex).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Sends one queued SysEx per call, so that the clock keeps running in between long messages
+    fn handle_sysex_output(&mut self) {
+        if let Some(sysex) = self.tx_sysex_q.front() {
+            if sysex.write_to(&mut self.tx_q) {
+                self.tx_sysex_q.pop_front();
+            }
+        }
+    }
+
+    /// Whether messages on `channel` come from the selected device
+    fn is_device_channel(&self, channel: u8) -> bool {
+        self.device
+            .as_ref()
+            .is_some_and(|device| device.tx_channel() == channel)
+    }
+
+    fn handle_midi_message(&mut self, message: MidiMessage) {
+        if message.is_real_time() {
+            // the clock master is on another port
+            if !self.clock_thru {
+                self.handle_ext_clock(message.status());
+            }
+            return;
+        }
+
+        match message {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } if !message.is_note_off() => {
+                self.update_note(channel, note, velocity);
+
+                if self.is_device_channel(channel) {
+                    let position = self.song_position();
+                    self.looper.record_note_on(position, note, velocity);
+                }
+            }
+            MidiMessage::NoteOn { channel, note, .. }
+            | MidiMessage::NoteOff { channel, note, .. } => {
+                self.remove_note(channel, note);
+
+                if self.is_device_channel(channel) {
+                    let position = self.song_position();
+                    self.looper.record_note_off(position, note);
+                }
+            }
+            MidiMessage::ControlChange {
+                channel,
+                control,
+                value,
+            } => {
+                self.update_cc(channel, control, value);
+
+                // other instruments may share the bus
+                if self.is_device_channel(channel) {
+                    let position = self.song_position();
+
+                    if !self.high_res_params {
+                        self.looper
+                            .record_cc(position, &[MIDI_CONTORL_CHANGE | channel, control, value]);
+                        return;
+                    }
+
+                    match self.param_decoders[channel as usize].decode(control, value) {
+                        Decoded::Change(change) => self.looper.record_param(position, change),
+                        Decoded::Refine(change) => self.looper.refine_param(change),
+                        Decoded::Incomplete => {}
+                    }
+                }
+            }
+            MidiMessage::PitchBend { channel, value } => self.record_gesture(
+                channel,
+                ParamChange {
+                    param: Param::PitchBend,
+                    value,
+                },
+            ),
+            MidiMessage::ChannelAftertouch { channel, pressure } => self.record_gesture(
+                channel,
+                ParamChange {
+                    param: Param::ChannelPressure,
+                    value: pressure as u16,
+                },
+            ),
+            MidiMessage::PolyAftertouch {
+                channel,
+                note,
+                pressure,
+            } => self.record_gesture(
+                channel,
+                ParamChange {
+                    param: Param::PolyPressure(note),
+                    value: pressure as u16,
+                },
+            ),
+            _ => {}
+        };
+    }
+
+    /// Pitch bend and aftertouch are recorded like any other parameter of the device
+    fn record_gesture(&mut self, channel: u8, change: ParamChange) {
+        if self.is_device_channel(channel) {
+            let position = self.song_position();
+            self.looper.record_param(position, change);
         }
     }
 
     /// `now` is time elapsed since beginning of program start in microseconds
-    pub fn midi_tx_callback(&mut self) -> Vec<u8, TX_MIDI_Q_LEN> {
-        let mut tx_q = Vec::new();
+    ///
+    /// Returns the bytes to send, they are valid until the next call.
+    pub fn midi_tx_callback(&mut self) -> &[u8] {
+        self.tx_q.clear();
+
+        // the external clock master takes care of transport and clock
+        if self.clock_source == ClockSource::External {
+            while let Some(status) = self.clock_thru_q.pop_front() {
+                self.tx_q.push(status).ok();
+            }
+
+            self.handle_looper_playback();
+            self.handle_sysex_output();
+            return &self.tx_q;
+        }
 
         if let Some(device) = &mut self.device {
             // MIDI Start
@@ -203,7 +823,7 @@ impl UncycleCore {
                 self.start_flag = false;
                 device.run();
 
-                tx_q.push(MIDI_START).ok();
+                self.tx_q.push(MIDI_START).ok();
                 self.clock_pulse_count = 0;
             }
 
@@ -211,23 +831,24 @@ impl UncycleCore {
             if self.stop_flag {
                 self.stop_flag = false;
                 device.stop();
+                self.looper.release_notes();
 
-                tx_q.push(MIDI_STOP).ok();
+                self.tx_q.push(MIDI_STOP).ok();
             }
         }
 
         // MIDI Clock
-        let interval = (60_000_000.0 / (self.bpm * 24.0)) as u64;
-
-        if self.now - self.last_clock_time >= interval {
+        if self.now - self.last_clock_time >= self.clock_interval() {
             self.last_clock_time = self.now;
             self.clock_pulse_count = self.clock_pulse_count.wrapping_add(1);
+            self.clock_ticks += 1;
 
-            tx_q.push(MIDI_CLOCK).ok();
+            self.tx_q.push(MIDI_CLOCK).ok();
         }
 
-        self.handle_looper_playback(&mut tx_q);
+        self.handle_looper_playback();
+        self.handle_sysex_output();
 
-        tx_q
+        &self.tx_q
     }
 }
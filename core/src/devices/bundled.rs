@@ -0,0 +1,6 @@
+//! Definitions of the devices in `core/definitions`, turned into const tables by `build.rs`
+
+use super::{DeviceDefinition, NoteDefinition, ParamDefinition};
+use crate::midi::{IdentityMatch, ManufacturerId};
+
+include!(concat!(env!("OUT_DIR"), "/bundled.rs"));
@@ -0,0 +1,218 @@
+use super::DeviceInterface;
+use crate::midi::{IdentityMatch, N_CC_NUMBERS, N_CHANNELS, N_NOTES};
+use core::fmt;
+use heapless::String;
+
+/// Longest id, name, manufacturer and port name of a definition, so they fit the strings of `DeviceInterface`
+pub const DEFINITION_STR_LEN: usize = 64;
+
+/// A note the device reacts to, e.g. an instrument of a drum machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoteDefinition {
+    pub note: u8,
+    pub name: &'static str,
+}
+
+/// A parameter of the device that is sent and received as CC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamDefinition {
+    pub cc: u8,
+    pub name: &'static str,
+    /// instrument or section the parameter belongs to, empty if there is none
+    pub group: &'static str,
+    /// lowest value the device makes use of
+    pub min: u8,
+    /// highest value the device makes use of
+    pub max: u8,
+    /// value the parameter starts with
+    pub default: u8,
+}
+
+impl ParamDefinition {
+    /// The CC number and every value fit into a MIDI message and the range isn't empty
+    pub const fn is_valid(&self) -> bool {
+        (self.cc as usize) < N_CC_NUMBERS
+            && self.min < self.max
+            && self.max <= 127
+            && self.min <= self.default
+            && self.default <= self.max
+    }
+}
+
+/// Everything uncycle needs to know about a device
+///
+/// Definitions can be const tables compiled into the firmware or be loaded from files and leaked by a host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceDefinition {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub manufacturer: &'static str,
+    /// part of the name of the MIDI ports the device shows up with
+    pub port_name: &'static str,
+    /// zero based MIDI channel the device listens and sends on
+    pub channel: u8,
+    pub notes: &'static [NoteDefinition],
+    pub params: &'static [ParamDefinition],
+    /// recognises the device by its answer to an Identity Request
+    pub identity: Option<IdentityMatch>,
+}
+
+/// Why a definition can't be used
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefinitionError {
+    /// id, name, manufacturer or port name is empty or longer than `DEFINITION_STR_LEN`
+    InvalidText,
+    /// the channel is above 15
+    InvalidChannel,
+    /// a note number is above 127
+    InvalidNote(u8),
+    /// a CC number is above 127, its range is empty or doesn't contain the default
+    InvalidParam(u8),
+    /// the identity doesn't fit into an Identity Reply
+    InvalidIdentity,
+    /// another device has the same id
+    DuplicateId,
+    /// no room for more definitions
+    Full,
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::InvalidText => write!(
+                f,
+                "id, name, manufacturer and port name need 1 to {} characters",
+                DEFINITION_STR_LEN
+            ),
+            DefinitionError::InvalidChannel => write!(f, "channel has to be 1 to 16"),
+            DefinitionError::InvalidNote(note) => write!(f, "note {} is not a MIDI note", note),
+            DefinitionError::InvalidParam(cc) => {
+                write!(
+                    f,
+                    "CC {} is not a MIDI CC or its range or default is invalid",
+                    cc
+                )
+            }
+            DefinitionError::InvalidIdentity => {
+                write!(f, "identity has to be made of 7 bit bytes")
+            }
+            DefinitionError::DuplicateId => write!(f, "another device has the same id"),
+            DefinitionError::Full => write!(f, "too many device definitions"),
+        }
+    }
+}
+
+impl DeviceDefinition {
+    /// Checks that every value fits into MIDI messages and the strings of `DeviceInterface`
+    ///
+    /// This is const, so that `build.rs` can have the compiler check the bundled definitions the same way.
+    pub const fn validate(&self) -> Result<(), DefinitionError> {
+        let texts = [self.id, self.name, self.manufacturer, self.port_name];
+
+        let mut i = 0;
+        while i < texts.len() {
+            if texts[i].is_empty() || texts[i].len() > DEFINITION_STR_LEN {
+                return Err(DefinitionError::InvalidText);
+            }
+            i += 1;
+        }
+
+        if self.channel as usize >= N_CHANNELS {
+            return Err(DefinitionError::InvalidChannel);
+        }
+
+        i = 0;
+        while i < self.notes.len() {
+            if self.notes[i].note as usize >= N_NOTES {
+                return Err(DefinitionError::InvalidNote(self.notes[i].note));
+            }
+            i += 1;
+        }
+
+        i = 0;
+        while i < self.params.len() {
+            if !self.params[i].is_valid() {
+                return Err(DefinitionError::InvalidParam(self.params[i].cc));
+            }
+            i += 1;
+        }
+
+        if let Some(identity) = &self.identity {
+            if !identity.is_valid() {
+                return Err(DefinitionError::InvalidIdentity);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A device that is fully described by its definition
+#[derive(Clone, Copy, Debug)]
+pub struct DefinedDevice {
+    definition: &'static DeviceDefinition,
+    running: bool,
+}
+
+impl DefinedDevice {
+    pub fn new(definition: &'static DeviceDefinition) -> Self {
+        Self {
+            definition,
+            running: false,
+        }
+    }
+
+    pub fn definition(&self) -> &'static DeviceDefinition {
+        self.definition
+    }
+}
+
+impl DeviceInterface for DefinedDevice {
+    fn run(&mut self) {
+        self.running = true;
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn rx_channel(&self) -> u8 {
+        self.definition.channel
+    }
+
+    fn tx_channel(&self) -> u8 {
+        self.definition.channel
+    }
+
+    fn name_to_str(&self) -> String<64> {
+        String::try_from(self.definition.name).unwrap_or_default()
+    }
+
+    fn manufacturer_to_str(&self) -> String<64> {
+        String::try_from(self.definition.manufacturer).unwrap_or_default()
+    }
+
+    fn id_to_str(&self) -> String<64> {
+        String::try_from(self.definition.id).unwrap_or_default()
+    }
+
+    fn port_name_to_str(&self) -> String<64> {
+        String::try_from(self.definition.port_name).unwrap_or_default()
+    }
+
+    fn params(&self) -> &'static [ParamDefinition] {
+        self.definition.params
+    }
+
+    fn notes(&self) -> &'static [NoteDefinition] {
+        self.definition.notes
+    }
+
+    fn identity(&self) -> Option<IdentityMatch> {
+        self.definition.identity
+    }
+}
@@ -0,0 +1,101 @@
+use super::{DeviceInterface, NoteDefinition, ParamDefinition};
+use crate::midi::{IdentityMatch, N_CC_NUMBERS};
+use heapless::String;
+
+/// Any device that sends and receives CC, every controller of its channel is a lane
+///
+/// It has no port name, so the host has to let the user pick its ports.
+#[derive(Clone, Debug, Default)]
+pub struct GenericDevice {
+    channel: u8,
+    running: bool,
+}
+
+impl GenericDevice {
+    /// `channel` is zero based
+    pub fn new(channel: u8) -> Self {
+        Self {
+            channel,
+            running: false,
+        }
+    }
+
+    /// Channels above 15 are ignored
+    pub fn set_channel(&mut self, channel: u8) {
+        if channel < 16 {
+            self.channel = channel;
+        }
+    }
+}
+
+impl DeviceInterface for GenericDevice {
+    fn run(&mut self) {
+        self.running = true;
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn rx_channel(&self) -> u8 {
+        self.channel
+    }
+
+    fn tx_channel(&self) -> u8 {
+        self.channel
+    }
+
+    fn name_to_str(&self) -> String<64> {
+        String::try_from("Any CC Device").unwrap()
+    }
+
+    fn manufacturer_to_str(&self) -> String<64> {
+        String::try_from("Generic").unwrap()
+    }
+
+    fn id_to_str(&self) -> String<64> {
+        String::try_from("generic").unwrap()
+    }
+
+    fn port_name_to_str(&self) -> String<64> {
+        String::new()
+    }
+
+    fn params(&self) -> &'static [ParamDefinition] {
+        &GENERIC_PARAMS
+    }
+
+    fn notes(&self) -> &'static [NoteDefinition] {
+        &[]
+    }
+
+    fn identity(&self) -> Option<IdentityMatch> {
+        None
+    }
+}
+
+/// Every CC number with its full range, starting at 0
+pub const GENERIC_PARAMS: [ParamDefinition; N_CC_NUMBERS] = generic_params();
+
+const fn generic_params() -> [ParamDefinition; N_CC_NUMBERS] {
+    let mut params = [ParamDefinition {
+        cc: 0,
+        name: "CC",
+        group: "",
+        min: 0,
+        max: 127,
+        default: 0,
+    }; N_CC_NUMBERS];
+    let mut i = 0;
+
+    while i < N_CC_NUMBERS {
+        params[i].cc = i as u8;
+        i += 1;
+    }
+
+    params
+}
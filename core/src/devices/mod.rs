@@ -1,14 +1,23 @@
+pub mod bundled;
+pub mod definition;
+pub mod generic;
 pub mod tr8;
+pub mod tr8s;
 
+pub use definition::{
+    DefinedDevice, DefinitionError, DeviceDefinition, NoteDefinition, ParamDefinition,
+};
+pub use generic::GenericDevice;
 pub use tr8::TR8;
+pub use tr8s::TR8S;
 
 #[cfg(feature = "std")]
-use std::{string::ToString, str::FromStr};
+use std::{fmt::Display, str::FromStr};
 
 #[cfg(feature = "std")]
-impl ToString for SupportedDevice {
-    fn to_string(&self) -> std::string::String {
-        self.id_to_str().to_string()
+impl Display for SupportedDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.id_to_str())
     }
 }
 
@@ -16,13 +25,7 @@ impl ToString for SupportedDevice {
 impl FromStr for SupportedDevice {
     type Err = std::fmt::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        for device in SupportedDevice::iter() {
-            if device.id_to_str().eq(s) {
-                return Ok(device);
-            }
-        }
-        
-        Err(std::fmt::Error)
+        SupportedDevice::from_id(s).ok_or(std::fmt::Error)
     }
 }
 
@@ -30,12 +33,25 @@ use strum::EnumIter;
 use strum::IntoEnumIterator;
 use enum_dispatch::enum_dispatch;
 use heapless::String;
+use crate::midi::IdentityMatch;
 
 
 #[enum_dispatch(DeviceInterface)]
 #[derive(Clone, Debug, EnumIter)]
 pub enum SupportedDevice {
     TR8(TR8),
+    TR8S(TR8S),
+    Generic(GenericDevice),
+    /// not iterated, since definitions are only known at runtime
+    #[strum(disabled)]
+    Defined(DefinedDevice),
+}
+
+impl SupportedDevice {
+    /// Built-in device with this `id_to_str`, in its default state
+    pub fn from_id(id: &str) -> Option<Self> {
+        SupportedDevice::iter().find(|device| device.id_to_str().eq(id))
+    }
 }
 
 
@@ -45,7 +61,43 @@ pub trait DeviceInterface {
     fn stop(&mut self);
     fn is_running(&self) -> bool;
 
+    /// zero based MIDI channel the device listens on, recorded messages are played back on it
+    fn rx_channel(&self) -> u8;
+    /// zero based MIDI channel the device sends on, only messages on it are recorded
+    fn tx_channel(&self) -> u8;
+
     fn name_to_str(&self) -> String<64>;
     fn manufacturer_to_str(&self) -> String<64>;
     fn id_to_str(&self) -> String<64>;
+    /// part of the name of the MIDI ports the device shows up with, see `is_device_port`
+    fn port_name_to_str(&self) -> String<64>;
+
+    /// CC parameters that can be controlled and looped, grouped by instrument or section
+    fn params(&self) -> &'static [ParamDefinition];
+    /// notes the device reacts to
+    fn notes(&self) -> &'static [NoteDefinition];
+    /// how the device answers an Identity Request, if it does
+    fn identity(&self) -> Option<IdentityMatch>;
+
+    /// Parameter controlled by this CC number
+    fn param(&self, cc: u8) -> Option<&'static ParamDefinition> {
+        self.params().iter().find(|param| param.cc == cc)
+    }
+}
+
+/// Whether a MIDI port belongs to a device with this port name
+///
+/// The port name has to show up as a whole word, so the ports of a TR-8S don't count as ports of a TR-8.
+pub fn is_device_port(port_name: &str, device_port_name: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    !device_port_name.is_empty()
+        && port_name
+            .match_indices(device_port_name)
+            .any(|(i, _)| {
+                let before = port_name[..i].chars().next_back();
+                let after = port_name[i + device_port_name.len()..].chars().next();
+
+                !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+            })
 }
\ No newline at end of file
@@ -1,17 +1,13 @@
-use super::DeviceInterface;
+use super::bundled::{ROLAND_TR8, ROLAND_TR8_NOTES, ROLAND_TR8_PARAMS};
+use super::{DeviceInterface, NoteDefinition, ParamDefinition};
+use crate::midi::IdentityMatch;
 use heapless::String;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct TR8 {
     running: bool,
 }
 
-impl Default for TR8 {
-    fn default() -> Self {
-        Self { running: false }
-    }
-}
-
 impl DeviceInterface for TR8 {
     fn run(&mut self) {
         self.running = true;
@@ -25,19 +21,49 @@ impl DeviceInterface for TR8 {
         self.running
     }
 
+    fn rx_channel(&self) -> u8 {
+        TR_8_DEFAULT_CHANNEL
+    }
+
+    fn tx_channel(&self) -> u8 {
+        TR_8_DEFAULT_CHANNEL
+    }
+
     fn name_to_str(&self) -> String<64> {
-        String::try_from("TR-8").unwrap()
+        String::try_from(ROLAND_TR8.name).unwrap()
     }
 
     fn manufacturer_to_str(&self) -> String<64> {
-        String::try_from("Roland").unwrap()
+        String::try_from(ROLAND_TR8.manufacturer).unwrap()
     }
 
     fn id_to_str(&self) -> String<64> {
-        String::try_from("roland-tr8").unwrap()
+        String::try_from(ROLAND_TR8.id).unwrap()
+    }
+
+    fn port_name_to_str(&self) -> String<64> {
+        String::try_from(ROLAND_TR8.port_name).unwrap()
+    }
+
+    fn params(&self) -> &'static [ParamDefinition] {
+        &TR_8_PARAMS
+    }
+
+    fn notes(&self) -> &'static [NoteDefinition] {
+        &TR_8_NOTE_MAP
+    }
+
+    fn identity(&self) -> Option<IdentityMatch> {
+        Some(TR_8_IDENTITY)
     }
 }
 
+/// channel 10, which is the factory setting for both directions
+pub const TR_8_DEFAULT_CHANNEL: u8 = ROLAND_TR8.channel;
+
+/// Identity Reply of the TR-8, regardless of the model number
+pub const TR_8_IDENTITY: IdentityMatch = ROLAND_TR8.identity.unwrap();
+
 /// (number: u8, name: &'static str)
 type RichMidiCC = (u8, &'static str);
 
@@ -45,74 +71,95 @@ pub const TR_8_INTRUMENTS: usize = 11;
 pub const TR_8_STEPS: usize = TR_8_INTRUMENTS + 5;
 pub const TR_8_PARAM_ELEMS: usize = TR_8_INTRUMENTS + 2;
 
+pub const TR_8_N_PARAMS: usize = 2 * TR_8_PARAM_ELEMS + TR_8_INTRUMENTS;
+
+/// Knobs row by row followed by the faders, as listed in `definitions/roland-tr8.toml`
+pub const TR_8_PARAMS: [ParamDefinition; TR_8_N_PARAMS] = ROLAND_TR8_PARAMS;
+
+/// Every note of the TR-8 with its instrument, BD2, SD2, CB and TB need the 7x7 update
+pub const TR_8_NOTE_MAP: [NoteDefinition; TR_8_STEPS - 1] = ROLAND_TR8_NOTES;
+
 // relevant notes to check
 
-pub const TR_8_NOTES: [u8; TR_8_STEPS] = [
-    36, // BD
-    38, // SD
-    43, // LT
-    47, // MT
-    50, // HT
-    37, // RS
-    39, // HC
-    42, // CH
-    46, // OH
-    49, // CC
-    51, // RC
-    //
-    // | these might be unsupported if 727 update has not been flashed on device
-    // v
-    35, // BD2
-    40, // SD2
-    56, // CB
-    54, // TB
-    0,  // unused
-];
+/// notes of `TR_8_NOTE_MAP`, the last step is unused
+pub const TR_8_NOTES: [u8; TR_8_STEPS] = tr8_notes();
+
+/// instrument of each of `TR_8_NOTES`, without the unused last one
+pub const TR_8_NOTE_NAMES: [&str; TR_8_STEPS - 1] = tr8_note_names();
 
 // relevant CC numbers to check
 
-pub const TR_8_CC_FADER: [RichMidiCC; TR_8_INTRUMENTS] = [
-    (24, "BD"),
-    (29, "SD"),
-    (48, "LT"),
-    (51, "MT"),
-    (54, "HT"),
-    (57, "RS"),
-    (60, "HC"),
-    (63, "CH"),
-    (82, "OH"),
-    (85, "CC"),
-    (88, "RC"),
-];
-
-pub const TR_8_CC_PARAMS_1ST_ROW: [RichMidiCC; TR_8_PARAM_ELEMS] = [
-    (20, "TUNE"),   // BD
-    (21, "ATTACK"), // BD
-    (25, "TUNE"),   // SD
-    (26, "SNAPPY"), // SD
-    (46, "TUNE"),   // LT
-    (49, "TUNE"),   // MT
-    (52, "TUNE"),   // HT
-    (55, "TUNE"),   // RS
-    (58, "TUNE"),   // HC
-    (61, "TUNE"),   // CH
-    (80, "TUNE"),   // OH
-    (83, "TUNE"),   // CC
-    (86, "TUNE"),   // RC
-];
-
-pub const TR_8_CC_PARAMS_2ND_ROW: [RichMidiCC; TR_8_PARAM_ELEMS] = [
-    (22, "COMP"),  // BD
-    (23, "DECAY"), // BD
-    (27, "COMP"),  // SD
-    (28, "DECAY"), // SD
-    (47, "DECAY"), // LT
-    (50, "DECAY"), // MT
-    (53, "DECAY"), // HT
-    (56, "DECAY"), // RS
-    (59, "DECAY"), // HC
-    (62, "DECAY"), // CH
-    (81, "DECAY"), // OH
-    (84, "DECAY"), // CC
-    (87, "DECAY"), // RC
-];
+pub const TR_8_CC_PARAMS_1ST_ROW: [RichMidiCC; TR_8_PARAM_ELEMS] = tr8_knobs(0);
+
+pub const TR_8_CC_PARAMS_2ND_ROW: [RichMidiCC; TR_8_PARAM_ELEMS] = tr8_knobs(TR_8_PARAM_ELEMS);
+
+/// level fader of each instrument
+pub const TR_8_CC_FADER: [RichMidiCC; TR_8_INTRUMENTS] = tr8_faders();
+
+/// instrument of each element in the parameter rows
+pub const TR_8_PARAM_INSTRUMENTS: [&str; TR_8_PARAM_ELEMS] = tr8_param_instruments();
+
+const fn tr8_notes() -> [u8; TR_8_STEPS] {
+    let mut notes = [0; TR_8_STEPS];
+    let mut i = 0;
+
+    while i < TR_8_STEPS - 1 {
+        notes[i] = TR_8_NOTE_MAP[i].note;
+        i += 1;
+    }
+
+    notes
+}
+
+const fn tr8_note_names() -> [&'static str; TR_8_STEPS - 1] {
+    let mut names = [""; TR_8_STEPS - 1];
+    let mut i = 0;
+
+    while i < TR_8_STEPS - 1 {
+        names[i] = TR_8_NOTE_MAP[i].name;
+        i += 1;
+    }
+
+    names
+}
+
+/// one row of knobs, starting at `offset` in `TR_8_PARAMS`
+const fn tr8_knobs(offset: usize) -> [RichMidiCC; TR_8_PARAM_ELEMS] {
+    let mut knobs = [(0, ""); TR_8_PARAM_ELEMS];
+    let mut i = 0;
+
+    while i < TR_8_PARAM_ELEMS {
+        let param = TR_8_PARAMS[offset + i];
+
+        knobs[i] = (param.cc, param.name);
+        i += 1;
+    }
+
+    knobs
+}
+
+const fn tr8_faders() -> [RichMidiCC; TR_8_INTRUMENTS] {
+    let mut faders = [(0, ""); TR_8_INTRUMENTS];
+    let mut i = 0;
+
+    while i < TR_8_INTRUMENTS {
+        let param = TR_8_PARAMS[2 * TR_8_PARAM_ELEMS + i];
+
+        faders[i] = (param.cc, param.group);
+        i += 1;
+    }
+
+    faders
+}
+
+const fn tr8_param_instruments() -> [&'static str; TR_8_PARAM_ELEMS] {
+    let mut instruments = [""; TR_8_PARAM_ELEMS];
+    let mut i = 0;
+
+    while i < TR_8_PARAM_ELEMS {
+        instruments[i] = TR_8_PARAMS[i].group;
+        i += 1;
+    }
+
+    instruments
+}
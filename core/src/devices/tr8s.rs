@@ -0,0 +1,154 @@
+use super::bundled::{ROLAND_TR8S, ROLAND_TR8S_NOTES, ROLAND_TR8S_PARAMS};
+use super::{DeviceInterface, NoteDefinition, ParamDefinition};
+use crate::midi::IdentityMatch;
+use heapless::String;
+
+#[derive(Clone, Debug, Default)]
+pub struct TR8S {
+    running: bool,
+}
+
+impl DeviceInterface for TR8S {
+    fn run(&mut self) {
+        self.running = true;
+    }
+
+    fn stop(&mut self) {
+        self.running = false;
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn rx_channel(&self) -> u8 {
+        TR_8S_DEFAULT_CHANNEL
+    }
+
+    fn tx_channel(&self) -> u8 {
+        TR_8S_DEFAULT_CHANNEL
+    }
+
+    fn name_to_str(&self) -> String<64> {
+        String::try_from(ROLAND_TR8S.name).unwrap()
+    }
+
+    fn manufacturer_to_str(&self) -> String<64> {
+        String::try_from(ROLAND_TR8S.manufacturer).unwrap()
+    }
+
+    fn id_to_str(&self) -> String<64> {
+        String::try_from(ROLAND_TR8S.id).unwrap()
+    }
+
+    fn port_name_to_str(&self) -> String<64> {
+        String::try_from(ROLAND_TR8S.port_name).unwrap()
+    }
+
+    fn params(&self) -> &'static [ParamDefinition] {
+        &TR_8S_PARAMS
+    }
+
+    fn notes(&self) -> &'static [NoteDefinition] {
+        &TR_8S_NOTE_MAP
+    }
+
+    fn identity(&self) -> Option<IdentityMatch> {
+        Some(TR_8S_IDENTITY)
+    }
+}
+
+/// channel 10, which is the factory setting for both directions
+pub const TR_8S_DEFAULT_CHANNEL: u8 = ROLAND_TR8S.channel;
+
+/// Identity Reply of the TR-8S, regardless of the model number
+pub const TR_8S_IDENTITY: IdentityMatch = ROLAND_TR8S.identity.unwrap();
+
+pub const TR_8S_INSTRUMENTS: usize = 11;
+
+/// Knobs of each instrument from top to bottom, followed by its fader
+pub const TR_8S_INSTRUMENT_PARAMS: [&str; 4] = ["TUNE", "DECAY", "CTRL", "LEVEL"];
+
+pub const TR_8S_N_INSTRUMENT_PARAMS: usize = TR_8S_INSTRUMENT_PARAMS.len() * TR_8S_INSTRUMENTS;
+/// ATTACK and COMP of BD, SNAPPY and COMP of SD
+pub const TR_8S_N_EXTRA_PARAMS: usize = 4;
+pub const TR_8S_N_KIT_PARAMS: usize = 7;
+pub const TR_8S_N_PARAMS: usize =
+    TR_8S_N_INSTRUMENT_PARAMS + TR_8S_N_EXTRA_PARAMS + TR_8S_N_KIT_PARAMS;
+
+/// Every instrument with `TR_8S_INSTRUMENT_PARAMS` in a row, the extra knobs of BD and SD and the kit, as listed in
+/// `definitions/roland-tr8s.toml`
+pub const TR_8S_PARAMS: [ParamDefinition; TR_8S_N_PARAMS] = ROLAND_TR8S_PARAMS;
+
+/// Every instrument with the note it is triggered with by default
+pub const TR_8S_NOTE_MAP: [NoteDefinition; TR_8S_INSTRUMENTS] = ROLAND_TR8S_NOTES;
+
+pub const TR_8S_INSTRUMENT_NAMES: [&str; TR_8S_INSTRUMENTS] = tr8s_note_names();
+
+/// factory setting of the note each instrument is triggered with
+pub const TR_8S_NOTES: [u8; TR_8S_INSTRUMENTS] = tr8s_notes();
+
+// CC numbers of each instrument, in the order of `TR_8S_INSTRUMENT_NAMES`
+
+pub const TR_8S_CC_TUNE: [u8; TR_8S_INSTRUMENTS] = tr8s_instrument_ccs(0);
+pub const TR_8S_CC_DECAY: [u8; TR_8S_INSTRUMENTS] = tr8s_instrument_ccs(1);
+pub const TR_8S_CC_CTRL: [u8; TR_8S_INSTRUMENTS] = tr8s_instrument_ccs(2);
+pub const TR_8S_CC_LEVEL: [u8; TR_8S_INSTRUMENTS] = tr8s_instrument_ccs(3);
+
+/// Knobs only BD and SD have
+pub const TR_8S_EXTRA_PARAMS: [ParamDefinition; TR_8S_N_EXTRA_PARAMS] =
+    tr8s_params_from(TR_8S_N_INSTRUMENT_PARAMS);
+
+/// Parameters of the whole kit, which the TR-8 has no CC for
+pub const TR_8S_KIT_PARAMS: [ParamDefinition; TR_8S_N_KIT_PARAMS] =
+    tr8s_params_from(TR_8S_N_INSTRUMENT_PARAMS + TR_8S_N_EXTRA_PARAMS);
+
+const fn tr8s_notes() -> [u8; TR_8S_INSTRUMENTS] {
+    let mut notes = [0; TR_8S_INSTRUMENTS];
+    let mut i = 0;
+
+    while i < TR_8S_INSTRUMENTS {
+        notes[i] = TR_8S_NOTE_MAP[i].note;
+        i += 1;
+    }
+
+    notes
+}
+
+const fn tr8s_note_names() -> [&'static str; TR_8S_INSTRUMENTS] {
+    let mut names = [""; TR_8S_INSTRUMENTS];
+    let mut i = 0;
+
+    while i < TR_8S_INSTRUMENTS {
+        names[i] = TR_8S_NOTE_MAP[i].name;
+        i += 1;
+    }
+
+    names
+}
+
+/// CC number of one of `TR_8S_INSTRUMENT_PARAMS` for every instrument
+const fn tr8s_instrument_ccs(param: usize) -> [u8; TR_8S_INSTRUMENTS] {
+    let mut ccs = [0; TR_8S_INSTRUMENTS];
+    let mut i = 0;
+
+    while i < TR_8S_INSTRUMENTS {
+        ccs[i] = TR_8S_PARAMS[i * TR_8S_INSTRUMENT_PARAMS.len() + param].cc;
+        i += 1;
+    }
+
+    ccs
+}
+
+/// `N` parameters starting at `offset` in `TR_8S_PARAMS`
+const fn tr8s_params_from<const N: usize>(offset: usize) -> [ParamDefinition; N] {
+    let mut params = [TR_8S_PARAMS[0]; N];
+    let mut i = 0;
+
+    while i < N {
+        params[i] = TR_8S_PARAMS[offset + i];
+        i += 1;
+    }
+
+    params
+}
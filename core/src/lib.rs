@@ -5,12 +5,27 @@ extern crate std;
 
 pub mod devices;
 
+mod clock;
 mod core;
 mod looper;
 mod midi;
+mod session;
+mod smf;
 
 pub mod prelude {
-    pub use crate::core::UncycleCore;
-    pub use crate::devices::{DeviceInterface, SupportedDevice};
+    pub use crate::clock::{TempoEstimator, PPQN};
+    pub use crate::core::{ClockSource, SysEx, UncycleCore, MAX_DEVICE_DEFINITIONS, SYSEX_LEN};
+    pub use crate::devices::{
+        is_device_port, DefinedDevice, DefinitionError, DeviceDefinition, DeviceInterface,
+        GenericDevice, NoteDefinition, ParamDefinition, SupportedDevice,
+    };
+    pub use crate::looper::{
+        LoopEvent, OverdubMode, Quantize, RecMode, ShrinkMode, LOOPER_MAX_LEN, LOOPER_MIN_LEN,
+        SUBTICKS_PER_TICK,
+    };
     pub use crate::midi::*;
+    pub use crate::session::{SessionError, SessionReader, SessionWriter, SESSION_VERSION};
+    pub use crate::smf::{
+        read_smf, ByteSink, Smf, SmfError, SmfEvent, SmfFormat, SmfImport, SMF_PPQ,
+    };
 }
@@ -0,0 +1,91 @@
+use heapless::Vec;
+
+use crate::midi::{MidiMessage, Param, ParamChange};
+
+/// What the looper plays back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopEvent {
+    Param(ParamChange),
+    NoteOn { note: u8, velocity: u8 },
+    NoteOff { note: u8 },
+}
+
+impl LoopEvent {
+    /// Channel messages that can be looped, control changes are taken as plain 7 bit CC
+    pub fn from_message(message: &MidiMessage) -> Option<Self> {
+        let param = |param: Param, value: u16| Some(LoopEvent::Param(ParamChange { param, value }));
+
+        match *message {
+            MidiMessage::NoteOn { note, velocity, .. } if !message.is_note_off() => {
+                Some(LoopEvent::NoteOn { note, velocity })
+            }
+            MidiMessage::NoteOn { note, .. } | MidiMessage::NoteOff { note, .. } => {
+                Some(LoopEvent::NoteOff { note })
+            }
+            MidiMessage::ControlChange { control, value, .. } => {
+                Some(LoopEvent::Param(ParamChange::cc(control, value)))
+            }
+            MidiMessage::PitchBend { value, .. } => param(Param::PitchBend, value),
+            MidiMessage::ChannelAftertouch { pressure, .. } => {
+                param(Param::ChannelPressure, pressure as u16)
+            }
+            MidiMessage::PolyAftertouch { note, pressure, .. } => {
+                param(Param::PolyPressure(note), pressure as u16)
+            }
+            _ => None,
+        }
+    }
+
+    /// Messages in the order they have to be sent
+    pub fn to_messages(&self, channel: u8) -> Vec<MidiMessage, 4> {
+        let message = match *self {
+            LoopEvent::Param(change) => return change.to_messages(channel),
+            LoopEvent::NoteOn { note, velocity } => MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            },
+            LoopEvent::NoteOff { note } => MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity: 0,
+            },
+        };
+
+        let mut messages = Vec::new();
+        messages.push(message).ok();
+
+        messages
+    }
+}
+
+/// What the looper records
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum RecordedEvent {
+    Param(ParamChange),
+    /// `len` in sub-ticks is unknown until the note off has been received
+    Note {
+        note: u8,
+        velocity: u8,
+        len: Option<u32>,
+    },
+}
+
+impl RecordedEvent {
+    /// CC number of the lane a parameter change is recorded in, notes are not part of a lane
+    pub(super) fn lane(&self) -> Option<u8> {
+        match self {
+            RecordedEvent::Param(change) => Some(change.param.lane()),
+            RecordedEvent::Note { .. } => None,
+        }
+    }
+
+    /// Events that overwrite each other when they end up on the same grid position
+    pub(super) fn is_same_as(&self, other: &RecordedEvent) -> bool {
+        match (self, other) {
+            (RecordedEvent::Param(a), RecordedEvent::Param(b)) => a.param.is_same_as(&b.param),
+            (RecordedEvent::Note { note: a, .. }, RecordedEvent::Note { note: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+}
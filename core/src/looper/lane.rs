@@ -0,0 +1,56 @@
+use crate::midi::N_LANES;
+
+/// Playback state of all recorded messages sharing the same CC number, pitch bend or aftertouch lane
+#[derive(Clone, Copy, Default)]
+pub struct Lane {
+    pub muted: bool,
+    pub soloed: bool,
+}
+
+pub struct Lanes {
+    lanes: [Lane; N_LANES],
+    n_soloed: u8,
+}
+
+impl Lanes {
+    pub fn new() -> Self {
+        Self {
+            lanes: [Lane::default(); N_LANES],
+            n_soloed: 0,
+        }
+    }
+
+    pub fn get(&self, cc_num: u8) -> &Lane {
+        &self.lanes[cc_num as usize]
+    }
+
+    pub fn toggle_mute(&mut self, cc_num: u8) {
+        self.lanes[cc_num as usize].muted ^= true;
+    }
+
+    pub fn toggle_solo(&mut self, cc_num: u8) {
+        let lane = &mut self.lanes[cc_num as usize];
+        lane.soloed ^= true;
+
+        if lane.soloed {
+            self.n_soloed += 1;
+        } else {
+            self.n_soloed -= 1;
+        }
+    }
+
+    /// As soon as one lane is soloed, only soloed lanes are audible
+    pub fn is_audible(&self, cc_num: u8) -> bool {
+        let lane = self.get(cc_num);
+
+        if self.n_soloed > 0 {
+            lane.soloed
+        } else {
+            !lane.muted
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
@@ -0,0 +1,1032 @@
+mod event;
+mod lane;
+mod session;
+
+pub use event::LoopEvent;
+pub use lane::Lane;
+
+use crate::{
+    clock::PPQN,
+    midi::{MidiMsg, Param, ParamChange, N_LANES, N_NOTES},
+};
+use event::RecordedEvent;
+use lane::Lanes;
+
+use heapless::Vec;
+
+const DEFAULT_REC_LEN_STEPS: u16 = 32;
+/// Shortest and longest loop in steps
+pub const LOOPER_MIN_LEN: u16 = 4;
+pub const LOOPER_MAX_LEN: u16 = 256;
+
+/// MIDI clock pulses per sixteenth
+const TICKS_PER_STEP: u32 = PPQN / 4;
+
+/// Fractions of a MIDI clock pulse the looper timeline is resolved in
+///
+/// All looper times are in these sub-ticks, so loops follow tempo changes instead of being fixed in µs.
+pub const SUBTICKS_PER_TICK: u32 = 256;
+
+/// room for a parameter change of every lane, poly aftertouch and a note on and off of every note
+const PLAYBACK_BUFFER_LEN: usize = N_LANES + 3 * N_NOTES;
+
+/// Unquantised messages of a parameter closer than this to the previous one in the same pass only update its value,
+/// which thins out dense streams like pitch bend to at most one message per MIDI clock pulse
+const MIN_PARAM_SPACING: u32 = SUBTICKS_PER_TICK;
+
+/// Number of messages that can be recorded
+const REC_BUFFER_LEN: usize = 1024;
+
+/// Room for every recorded message, notes take up a note on and off
+pub const LOOP_EVENTS_LEN: usize = 2 * REC_BUFFER_LEN;
+
+/// Parameters that can count as touched at the same time, during a replacing overdub
+const TOUCHES_LEN: usize = N_LANES;
+
+/// Maximum number of record/overdub passes that can be undone, older passes get merged
+const MAX_LAYERS: u8 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecMode {
+    /// every record or overdub pass lasts exactly one loop
+    OneShot,
+    /// after the first recorded loop the record head stays down and every incoming CC is overdubbed
+    Continuous,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverdubMode {
+    /// overdubbed messages are added on top of the existing recording
+    Add,
+    /// like touch automation, incoming CC messages replace the recording of their parameter while the knob is moved
+    Replace,
+}
+
+/// Part of the recording that is kept when the loop gets shorter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShrinkMode {
+    /// keep the steps from the start of the loop, e.g. the first half when halving
+    KeepStart,
+    /// keep the steps up to the end of the loop, e.g. the second half when halving
+    KeepEnd,
+}
+
+/// Grid recorded messages are snapped to, relative to MIDI clock pulses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantize {
+    Off,
+    Sixteenth,
+    ThirtySecond,
+    /// one MIDI clock pulse, i.e. a 1/96 note
+    Tick,
+}
+
+impl Quantize {
+    /// Grid resolution in MIDI clock pulses
+    pub fn ticks(&self) -> Option<u32> {
+        match self {
+            Quantize::Off => None,
+            Quantize::Sixteenth => Some(6),
+            Quantize::ThirtySecond => Some(3),
+            Quantize::Tick => Some(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RecordedMidiMsg {
+    time: u32,
+    event: RecordedEvent,
+    /// record or overdub pass this message belongs to
+    layer: u8,
+    /// overdub pass that replaced this message, which is still kept for `undo`
+    replaced_in: Option<u8>,
+}
+
+pub struct Looper {
+    /// statically allocated buffer for everything that can be played back at once
+    playback_buffer: Vec<LoopEvent, PLAYBACK_BUFFER_LEN>,
+
+    /// in sub-ticks
+    time_last_checked: u64,
+
+    /// every recorded message, notes and parameters of all passes
+    pub recorded: Vec<RecordedMidiMsg, REC_BUFFER_LEN>,
+    pub record: bool,
+    rec_start: Option<u64>,
+
+    pub loop_steps: u16,
+
+    /// in sub-ticks
+    loop_len: u32,
+
+    pub overdub: bool,
+    overdub_start: Option<u64>,
+
+    rec_mode: RecMode,
+    overdub_mode: OverdubMode,
+    quantize: Quantize,
+    shrink_mode: ShrinkMode,
+    /// loop time of the last incoming message of each parameter touched within the last step of the running pass
+    last_touch: Vec<(Param, u32), TOUCHES_LEN>,
+
+    /// layers below this index are played back, the others have been undone
+    n_layers: u8,
+    /// layers below this index can be brought back with `redo`
+    n_recorded_layers: u8,
+    /// layer of the running pass, only assigned once the pass records its first message
+    pass_layer: Option<u8>,
+
+    lanes: Lanes,
+    /// only this CC number is recorded during the running pass
+    lane_filter: Option<u8>,
+
+    /// loop time each held note has been played at before it got snapped to the grid
+    held_notes: [Option<u32>; N_NOTES],
+    /// song position in sub-ticks at which each played back note has to be released
+    sounding_notes: [Option<u64>; N_NOTES],
+    /// release every sounding note with the next playback
+    release_all: bool,
+}
+
+impl Default for Looper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Looper {
+    pub fn new() -> Self {
+        Self {
+            playback_buffer: Vec::new(),
+
+            time_last_checked: 0,
+
+            recorded: Vec::new(),
+            record: false,
+            rec_start: None,
+
+            loop_steps: DEFAULT_REC_LEN_STEPS,
+            loop_len: steps_to_subticks(DEFAULT_REC_LEN_STEPS),
+
+            overdub: false,
+            overdub_start: None,
+
+            rec_mode: RecMode::OneShot,
+            overdub_mode: OverdubMode::Add,
+            quantize: Quantize::Off,
+            shrink_mode: ShrinkMode::KeepStart,
+            last_touch: Vec::new(),
+
+            n_layers: 0,
+            n_recorded_layers: 0,
+            pass_layer: None,
+
+            lanes: Lanes::new(),
+            lane_filter: None,
+
+            held_notes: [None; N_NOTES],
+            sounding_notes: [None; N_NOTES],
+            release_all: false,
+        }
+    }
+
+    pub fn set_rec_mode(&mut self, mode: RecMode) {
+        self.rec_mode = mode;
+    }
+
+    pub fn get_rec_mode(&self) -> RecMode {
+        self.rec_mode
+    }
+
+    pub fn set_overdub_mode(&mut self, mode: OverdubMode) {
+        self.overdub_mode = mode;
+    }
+
+    pub fn get_overdub_mode(&self) -> OverdubMode {
+        self.overdub_mode
+    }
+
+    pub fn set_quantize(&mut self, quantize: Quantize) {
+        self.quantize = quantize;
+    }
+
+    pub fn get_quantize(&self) -> Quantize {
+        self.quantize
+    }
+
+    pub fn set_shrink_mode(&mut self, mode: ShrinkMode) {
+        self.shrink_mode = mode;
+    }
+
+    pub fn get_shrink_mode(&self) -> ShrinkMode {
+        self.shrink_mode
+    }
+
+    /// Sends a note off for every note that is played back with the next playback, e.g. when the transport stops
+    pub fn release_notes(&mut self) {
+        self.release_all = true;
+    }
+
+    /// Snaps everything that has been recorded so far to the current grid
+    ///
+    /// Of several messages of the same lane and pass that end up on the same grid position, only the most recent
+    /// one is kept.
+    pub fn quantize_recording(&mut self) {
+        let Some(grid) = self.grid_len() else {
+            return;
+        };
+
+        let loop_len = self.loop_len;
+        self.recorded
+            .iter_mut()
+            .for_each(|cc| cc.time = snap_to_grid(cc.time, grid, loop_len));
+
+        // messages are stored in the order they have been recorded
+        let mut i = 0;
+        while i < self.recorded.len() {
+            let cc = self.recorded[i];
+            let is_overwritten = self.recorded[i + 1..].iter().any(|later| {
+                later.time == cc.time
+                    && later.layer == cc.layer
+                    && later.event.is_same_as(&cc.event)
+            });
+
+            if is_overwritten {
+                self.recorded.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Sets the loop length from 4 to U16_MAX steps and adapts what has been recorded so far
+    ///
+    /// A longer loop repeats the existing recording until it is filled up, so doubling duplicates it. A shorter
+    /// loop keeps the part selected by the `ShrinkMode`. Repeated messages are dropped once the buffer is full.
+    pub fn set_loop_steps(&mut self, steps: u16) {
+        assert!(steps >= 4);
+
+        let old_len = self.loop_len;
+        let new_len = steps_to_subticks(steps);
+
+        if new_len > old_len {
+            let n_recorded = self.recorded.len();
+
+            for i in 0..n_recorded {
+                let mut copy = self.recorded[i];
+
+                copy.time += old_len;
+                while copy.time < new_len && self.recorded.push(copy).is_ok() {
+                    copy.time += old_len;
+                }
+            }
+        } else if new_len < old_len {
+            match self.shrink_mode {
+                ShrinkMode::KeepStart => self.recorded.retain(|cc| cc.time < new_len),
+                ShrinkMode::KeepEnd => {
+                    let offset = old_len - new_len;
+
+                    self.recorded.retain(|cc| cc.time >= offset);
+                    self.recorded.iter_mut().for_each(|cc| cc.time -= offset);
+                }
+            }
+        }
+
+        // touches and held notes refer to positions of the old loop
+        self.last_touch.clear();
+        self.held_notes = [None; N_NOTES];
+
+        self.loop_len = new_len;
+        self.loop_steps = steps;
+    }
+
+    /// Length of the loop in sub-ticks
+    pub fn loop_len(&self) -> u32 {
+        self.loop_len
+    }
+
+    /// Engage in recording CC messages, `now` is the song position in sub-ticks
+    ///
+    /// In continuous mode this toggles the record head while overdubbing.
+    pub fn start_recording(&mut self, now: u64) {
+        if !self.record {
+            if self.rec_start.is_none() {
+                self.record = true;
+                self.rec_start = Some(now);
+                self.end_pass();
+            } else if !self.overdub {
+                self.overdub_start = Some(now);
+                self.overdub = true;
+                self.end_pass();
+            } else if self.rec_mode == RecMode::Continuous {
+                self.overdub = false;
+                self.overdub_start = None;
+                self.end_pass();
+            }
+        }
+    }
+
+    pub fn delete_recording(&mut self) {
+        self.recorded.clear();
+        self.release_all = true;
+        self.record = false;
+        self.rec_start = None;
+        self.overdub = false;
+        self.overdub_start = None;
+        self.n_layers = 0;
+        self.n_recorded_layers = 0;
+        self.end_pass();
+        self.lanes.reset();
+        self.held_notes = [None; N_NOTES];
+    }
+
+    pub fn lane(&self, cc_num: u8) -> &Lane {
+        self.lanes.get(cc_num)
+    }
+
+    pub fn toggle_mute_lane(&mut self, cc_num: u8) {
+        self.lanes.toggle_mute(cc_num);
+    }
+
+    pub fn toggle_solo_lane(&mut self, cc_num: u8) {
+        self.lanes.toggle_solo(cc_num);
+    }
+
+    /// Removes all recorded messages of one CC number, including undone ones
+    pub fn clear_lane(&mut self, cc_num: u8) {
+        self.recorded.retain(|cc| cc.event.lane() != Some(cc_num));
+    }
+
+    /// Records only this CC number for one pass, the other lanes keep playing
+    ///
+    /// The pass replaces everything the lane has recorded, so undoing it brings the lane back.
+    pub fn rerecord_lane(&mut self, now: u64, cc_num: u8) {
+        if self.record || self.overdub {
+            return;
+        }
+
+        self.start_recording(now);
+        self.lane_filter = Some(cc_num);
+
+        // a first recording has nothing to replace
+        if !self.overdub {
+            return;
+        }
+
+        if let Some(layer) = self.recording_layer() {
+            self.recorded
+                .iter_mut()
+                .filter(|cc| cc.event.lane() == Some(cc_num) && cc.replaced_in.is_none())
+                .filter(|cc| cc.layer != layer)
+                .for_each(|cc| cc.replaced_in = Some(layer));
+        }
+    }
+
+    /// Which lanes have recorded messages that are currently part of the loop
+    pub fn lanes_with_automation(&self) -> [bool; N_LANES] {
+        let mut automated = [false; N_LANES];
+
+        self.recorded
+            .iter()
+            .filter(|cc| self.is_in_loop(cc))
+            .filter_map(|cc| cc.event.lane())
+            .for_each(|cc_num| automated[cc_num as usize] = true);
+
+        automated
+    }
+
+    /// Removes the last record/overdub pass from playback
+    ///
+    /// A running pass is stopped first, also before it has recorded anything and in continuous mode. Otherwise the
+    /// next incoming message would start a new pass, which discards the undone one.
+    pub fn undo(&mut self) {
+        // a first recording without any message hasn't started the loop yet
+        if self.record && self.pass_layer.is_none() {
+            self.rec_start = None;
+        }
+
+        self.record = false;
+        self.overdub = false;
+        self.overdub_start = None;
+        self.end_pass();
+
+        self.n_layers = self.n_layers.saturating_sub(1);
+    }
+
+    /// Brings back the last undone record/overdub pass
+    pub fn redo(&mut self) {
+        if self.n_layers < self.n_recorded_layers {
+            self.n_layers += 1;
+        }
+    }
+
+    /// Number of record/overdub passes that are played back
+    pub fn n_layers(&self) -> u8 {
+        self.n_layers
+    }
+
+    /// Number of record/overdub passes that have been undone and can be redone
+    pub fn n_undone_layers(&self) -> u8 {
+        self.n_recorded_layers - self.n_layers
+    }
+
+    /// Records a plain 7 bit CC message, `now` is the song position in sub-ticks
+    pub fn record_cc(&mut self, now: u64, cc_msg: &MidiMsg) {
+        self.record_param(now, ParamChange::cc(cc_msg[1], cc_msg[2]));
+    }
+
+    /// Must be called for every incoming parameter change, `now` is the song position in sub-ticks
+    pub fn record_param(&mut self, now: u64, change: ParamChange) {
+        let lane = change.param.lane();
+
+        if self.lane_filter.is_some_and(|cc_num| cc_num != lane) {
+            return;
+        }
+
+        if self.overdub && self.overdub_mode == OverdubMode::Replace {
+            if let (Some(time), Some(layer)) = (self.loop_position(now), self.recording_layer()) {
+                self.punch_in(change.param, time, layer);
+            }
+        }
+
+        self.record_event(now, RecordedEvent::Param(change));
+    }
+
+    /// Must be called for every incoming note on, `now` is the song position in sub-ticks
+    ///
+    /// Notes are added on top of the recording in every overdub mode and are not part of a lane.
+    pub fn record_note_on(&mut self, now: u64, note: u8, velocity: u8) {
+        if self.lane_filter.is_some() {
+            return;
+        }
+
+        // a note that is still held ends where it gets played again
+        self.record_note_off(now, note);
+
+        self.held_notes[note as usize] = self.loop_position(now);
+        self.record_event(
+            now,
+            RecordedEvent::Note {
+                note,
+                velocity,
+                len: None,
+            },
+        );
+    }
+
+    /// Must be called for every incoming note off, also after recording has ended so that held notes get their length
+    pub fn record_note_off(&mut self, now: u64, note: u8) {
+        if let Some(position) = self.loop_position(now) {
+            self.end_note(note, position);
+        }
+    }
+
+    /// Gives the latest held note of this number its length, `time` is the loop time of the note off
+    ///
+    /// The length is measured from where the note has been played, so a note moved by the grid keeps its length.
+    fn end_note(&mut self, note: u8, time: u32) {
+        let loop_len = self.loop_len;
+        let played_at = self.held_notes[note as usize].take();
+
+        let held = self
+            .recorded
+            .iter_mut()
+            .rev()
+            .find_map(|cc| match &mut cc.event {
+                RecordedEvent::Note {
+                    note: held,
+                    len: len @ None,
+                    ..
+                } if *held == note => Some((len, cc.time)),
+                _ => None,
+            });
+
+        if let Some((len, start)) = held {
+            *len = Some(loop_distance(played_at.unwrap_or(start), time, loop_len));
+        }
+    }
+
+    /// Replaces the recording with events that have not been played in, e.g. from a file
+    ///
+    /// `now` is the song position the loop starts at and `events` are ordered by their loop time in sub-ticks. Note
+    /// offs end the latest note on of their note, notes without one last until the end of the loop. Everything is
+    /// one pass that can be undone. Returns the number of events that did not fit into the recording or start at or
+    /// after the end of the loop, note offs there are left out since the loop end releases their notes anyway.
+    ///
+    /// If none of the events would be part of the recording, nothing changes and `None` is returned.
+    pub fn load_recording(
+        &mut self,
+        now: u64,
+        steps: u16,
+        events: &[(u32, LoopEvent)],
+    ) -> Option<usize> {
+        let loop_len = steps_to_subticks(steps);
+        let is_note_off = |event: &LoopEvent| matches!(event, LoopEvent::NoteOff { .. });
+
+        if !events
+            .iter()
+            .any(|(time, event)| *time < loop_len && !is_note_off(event))
+        {
+            return None;
+        }
+
+        self.delete_recording();
+        self.set_loop_steps(steps);
+        self.rec_start = Some(now);
+
+        let layer = self.begin_layer();
+        let mut n_dropped = events
+            .iter()
+            .filter(|(time, event)| *time >= loop_len && !is_note_off(event))
+            .count();
+
+        for (time, event) in events.iter().filter(|(time, _)| *time < loop_len) {
+            let event = match *event {
+                LoopEvent::Param(change) => RecordedEvent::Param(change),
+                LoopEvent::NoteOn { note, velocity } => {
+                    self.end_note(note, *time);
+
+                    RecordedEvent::Note {
+                        note,
+                        velocity,
+                        len: None,
+                    }
+                }
+                LoopEvent::NoteOff { note } => {
+                    self.end_note(note, *time);
+                    continue;
+                }
+            };
+
+            let loaded = RecordedMidiMsg {
+                event,
+                time: *time,
+                layer,
+                replaced_in: None,
+            };
+
+            if self.recorded.push(loaded).is_err() {
+                n_dropped += 1;
+            }
+        }
+
+        self.recorded.iter_mut().for_each(|cc| {
+            if let RecordedEvent::Note {
+                len: len @ None, ..
+            } = &mut cc.event
+            {
+                *len = Some(loop_len - cc.time);
+            }
+        });
+
+        self.end_pass();
+
+        Some(n_dropped)
+    }
+
+    /// Layer of the running pass, allocated with the first message that is recorded
+    fn recording_layer(&mut self) -> Option<u8> {
+        if !self.record && !self.overdub {
+            return None;
+        }
+
+        match self.pass_layer {
+            Some(layer) => Some(layer),
+            None => Some(self.begin_layer()),
+        }
+    }
+
+    fn record_event(&mut self, now: u64, event: RecordedEvent) {
+        let (Some(time), Some(layer)) = (self.loop_position(now), self.recording_layer()) else {
+            return;
+        };
+
+        let time = match self.grid_len() {
+            Some(grid) => snap_to_grid(time, grid, self.loop_len),
+            None => time,
+        };
+
+        // on a grid, the latest value of a parameter or note within the same pass wins
+        let loop_len = self.loop_len;
+        let existing = match self.quantize {
+            Quantize::Off => self
+                .recorded
+                .iter_mut()
+                .rev()
+                .find(|cc| cc.layer == layer && cc.event.is_same_as(&event))
+                .filter(|cc| {
+                    cc.event.lane().is_some()
+                        && loop_distance(cc.time, time, loop_len) < MIN_PARAM_SPACING
+                }),
+            _ => self
+                .recorded
+                .iter_mut()
+                .find(|cc| cc.time == time && cc.layer == layer && cc.event.is_same_as(&event)),
+        };
+
+        if let Some(cc) = existing {
+            cc.event = event;
+        } else {
+            self.recorded
+                .push(RecordedMidiMsg {
+                    event,
+                    time,
+                    layer,
+                    replaced_in: None,
+                })
+                .ok();
+        }
+    }
+
+    /// Completes the parameter change that has just been recorded with its LSB, e.g. a 7 bit CC turns out to be the
+    /// MSB of a 14 bit CC
+    pub fn refine_param(&mut self, change: ParamChange) {
+        let Some(layer) = self.pass_layer else {
+            return;
+        };
+
+        if self
+            .lane_filter
+            .is_some_and(|cc_num| cc_num != change.param.lane())
+        {
+            return;
+        }
+
+        // messages are stored in the order of arrival, so the latest one of the parameter is the one to complete
+        let event = RecordedEvent::Param(change);
+
+        if let Some(cc) = self
+            .recorded
+            .iter_mut()
+            .rev()
+            .find(|cc| cc.layer == layer && cc.event.is_same_as(&event))
+        {
+            cc.event = event;
+        }
+    }
+
+    /// Replaces older messages of a parameter in between two incoming messages of it, as long as they arrive within
+    /// one step. This is how long a knob counts as touched. Parameters that share a lane, like the aftertouch of
+    /// different notes or several NRPN, are replaced on their own.
+    fn punch_in(&mut self, param: Param, time: u32, layer: u8) {
+        let step_len = self.step_len();
+        let loop_len = self.loop_len;
+
+        // touches that are over make room for new ones
+        self.last_touch
+            .retain(|(_, last)| loop_distance(*last, time, loop_len) <= step_len);
+
+        let span_begin = match self
+            .last_touch
+            .iter_mut()
+            .find(|(touched, _)| touched.is_same_as(&param))
+        {
+            Some((_, last)) => core::mem::replace(last, time),
+            None => {
+                // without room the parameter only counts as touched at this message
+                self.last_touch.push((param, time)).ok();
+                time
+            }
+        };
+
+        let n_layers = self.n_layers;
+
+        self.recorded
+            .iter_mut()
+            .filter(|cc| match cc.event {
+                RecordedEvent::Param(change) => change.param.is_same_as(&param),
+                RecordedEvent::Note { .. } => false,
+            })
+            .filter(|cc| cc.layer != layer && cc.replaced_in.is_none())
+            .filter(|cc| cc.layer < n_layers)
+            .filter(|cc| {
+                loop_distance(span_begin, cc.time, loop_len)
+                    <= loop_distance(span_begin, time, loop_len)
+            })
+            .for_each(|cc| cc.replaced_in = Some(layer));
+    }
+
+    /// Whether a parameter is touched during a replacing overdub, its older messages are held back
+    fn is_punched_in(&self, param: Param, time: u32) -> bool {
+        self.overdub
+            && self.overdub_mode == OverdubMode::Replace
+            && self.last_touch.iter().any(|(touched, last)| {
+                touched.is_same_as(&param)
+                    && loop_distance(*last, time, self.loop_len) <= self.step_len()
+            })
+    }
+
+    /// Recorded message is part of a layer that has not been undone and has not been replaced by one
+    fn is_in_loop(&self, cc: &RecordedMidiMsg) -> bool {
+        cc.layer < self.n_layers && cc.replaced_in.is_none_or(|layer| layer >= self.n_layers)
+    }
+
+    /// Length of a sixteenth in sub-ticks
+    fn step_len(&self) -> u32 {
+        TICKS_PER_STEP * SUBTICKS_PER_TICK
+    }
+
+    /// Length of one quantisation grid cell in sub-ticks
+    fn grid_len(&self) -> Option<u32> {
+        self.quantize.ticks().map(|ticks| ticks * SUBTICKS_PER_TICK)
+    }
+
+    fn end_pass(&mut self) {
+        self.pass_layer = None;
+        self.lane_filter = None;
+        self.last_touch.clear();
+    }
+
+    /// Allocates a new layer for the running pass, which discards everything that could have been redone
+    fn begin_layer(&mut self) -> u8 {
+        let n_layers = self.n_layers;
+        self.recorded.retain(|msg| msg.layer < n_layers);
+        self.recorded
+            .iter_mut()
+            .filter(|msg| msg.replaced_in.is_some_and(|layer| layer >= n_layers))
+            .for_each(|msg| msg.replaced_in = None);
+
+        // merge the two oldest layers to make room
+        if self.n_layers == MAX_LAYERS {
+            self.recorded.iter_mut().for_each(|msg| {
+                msg.layer = msg.layer.saturating_sub(1);
+                msg.replaced_in = msg.replaced_in.map(|layer| layer.saturating_sub(1));
+            });
+            self.recorded
+                .retain(|msg| msg.replaced_in.is_none_or(|layer| layer > msg.layer));
+            self.n_layers -= 1;
+        }
+
+        let layer = self.n_layers;
+
+        self.n_layers += 1;
+        self.n_recorded_layers = self.n_layers;
+        self.pass_layer = Some(layer);
+
+        layer
+    }
+
+    /// End of loop handling
+    pub fn handle_eol(&mut self, now: u64) {
+        if let Some(start) = self.rec_start {
+            if self.record && now.saturating_sub(start) >= self.loop_len as u64 {
+                self.record = false;
+                self.end_pass();
+
+                // playback starts right at the end of the first loop, what came before has just been played live
+                self.time_last_checked = self.time_last_checked.max(start + self.loop_len as u64);
+
+                // keep the record head down
+                if self.rec_mode == RecMode::Continuous {
+                    self.overdub = true;
+                    self.overdub_start = Some(now);
+                }
+            }
+        }
+
+        if let Some(start) = self.overdub_start {
+            if self.overdub && now.saturating_sub(start) >= self.loop_len as u64 {
+                self.end_pass();
+
+                match self.rec_mode {
+                    RecMode::OneShot => {
+                        self.overdub = false;
+                        self.overdub_start = None;
+                    }
+                    RecMode::Continuous => self.overdub_start = Some(now),
+                }
+            }
+        }
+    }
+
+    /// Moves the beginning of the loop to `start`, so that playback starts over from there
+    ///
+    /// A first recording that is still running keeps its start, since what it has recorded refers to it.
+    pub fn restart(&mut self, start: u64) {
+        if self.rec_start.is_some() && !self.record {
+            self.rec_start = Some(start);
+        }
+    }
+
+    /// Time within the loop in sub-ticks, counted from the start of the first recording
+    fn loop_position(&self, now: u64) -> Option<u32> {
+        self.rec_start
+            .map(|start| (now.saturating_sub(start) % self.loop_len as u64) as u32)
+    }
+
+    /// Everything that is played back within one cycle with its loop time in sub-ticks, ordered by time
+    ///
+    /// Note offs come before anything else at the same time. Notes that are still held are left out and notes
+    /// reaching past the end of the loop end with it.
+    pub fn loop_events(&self) -> Vec<(u32, LoopEvent), LOOP_EVENTS_LEN> {
+        let mut events: Vec<(u32, LoopEvent), LOOP_EVENTS_LEN> = Vec::new();
+
+        for cc in self.recorded.iter().filter(|cc| self.is_in_loop(cc)) {
+            match cc.event {
+                RecordedEvent::Param(change) => {
+                    if self.lanes.is_audible(change.param.lane()) {
+                        events.push((cc.time, LoopEvent::Param(change))).ok();
+                    }
+                }
+                RecordedEvent::Note { len: None, .. } => {}
+                RecordedEvent::Note {
+                    note,
+                    velocity,
+                    len: Some(len),
+                } => {
+                    // a note off at the same time would be sorted before its note on
+                    let end = (cc.time + len.max(1)).min(self.loop_len);
+
+                    events
+                        .push((cc.time, LoopEvent::NoteOn { note, velocity }))
+                        .ok();
+                    events.push((end, LoopEvent::NoteOff { note })).ok();
+                }
+            }
+        }
+
+        // the order of recording is kept for events at the same time
+        let mut order: Vec<u16, LOOP_EVENTS_LEN> = (0..events.len() as u16).collect();
+        order.sort_unstable_by_key(|i| {
+            let (time, event) = events[*i as usize];
+            (time, !matches!(event, LoopEvent::NoteOff { .. }), *i)
+        });
+
+        order.iter().map(|i| events[*i as usize]).collect()
+    }
+
+    /// Returns a heapless vector with every recorded event in between the last call (inclusive) and `now`
+    /// (exclusive). Consecutive calls cover the loop without gaps or overlaps, so each event is played exactly once
+    /// per cycle, also when a call spans the end of the loop. Note offs of played back notes that are due come first.
+    /// Events that do not fit into the buffer are dropped, which is the worst case scenario.
+    pub fn play_back_recording(&mut self, now: u64) -> &Vec<LoopEvent, PLAYBACK_BUFFER_LEN> {
+        self.playback_buffer.clear();
+
+        self.play_back_note_offs(now);
+
+        if !self.record {
+            if let Some(start) = self.rec_start {
+                let window = PlaybackWindow::new(
+                    self.time_last_checked.saturating_sub(start),
+                    now.saturating_sub(start),
+                    self.loop_len,
+                );
+
+                for cc in self.recorded.iter() {
+                    if !self.is_in_loop(cc) || !window.contains(cc.time) {
+                        continue;
+                    }
+
+                    match cc.event {
+                        RecordedEvent::Param(change) => {
+                            let lane = change.param.lane();
+
+                            if !self.lanes.is_audible(lane) {
+                                continue;
+                            }
+
+                            // held back while its parameter is being replaced
+                            if self.pass_layer != Some(cc.layer)
+                                && self.is_punched_in(change.param, cc.time)
+                            {
+                                continue;
+                            }
+
+                            self.playback_buffer
+                                .push(LoopEvent::Param(change)) // add this recorded event
+                                .ok(); // if vec is full, drop it
+                        }
+
+                        // still held by the player, so there is nothing to repeat yet
+                        RecordedEvent::Note { len: None, .. } => {}
+
+                        RecordedEvent::Note {
+                            note,
+                            velocity,
+                            len: Some(len),
+                        } => {
+                            let sounding = &mut self.sounding_notes[note as usize];
+
+                            // retrigger a note that is still sounding
+                            if sounding.take().is_some() {
+                                self.playback_buffer.push(LoopEvent::NoteOff { note }).ok();
+                            }
+
+                            if self
+                                .playback_buffer
+                                .push(LoopEvent::NoteOn { note, velocity })
+                                .is_ok()
+                            {
+                                *sounding = Some(now + len as u64);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.time_last_checked = self.time_last_checked.max(now);
+
+        &self.playback_buffer
+    }
+
+    /// Releases played back notes that have reached their end, or all of them if requested
+    fn play_back_note_offs(&mut self, now: u64) {
+        let release_all = core::mem::take(&mut self.release_all);
+
+        for (note, sounding) in self.sounding_notes.iter_mut().enumerate() {
+            if sounding.is_some_and(|end| release_all || end <= now) {
+                *sounding = None;
+                self.playback_buffer
+                    .push(LoopEvent::NoteOff { note: note as u8 })
+                    .ok();
+            }
+        }
+    }
+}
+
+/// Rounds `time` to the nearest multiple of `grid`, wrapping around at the end of the loop
+fn snap_to_grid(time: u32, grid: u32, loop_len: u32) -> u32 {
+    if grid == 0 {
+        return time;
+    }
+
+    let snapped = (time + grid / 2) / grid * grid;
+
+    if snapped >= loop_len {
+        0
+    } else {
+        snapped
+    }
+}
+
+/// Time it takes to get from `from` to `to` within a loop in sub-ticks
+fn loop_distance(from: u32, to: u32, loop_len: u32) -> u32 {
+    if to >= from {
+        to - from
+    } else {
+        loop_len - from + to
+    }
+}
+
+/// Half-open span of loop time `begin..end` that has passed in between two playback calls
+enum PlaybackWindow {
+    Empty,
+    /// the window lies within one cycle
+    Within {
+        begin: u32,
+        end: u32,
+    },
+    /// the window crosses the end of the loop, so it covers `begin..loop_len` and `0..end`
+    Wrapped {
+        begin: u32,
+        end: u32,
+    },
+    /// at least a whole cycle has passed
+    Full,
+}
+
+impl PlaybackWindow {
+    /// `begin` and `end` are counted from the start of the first recording
+    fn new(begin: u64, end: u64, loop_len: u32) -> Self {
+        let loop_len = loop_len as u64;
+
+        if end <= begin {
+            return Self::Empty;
+        }
+
+        if end - begin >= loop_len {
+            return Self::Full;
+        }
+
+        let begin_pos = (begin % loop_len) as u32;
+        let end_pos = (end % loop_len) as u32;
+
+        if begin_pos < end_pos {
+            Self::Within {
+                begin: begin_pos,
+                end: end_pos,
+            }
+        } else {
+            Self::Wrapped {
+                begin: begin_pos,
+                end: end_pos,
+            }
+        }
+    }
+
+    fn contains(&self, time: u32) -> bool {
+        match *self {
+            Self::Empty => false,
+            Self::Within { begin, end } => begin <= time && time < end,
+            Self::Wrapped { begin, end } => begin <= time || time < end,
+            Self::Full => true,
+        }
+    }
+}
+
+/// Length of `n_steps` sixteenths in sub-ticks
+fn steps_to_subticks(n_steps: u16) -> u32 {
+    n_steps as u32 * TICKS_PER_STEP * SUBTICKS_PER_TICK
+}
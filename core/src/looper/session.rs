@@ -0,0 +1,232 @@
+use super::{
+    event::RecordedEvent, Looper, OverdubMode, Quantize, RecMode, RecordedMidiMsg, ShrinkMode,
+    LOOPER_MAX_LEN, LOOPER_MIN_LEN, MAX_LAYERS,
+};
+use crate::{
+    midi::{Param, ParamChange, N_LANES, N_NOTES},
+    session::{SessionError, SessionReader, SessionWriter},
+};
+
+/// Marks a missing layer or note length
+const NONE_U8: u8 = u8::MAX;
+const NONE_U32: u32 = u32::MAX;
+
+const EVENT_PARAM: u8 = 0;
+const EVENT_NOTE: u8 = 1;
+
+impl Looper {
+    /// Settings, lanes and the recording including undone passes
+    pub fn write_session(&self, w: &mut SessionWriter) {
+        w.u16(self.loop_steps);
+        w.u8(match self.rec_mode {
+            RecMode::OneShot => 0,
+            RecMode::Continuous => 1,
+        });
+        w.u8(match self.overdub_mode {
+            OverdubMode::Add => 0,
+            OverdubMode::Replace => 1,
+        });
+        w.u8(match self.quantize {
+            Quantize::Off => 0,
+            Quantize::Sixteenth => 1,
+            Quantize::ThirtySecond => 2,
+            Quantize::Tick => 3,
+        });
+        w.u8(match self.shrink_mode {
+            ShrinkMode::KeepStart => 0,
+            ShrinkMode::KeepEnd => 1,
+        });
+
+        for lane in 0..N_LANES as u8 {
+            let lane = self.lanes.get(lane);
+            w.u8(lane.muted as u8 | (lane.soloed as u8) << 1);
+        }
+
+        w.u8(self.n_layers);
+        w.u8(self.n_recorded_layers);
+
+        w.u16(self.recorded.len() as u16);
+        for cc in self.recorded.iter() {
+            w.u32(cc.time);
+            w.u8(cc.layer);
+            w.u8(cc.replaced_in.unwrap_or(NONE_U8));
+
+            match cc.event {
+                RecordedEvent::Param(change) => {
+                    let (kind, number) = param_to_parts(change.param);
+
+                    w.u8(EVENT_PARAM);
+                    w.u8(kind);
+                    w.u16(number);
+                    w.u16(change.value);
+                }
+                RecordedEvent::Note {
+                    note,
+                    velocity,
+                    len,
+                } => {
+                    w.u8(EVENT_NOTE);
+                    w.u8(note);
+                    w.u8(velocity);
+                    w.u32(len.unwrap_or(NONE_U32));
+                }
+            }
+        }
+    }
+
+    /// Replaces everything with what `write_session` has written, the loop starts at song position `now`
+    ///
+    /// Notes that are sounding get released. Nothing changes if the session can't be read or holds values the looper
+    /// can't play back.
+    pub fn read_session(&mut self, r: &mut SessionReader, now: u64) -> Result<(), SessionError> {
+        let mut looper = Looper::new();
+
+        let loop_steps = r.u16()?;
+        if !(LOOPER_MIN_LEN..=LOOPER_MAX_LEN).contains(&loop_steps) {
+            return Err(SessionError::Malformed);
+        }
+        looper.set_loop_steps(loop_steps);
+
+        looper.rec_mode = match r.u8()? {
+            0 => RecMode::OneShot,
+            1 => RecMode::Continuous,
+            _ => return Err(SessionError::Malformed),
+        };
+        looper.overdub_mode = match r.u8()? {
+            0 => OverdubMode::Add,
+            1 => OverdubMode::Replace,
+            _ => return Err(SessionError::Malformed),
+        };
+        looper.quantize = match r.u8()? {
+            0 => Quantize::Off,
+            1 => Quantize::Sixteenth,
+            2 => Quantize::ThirtySecond,
+            3 => Quantize::Tick,
+            _ => return Err(SessionError::Malformed),
+        };
+        looper.shrink_mode = match r.u8()? {
+            0 => ShrinkMode::KeepStart,
+            1 => ShrinkMode::KeepEnd,
+            _ => return Err(SessionError::Malformed),
+        };
+
+        for lane in 0..N_LANES as u8 {
+            let flags = r.u8()?;
+
+            if flags & 0b01 != 0 {
+                looper.lanes.toggle_mute(lane);
+            }
+            if flags & 0b10 != 0 {
+                looper.lanes.toggle_solo(lane);
+            }
+        }
+
+        looper.n_layers = r.u8()?;
+        looper.n_recorded_layers = r.u8()?;
+        if looper.n_layers > looper.n_recorded_layers || looper.n_recorded_layers > MAX_LAYERS {
+            return Err(SessionError::Malformed);
+        }
+
+        let n_recorded = r.u16()?;
+        for _ in 0..n_recorded {
+            let time = r.u32()?;
+            let layer = r.u8()?;
+            let replaced_in = match r.u8()? {
+                NONE_U8 => None,
+                layer => Some(layer),
+            };
+
+            let event = match r.u8()? {
+                EVENT_PARAM => {
+                    let param = param_from_parts(r.u8()?, r.u16()?)?;
+                    RecordedEvent::Param(ParamChange {
+                        param,
+                        value: r.u16()?,
+                    })
+                }
+                EVENT_NOTE => RecordedEvent::Note {
+                    note: r.u8()?,
+                    velocity: r.u8()?,
+                    len: match r.u32()? {
+                        NONE_U32 => None,
+                        len => Some(len),
+                    },
+                },
+                _ => return Err(SessionError::Malformed),
+            };
+
+            let is_valid = match event {
+                RecordedEvent::Param(change) => change.is_valid(),
+                RecordedEvent::Note {
+                    note,
+                    velocity,
+                    len,
+                } => {
+                    (note as usize) < N_NOTES
+                        && velocity < 128
+                        && len.is_none_or(|len| len <= looper.loop_len)
+                }
+            };
+
+            // a pass can only replace messages of older passes
+            let replaced_in_valid = replaced_in.is_none_or(|replaced_in| {
+                layer < replaced_in && replaced_in < looper.n_recorded_layers
+            });
+
+            if !is_valid
+                || !replaced_in_valid
+                || time >= looper.loop_len
+                || layer >= looper.n_recorded_layers
+            {
+                return Err(SessionError::Malformed);
+            }
+
+            looper
+                .recorded
+                .push(RecordedMidiMsg {
+                    time,
+                    event,
+                    layer,
+                    replaced_in,
+                })
+                .map_err(|_| SessionError::Malformed)?;
+        }
+
+        if !looper.recorded.is_empty() {
+            looper.rec_start = Some(now);
+        }
+
+        looper.sounding_notes = self.sounding_notes;
+        looper.release_all = true;
+        *self = looper;
+
+        Ok(())
+    }
+}
+
+fn param_to_parts(param: Param) -> (u8, u16) {
+    match param {
+        Param::Cc(control) => (0, control as u16),
+        Param::Cc14(control) => (1, control as u16),
+        Param::Nrpn(number) => (2, number),
+        Param::Rpn(number) => (3, number),
+        Param::PitchBend => (4, 0),
+        Param::ChannelPressure => (5, 0),
+        Param::PolyPressure(note) => (6, note as u16),
+    }
+}
+
+fn param_from_parts(kind: u8, number: u16) -> Result<Param, SessionError> {
+    let byte = || u8::try_from(number).map_err(|_| SessionError::Malformed);
+
+    match kind {
+        0 => Ok(Param::Cc(byte()?)),
+        1 => Ok(Param::Cc14(byte()?)),
+        2 => Ok(Param::Nrpn(number)),
+        3 => Ok(Param::Rpn(number)),
+        4 => Ok(Param::PitchBend),
+        5 => Ok(Param::ChannelPressure),
+        6 => Ok(Param::PolyPressure(byte()?)),
+        _ => Err(SessionError::Malformed),
+    }
+}
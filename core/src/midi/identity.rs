@@ -0,0 +1,120 @@
+use super::is_data_byte;
+
+/// Sub-ID of Universal Non-Realtime SysEx
+pub const SYSEX_NON_REALTIME: u8 = 0x7E;
+/// SysEx device ID every device listens to
+pub const SYSEX_ALL_DEVICES: u8 = 0x7F;
+
+const GENERAL_INFORMATION: u8 = 0x06;
+const IDENTITY_REQUEST: u8 = 0x01;
+const IDENTITY_REPLY: u8 = 0x02;
+
+/// family, member and version following the manufacturer ID
+const IDENTITY_FIELDS_LEN: usize = 8;
+/// Identity Reply with a one byte manufacturer ID
+const IDENTITY_REPLY_MIN_LEN: usize = 5 + IDENTITY_FIELDS_LEN;
+
+/// Payload of an Identity Request that every device on the port answers
+pub const IDENTITY_REQUEST_PAYLOAD: [u8; 4] = [
+    SYSEX_NON_REALTIME,
+    SYSEX_ALL_DEVICES,
+    GENERAL_INFORMATION,
+    IDENTITY_REQUEST,
+];
+
+/// MIDI manufacturer ID, three byte IDs start with `0x00` which is left out here
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManufacturerId {
+    Short(u8),
+    Extended(u8, u8),
+}
+
+impl ManufacturerId {
+    /// Fits into the data bytes of an Identity Reply, a one byte ID can't be `0x00`
+    pub const fn is_valid(&self) -> bool {
+        match *self {
+            ManufacturerId::Short(id) => id != 0 && is_data_byte(id),
+            ManufacturerId::Extended(id_1, id_2) => is_data_byte(id_1) && is_data_byte(id_2),
+        }
+    }
+}
+
+/// Roland Corporation
+pub const MANUFACTURER_ROLAND: ManufacturerId = ManufacturerId::Short(0x41);
+
+/// What a device answers to an Identity Request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Identity {
+    /// SysEx device ID of the unit that answered, tells identical units apart
+    pub device_id: u8,
+    pub manufacturer: ManufacturerId,
+    /// model family, the two bytes of the reply are read LSB first
+    pub family: u16,
+    /// model within the family, the two bytes of the reply are read LSB first
+    pub member: u16,
+    /// software revision as sent
+    pub version: [u8; 4],
+}
+
+impl Identity {
+    /// Reads the payload of an Identity Reply, without `0xF0` and `0xF7`
+    ///
+    /// Some devices append bytes after the version, those are ignored.
+    pub fn from_reply(payload: &[u8]) -> Option<Self> {
+        if payload.len() < IDENTITY_REPLY_MIN_LEN
+            || payload[0] != SYSEX_NON_REALTIME
+            || payload[2] != GENERAL_INFORMATION
+            || payload[3] != IDENTITY_REPLY
+            || !payload.iter().all(|byte| is_data_byte(*byte))
+        {
+            return None;
+        }
+
+        let (manufacturer, offset) = match payload[4] {
+            0x00 => (ManufacturerId::Extended(payload[5], payload[6]), 7),
+            id => (ManufacturerId::Short(id), 5),
+        };
+
+        let fields = payload.get(offset..offset + IDENTITY_FIELDS_LEN)?;
+
+        Some(Self {
+            device_id: payload[1],
+            manufacturer,
+            family: u16::from_le_bytes([fields[0], fields[1]]),
+            member: u16::from_le_bytes([fields[2], fields[3]]),
+            version: [fields[4], fields[5], fields[6], fields[7]],
+        })
+    }
+}
+
+/// Two data bytes, like family and member of an Identity Reply
+const fn is_data_word(word: u16) -> bool {
+    word & 0x8080 == 0
+}
+
+/// Identity a device is recognised by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdentityMatch {
+    pub manufacturer: ManufacturerId,
+    pub family: u16,
+    /// `None` accepts every model of the family
+    pub member: Option<u16>,
+}
+
+impl IdentityMatch {
+    /// Every value can be sent in an Identity Reply, family and member are two data bytes read LSB first
+    pub const fn is_valid(&self) -> bool {
+        self.manufacturer.is_valid()
+            && is_data_word(self.family)
+            && match self.member {
+                Some(member) => is_data_word(member),
+                None => true,
+            }
+    }
+
+    pub fn matches(&self, identity: &Identity) -> bool {
+        self.manufacturer == identity.manufacturer
+            && self.family == identity.family
+            && self.member.is_none_or(|member| member == identity.member)
+    }
+}
@@ -0,0 +1,295 @@
+use core::fmt;
+
+use heapless::Vec;
+
+use super::*;
+
+/// Every MIDI 1.0 message except System Exclusive, which does not fit into three bytes
+///
+/// Channels are zero based (0..=15), data bytes are 7 bit and 14 bit values are combined from LSB and MSB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiMessage {
+    // channel voice
+    /// a Note On with velocity 0 is kept as such, see `is_note_off`
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    PolyAftertouch {
+        channel: u8,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        control: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelAftertouch {
+        channel: u8,
+        pressure: u8,
+    },
+    /// 14 bit with the center at `PITCH_BEND_CENTER`
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+
+    // system common
+    /// message type in the upper and value in the lower nibble
+    MtcQuarterFrame(u8),
+    /// in sixteenths since the start of the song, 14 bit
+    SongPosition(u16),
+    SongSelect(u8),
+    TuneRequest,
+
+    // system real-time
+    TimingClock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    SystemReset,
+}
+
+impl MidiMessage {
+    /// Parses one complete message, `bytes` has to start with a status byte and may be longer than the message
+    ///
+    /// Returns `None` for System Exclusive, undefined status bytes, missing or invalid data bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let status = *bytes.first()?;
+        let len = message_len(status)?;
+        let data = bytes.get(1..len)?;
+
+        if data.iter().any(|byte| !is_data_byte(*byte)) {
+            return None;
+        }
+
+        Self::from_status_and_data(status, data)
+    }
+
+    /// Builds a message from a status byte and exactly the number of data bytes it requires
+    ///
+    /// Data bytes are expected to be valid already.
+    pub(crate) fn from_status_and_data(status: u8, data: &[u8]) -> Option<Self> {
+        let channel = status & 0x0F;
+        let data1 = data.first().copied().unwrap_or(0);
+        let data2 = data.get(1).copied().unwrap_or(0);
+
+        let message = match status & 0xF0 {
+            MIDI_NOTE_OFF => Self::NoteOff {
+                channel,
+                note: data1,
+                velocity: data2,
+            },
+            MIDI_NOTE_ON => Self::NoteOn {
+                channel,
+                note: data1,
+                velocity: data2,
+            },
+            MIDI_POLY_AFTERTOUCH => Self::PolyAftertouch {
+                channel,
+                note: data1,
+                pressure: data2,
+            },
+            MIDI_CONTORL_CHANGE => Self::ControlChange {
+                channel,
+                control: data1,
+                value: data2,
+            },
+            MIDI_PROGRAM_CHANGE => Self::ProgramChange {
+                channel,
+                program: data1,
+            },
+            MIDI_CHANNEL_AFTERTOUCH => Self::ChannelAftertouch {
+                channel,
+                pressure: data1,
+            },
+            MIDI_PITCH_BEND => Self::PitchBend {
+                channel,
+                value: combine_14_bit(data1, data2),
+            },
+            _ => match status {
+                MIDI_MTC_QUARTER_FRAME => Self::MtcQuarterFrame(data1),
+                MIDI_SONG_POSITION => Self::SongPosition(combine_14_bit(data1, data2)),
+                MIDI_SONG_SELECT => Self::SongSelect(data1),
+                MIDI_TUNE_REQUEST => Self::TuneRequest,
+                MIDI_CLOCK => Self::TimingClock,
+                MIDI_START => Self::Start,
+                MIDI_CONTINUE => Self::Continue,
+                MIDI_STOP => Self::Stop,
+                MIDI_ACTIVE_SENSING => Self::ActiveSensing,
+                MIDI_SYSTEM_RESET => Self::SystemReset,
+                _ => return None,
+            },
+        };
+
+        Some(message)
+    }
+
+    /// Serialises the message into 1 to 3 bytes
+    pub fn to_bytes(&self) -> Vec<u8, 3> {
+        let status = self.status();
+        let mut bytes = Vec::new();
+
+        bytes.push(status).ok();
+
+        match *self {
+            Self::NoteOn { note, velocity, .. } | Self::NoteOff { note, velocity, .. } => {
+                bytes.extend_from_slice(&[note, velocity]).ok();
+            }
+            Self::PolyAftertouch { note, pressure, .. } => {
+                bytes.extend_from_slice(&[note, pressure]).ok();
+            }
+            Self::ControlChange { control, value, .. } => {
+                bytes.extend_from_slice(&[control, value]).ok();
+            }
+            Self::ProgramChange { program, .. } => {
+                bytes.push(program).ok();
+            }
+            Self::ChannelAftertouch { pressure, .. } => {
+                bytes.push(pressure).ok();
+            }
+            Self::PitchBend { value, .. } | Self::SongPosition(value) => {
+                bytes.extend_from_slice(&split_14_bit(value)).ok();
+            }
+            Self::MtcQuarterFrame(data) | Self::SongSelect(data) => {
+                bytes.push(data).ok();
+            }
+            _ => {}
+        }
+
+        bytes
+    }
+
+    /// Status byte including the channel
+    pub fn status(&self) -> u8 {
+        let (message_type, channel) = match *self {
+            Self::NoteOff { channel, .. } => (MIDI_NOTE_OFF, channel),
+            Self::NoteOn { channel, .. } => (MIDI_NOTE_ON, channel),
+            Self::PolyAftertouch { channel, .. } => (MIDI_POLY_AFTERTOUCH, channel),
+            Self::ControlChange { channel, .. } => (MIDI_CONTORL_CHANGE, channel),
+            Self::ProgramChange { channel, .. } => (MIDI_PROGRAM_CHANGE, channel),
+            Self::ChannelAftertouch { channel, .. } => (MIDI_CHANNEL_AFTERTOUCH, channel),
+            Self::PitchBend { channel, .. } => (MIDI_PITCH_BEND, channel),
+            Self::MtcQuarterFrame(_) => (MIDI_MTC_QUARTER_FRAME, 0),
+            Self::SongPosition(_) => (MIDI_SONG_POSITION, 0),
+            Self::SongSelect(_) => (MIDI_SONG_SELECT, 0),
+            Self::TuneRequest => (MIDI_TUNE_REQUEST, 0),
+            Self::TimingClock => (MIDI_CLOCK, 0),
+            Self::Start => (MIDI_START, 0),
+            Self::Continue => (MIDI_CONTINUE, 0),
+            Self::Stop => (MIDI_STOP, 0),
+            Self::ActiveSensing => (MIDI_ACTIVE_SENSING, 0),
+            Self::SystemReset => (MIDI_SYSTEM_RESET, 0),
+        };
+
+        message_type | (channel & 0x0F)
+    }
+
+    /// Channel of channel voice messages, system messages have none
+    pub fn channel(&self) -> Option<u8> {
+        match *self {
+            Self::NoteOff { channel, .. }
+            | Self::NoteOn { channel, .. }
+            | Self::PolyAftertouch { channel, .. }
+            | Self::ControlChange { channel, .. }
+            | Self::ProgramChange { channel, .. }
+            | Self::ChannelAftertouch { channel, .. }
+            | Self::PitchBend { channel, .. } => Some(channel),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes including the status byte
+    pub fn n_bytes(&self) -> usize {
+        message_len(self.status()).unwrap_or(1)
+    }
+
+    /// Note Off or Note On with velocity 0
+    pub fn is_note_off(&self) -> bool {
+        matches!(
+            self,
+            Self::NoteOff { .. } | Self::NoteOn { velocity: 0, .. }
+        )
+    }
+
+    pub fn is_real_time(&self) -> bool {
+        is_real_time(self.status())
+    }
+}
+
+impl fmt::Display for MidiMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(channel) = self.channel() {
+            write!(f, "Ch {:2} ", channel + 1)?;
+        }
+
+        match *self {
+            Self::NoteOn { note, velocity, .. } => write!(f, "Note On {note} {velocity}"),
+            Self::NoteOff { note, velocity, .. } => write!(f, "Note Off {note} {velocity}"),
+            Self::PolyAftertouch { note, pressure, .. } => {
+                write!(f, "Poly Aftertouch {note} {pressure}")
+            }
+            Self::ControlChange { control, value, .. } => write!(f, "CC {control} {value}"),
+            Self::ProgramChange { program, .. } => write!(f, "Program Change {program}"),
+            Self::ChannelAftertouch { pressure, .. } => write!(f, "Aftertouch {pressure}"),
+            Self::PitchBend { value, .. } => {
+                write!(f, "Pitch Bend {}", value as i32 - PITCH_BEND_CENTER as i32)
+            }
+            Self::MtcQuarterFrame(data) => write!(f, "MTC Quarter Frame {data:02X}"),
+            Self::SongPosition(position) => write!(f, "Song Position {position}"),
+            Self::SongSelect(song) => write!(f, "Song Select {song}"),
+            Self::TuneRequest => f.write_str("Tune Request"),
+            Self::TimingClock => f.write_str("Clock"),
+            Self::Start => f.write_str("Start"),
+            Self::Continue => f.write_str("Continue"),
+            Self::Stop => f.write_str("Stop"),
+            Self::ActiveSensing => f.write_str("Active Sensing"),
+            Self::SystemReset => f.write_str("System Reset"),
+        }
+    }
+}
+
+/// Number of bytes of a message including `status`, `None` for SysEx and undefined or data bytes
+pub fn message_len(status: u8) -> Option<usize> {
+    match status {
+        0x80..=0xBF | 0xE0..=0xEF => Some(3),
+        0xC0..=0xDF => Some(2),
+        MIDI_MTC_QUARTER_FRAME | MIDI_SONG_SELECT => Some(2),
+        MIDI_SONG_POSITION => Some(3),
+        MIDI_TUNE_REQUEST => Some(1),
+        MIDI_CLOCK | MIDI_START | MIDI_CONTINUE | MIDI_STOP | MIDI_ACTIVE_SENSING
+        | MIDI_SYSTEM_RESET => Some(1),
+        _ => None,
+    }
+}
+
+pub const fn is_data_byte(byte: u8) -> bool {
+    byte & 0x80 == 0
+}
+
+/// Real-time bytes may appear anywhere, even in between the bytes of another message
+pub fn is_real_time(byte: u8) -> bool {
+    byte >= MIDI_CLOCK
+}
+
+/// Combines two 7 bit data bytes, least significant first
+pub(super) fn combine_14_bit(lsb: u8, msb: u8) -> u16 {
+    (msb as u16 & 0x7F) << 7 | (lsb as u16 & 0x7F)
+}
+
+/// Splits a 14 bit value into two data bytes, least significant first
+fn split_14_bit(value: u16) -> [u8; 2] {
+    [(value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8]
+}
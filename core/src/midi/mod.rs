@@ -0,0 +1,49 @@
+mod identity;
+mod message;
+mod param;
+mod parser;
+mod sysex;
+
+pub use identity::{
+    Identity, IdentityMatch, ManufacturerId, IDENTITY_REQUEST_PAYLOAD, MANUFACTURER_ROLAND,
+    SYSEX_ALL_DEVICES, SYSEX_NON_REALTIME,
+};
+pub use message::{is_data_byte, is_real_time, message_len, MidiMessage};
+pub use param::{
+    Decoded, Param, ParamChange, ParamDecoder, CC_DATA_ENTRY_LSB, CC_DATA_ENTRY_MSB, CC_NRPN_LSB,
+    CC_NRPN_MSB, CC_RPN_LSB, CC_RPN_MSB, LANE_CHANNEL_PRESSURE, LANE_PITCH_BEND,
+    LANE_POLY_PRESSURE, N_LANES,
+};
+pub use parser::{MidiEvent, MidiParser};
+pub use sysex::SysExBuffer;
+
+pub const N_CHANNELS: usize = 16;
+pub const N_NOTES: usize = 128;
+pub const N_CC_NUMBERS: usize = 128;
+
+pub const MIDI_CLOCK: u8 = 0xF8;
+pub const MIDI_START: u8 = 0xFA;
+pub const MIDI_CONTINUE: u8 = 0xFB;
+pub const MIDI_STOP: u8 = 0xFC;
+pub const MIDI_ACTIVE_SENSING: u8 = 0xFE;
+pub const MIDI_SYSTEM_RESET: u8 = 0xFF;
+
+pub const MIDI_SYSEX: u8 = 0xF0;
+pub const MIDI_MTC_QUARTER_FRAME: u8 = 0xF1;
+pub const MIDI_SONG_POSITION: u8 = 0xF2;
+pub const MIDI_SONG_SELECT: u8 = 0xF3;
+pub const MIDI_TUNE_REQUEST: u8 = 0xF6;
+pub const MIDI_SYSEX_END: u8 = 0xF7;
+
+pub const MIDI_NOTE_ON: u8 = 0x90;
+pub const MIDI_NOTE_OFF: u8 = 0x80;
+pub const MIDI_POLY_AFTERTOUCH: u8 = 0xA0;
+pub const MIDI_CONTORL_CHANGE: u8 = 0xB0;
+pub const MIDI_PROGRAM_CHANGE: u8 = 0xC0;
+pub const MIDI_CHANNEL_AFTERTOUCH: u8 = 0xD0;
+pub const MIDI_PITCH_BEND: u8 = 0xE0;
+
+/// 14 bit pitch bend value without any bend
+pub const PITCH_BEND_CENTER: u16 = 0x2000;
+
+pub type MidiMsg = [u8; 3];
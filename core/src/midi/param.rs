@@ -0,0 +1,292 @@
+use heapless::Vec;
+
+use super::{message::combine_14_bit, *};
+
+pub const CC_DATA_ENTRY_MSB: u8 = 6;
+pub const CC_DATA_ENTRY_LSB: u8 = 38;
+pub const CC_NRPN_LSB: u8 = 98;
+pub const CC_NRPN_MSB: u8 = 99;
+pub const CC_RPN_LSB: u8 = 100;
+pub const CC_RPN_MSB: u8 = 101;
+
+/// Looper lanes of the channel messages that are not control changes, they come after the ones of each CC number
+pub const LANE_PITCH_BEND: u8 = N_CC_NUMBERS as u8;
+pub const LANE_CHANNEL_PRESSURE: u8 = LANE_PITCH_BEND + 1;
+pub const LANE_POLY_PRESSURE: u8 = LANE_PITCH_BEND + 2;
+pub const N_LANES: usize = LANE_POLY_PRESSURE as usize + 1;
+
+/// Controllers below this number have an LSB controller at `control + CC_LSB_OFFSET`
+const N_CC_MSB: u8 = 32;
+const CC_LSB_OFFSET: u8 = 32;
+
+/// Parameter of a device that is changed by one or a group of CC messages, or by a performance gesture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Param {
+    /// plain 7 bit control change
+    Cc(u8),
+    /// 14 bit control change, the MSB controller number (0..32) followed by its LSB controller
+    Cc14(u8),
+    /// Non-Registered Parameter Number with a 14 bit value
+    Nrpn(u16),
+    /// Registered Parameter Number with a 14 bit value
+    Rpn(u16),
+    /// 14 bit with the center at `PITCH_BEND_CENTER`
+    PitchBend,
+    /// 7 bit channel aftertouch
+    ChannelPressure,
+    /// 7 bit aftertouch of one note
+    PolyPressure(u8),
+}
+
+impl Param {
+    /// CC number of the looper lane the parameter is recorded in
+    ///
+    /// 14 bit CC share the lane of their MSB controller, (N)RPN share the one of Data Entry. Pitch bend and
+    /// aftertouch have lanes of their own after the CC numbers, poly aftertouch of all notes shares one.
+    pub fn lane(&self) -> u8 {
+        match *self {
+            Param::Cc(control) | Param::Cc14(control) => control,
+            Param::Nrpn(_) | Param::Rpn(_) => CC_DATA_ENTRY_MSB,
+            Param::PitchBend => LANE_PITCH_BEND,
+            Param::ChannelPressure => LANE_CHANNEL_PRESSURE,
+            Param::PolyPressure(_) => LANE_POLY_PRESSURE,
+        }
+    }
+
+    /// Same parameter, no matter if a CC has been sent with 7 or 14 bit
+    pub fn is_same_as(&self, other: &Param) -> bool {
+        match (*self, *other) {
+            (Param::Cc(a) | Param::Cc14(a), Param::Cc(b) | Param::Cc14(b)) => a == b,
+            _ => self == other,
+        }
+    }
+}
+
+/// New value of a parameter, 7 bit for `Param::Cc` and aftertouch and 14 bit otherwise
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamChange {
+    pub param: Param,
+    pub value: u16,
+}
+
+impl ParamChange {
+    pub fn cc(control: u8, value: u8) -> Self {
+        Self {
+            param: Param::Cc(control),
+            value: value as u16,
+        }
+    }
+
+    /// Parameter number and value fit into the messages of the parameter
+    pub fn is_valid(&self) -> bool {
+        let (number_valid, max_value) = match self.param {
+            Param::Cc(control) => (control < 128, 0x7F),
+            Param::Cc14(control) => (control < CC_LSB_OFFSET, 0x3FFF),
+            Param::Nrpn(number) | Param::Rpn(number) => (number <= 0x3FFF, 0x3FFF),
+            Param::PitchBend => (true, 0x3FFF),
+            Param::ChannelPressure => (true, 0x7F),
+            Param::PolyPressure(note) => (note < 128, 0x7F),
+        };
+
+        number_valid && self.value <= max_value
+    }
+
+    /// Messages in the order they have to be sent, MSB before LSB and parameter number before value
+    pub fn to_messages(&self, channel: u8) -> Vec<MidiMessage, 4> {
+        let cc = |control: u8, value: u8| MidiMessage::ControlChange {
+            channel,
+            control,
+            value: value & 0x7F,
+        };
+        let msb = |value: u16| (value >> 7) as u8;
+        let lsb = |value: u16| (value & 0x7F) as u8;
+
+        let mut messages = Vec::new();
+
+        match self.param {
+            Param::Cc(control) => {
+                messages.push(cc(control, self.value as u8)).ok();
+            }
+            Param::Cc14(control) => {
+                messages.push(cc(control, msb(self.value))).ok();
+                messages
+                    .push(cc(control + CC_LSB_OFFSET, lsb(self.value)))
+                    .ok();
+            }
+            Param::Nrpn(number) | Param::Rpn(number) => {
+                let (msb_control, lsb_control) = match self.param {
+                    Param::Nrpn(_) => (CC_NRPN_MSB, CC_NRPN_LSB),
+                    _ => (CC_RPN_MSB, CC_RPN_LSB),
+                };
+
+                messages.push(cc(msb_control, msb(number))).ok();
+                messages.push(cc(lsb_control, lsb(number))).ok();
+                messages.push(cc(CC_DATA_ENTRY_MSB, msb(self.value))).ok();
+                messages.push(cc(CC_DATA_ENTRY_LSB, lsb(self.value))).ok();
+            }
+            Param::PitchBend => {
+                messages
+                    .push(MidiMessage::PitchBend {
+                        channel,
+                        value: self.value & 0x3FFF,
+                    })
+                    .ok();
+            }
+            Param::ChannelPressure => {
+                messages
+                    .push(MidiMessage::ChannelAftertouch {
+                        channel,
+                        pressure: self.value as u8 & 0x7F,
+                    })
+                    .ok();
+            }
+            Param::PolyPressure(note) => {
+                messages
+                    .push(MidiMessage::PolyAftertouch {
+                        channel,
+                        note,
+                        pressure: self.value as u8 & 0x7F,
+                    })
+                    .ok();
+            }
+        }
+
+        messages
+    }
+}
+
+/// What a control change means within the messages before it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoded {
+    /// a new parameter value
+    Change(ParamChange),
+    /// the LSB completing the value that has been decoded just before
+    Refine(ParamChange),
+    /// part of a transaction that does not change a value on its own, like selecting an (N)RPN
+    Incomplete,
+}
+
+/// Turns the control changes of one channel into parameter changes
+///
+/// A 14 bit MSB is a plain 7 bit CC until its LSB follows directly. An LSB on its own reuses the last MSB, which
+/// devices may skip if it did not change. (N)RPN values are sent through Data Entry after selecting a parameter.
+#[derive(Clone, Debug, Default)]
+pub struct ParamDecoder {
+    /// last MSB of each 14 bit controller
+    msb: [Option<u8>; N_CC_MSB as usize],
+    /// controller of the previous message, an LSB only refines the value if it directly follows its MSB
+    last_control: Option<u8>,
+
+    /// parameter number of the selected NRPN or RPN
+    param_number: [Option<u8>; 2],
+    selected_rpn: bool,
+    data_msb: Option<u8>,
+}
+
+impl ParamDecoder {
+    pub const fn new() -> Self {
+        Self {
+            msb: [None; N_CC_MSB as usize],
+            last_control: None,
+
+            param_number: [None; 2],
+            selected_rpn: false,
+            data_msb: None,
+        }
+    }
+
+    pub fn decode(&mut self, control: u8, value: u8) -> Decoded {
+        let last_control = self.last_control.replace(control);
+
+        match control {
+            CC_NRPN_MSB | CC_RPN_MSB => {
+                self.select(control == CC_RPN_MSB, 0, value);
+                Decoded::Incomplete
+            }
+
+            CC_NRPN_LSB | CC_RPN_LSB => {
+                self.select(control == CC_RPN_LSB, 1, value);
+                Decoded::Incomplete
+            }
+
+            CC_DATA_ENTRY_MSB if self.selected_param().is_some() => {
+                self.data_msb = Some(value);
+
+                Decoded::Change(ParamChange {
+                    param: self.selected_param().unwrap(),
+                    value: (value as u16) << 7,
+                })
+            }
+
+            CC_DATA_ENTRY_LSB if self.selected_param().is_some() => {
+                let change = ParamChange {
+                    param: self.selected_param().unwrap(),
+                    value: combine_14_bit(value, self.data_msb.unwrap_or(0)),
+                };
+
+                if last_control == Some(CC_DATA_ENTRY_MSB) {
+                    Decoded::Refine(change)
+                } else {
+                    Decoded::Change(change)
+                }
+            }
+
+            0..N_CC_MSB => {
+                self.msb[control as usize] = Some(value);
+                Decoded::Change(ParamChange::cc(control, value))
+            }
+
+            CC_LSB_OFFSET..64 => {
+                let msb_control = control - CC_LSB_OFFSET;
+
+                match self.msb[msb_control as usize] {
+                    Some(msb) => {
+                        let change = ParamChange {
+                            param: Param::Cc14(msb_control),
+                            value: combine_14_bit(value, msb),
+                        };
+
+                        if last_control == Some(msb_control) {
+                            Decoded::Refine(change)
+                        } else {
+                            Decoded::Change(change)
+                        }
+                    }
+                    // no MSB yet, so it is a plain controller after all
+                    None => Decoded::Change(ParamChange::cc(control, value)),
+                }
+            }
+
+            _ => Decoded::Change(ParamChange::cc(control, value)),
+        }
+    }
+
+    fn select(&mut self, rpn: bool, byte: usize, value: u8) {
+        if rpn != self.selected_rpn {
+            self.param_number = [None; 2];
+            self.selected_rpn = rpn;
+        }
+
+        self.param_number[byte] = Some(value);
+        self.data_msb = None;
+    }
+
+    fn selected_param(&self) -> Option<Param> {
+        let [Some(msb), Some(lsb)] = self.param_number else {
+            return None;
+        };
+
+        // RPN null deselects
+        if self.selected_rpn && msb == 0x7F && lsb == 0x7F {
+            return None;
+        }
+
+        let number = combine_14_bit(lsb, msb);
+
+        if self.selected_rpn {
+            Some(Param::Rpn(number))
+        } else {
+            Some(Param::Nrpn(number))
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use heapless::Vec;
+
+use super::*;
+
+/// Output of the `MidiParser`
+///
+/// System Exclusive is passed through byte by byte, so the parser does not need a buffer for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiEvent {
+    Message(MidiMessage),
+    SysExStart,
+    /// data byte in between `SysExStart` and `SysExEnd`
+    SysExByte(u8),
+    /// `complete` is false if another status byte interrupted the SysEx instead of End of Exclusive
+    SysExEnd {
+        complete: bool,
+    },
+}
+
+/// Byte at a time MIDI parser, e.g. for a serial DIN connection
+///
+/// - running status: data bytes without a status byte reuse the last channel status
+/// - real-time bytes are passed through immediately, even in between the bytes of another message
+/// - SysEx is framed into start, data bytes and end
+///
+/// Stray data bytes and undefined status bytes are dropped.
+#[derive(Clone, Debug, Default)]
+pub struct MidiParser {
+    /// status of the message being received, kept as running status for channel messages
+    status: Option<u8>,
+    data: [u8; 2],
+    n_data: usize,
+    in_sysex: bool,
+}
+
+impl MidiParser {
+    pub const fn new() -> Self {
+        Self {
+            status: None,
+            data: [0; 2],
+            n_data: 0,
+            in_sysex: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Must be called for every incoming byte in order
+    ///
+    /// Yields at most two events, if a status byte ends a SysEx and completes a message at the same time.
+    pub fn push(&mut self, byte: u8) -> Vec<MidiEvent, 2> {
+        let mut events = Vec::new();
+
+        if is_real_time(byte) {
+            if let Some(message) = MidiMessage::from_status_and_data(byte, &[]) {
+                events.push(MidiEvent::Message(message)).ok();
+            }
+
+            return events;
+        }
+
+        if is_data_byte(byte) {
+            if let Some(event) = self.push_data(byte) {
+                events.push(event).ok();
+            }
+
+            return events;
+        }
+
+        // any other status byte ends a SysEx
+        if self.in_sysex {
+            self.in_sysex = false;
+
+            events
+                .push(MidiEvent::SysExEnd {
+                    complete: byte == MIDI_SYSEX_END,
+                })
+                .ok();
+        }
+
+        self.status = None;
+        self.n_data = 0;
+
+        match byte {
+            MIDI_SYSEX => {
+                self.in_sysex = true;
+                events.push(MidiEvent::SysExStart).ok();
+            }
+
+            _ => match message_len(byte) {
+                Some(1) => {
+                    if let Some(message) = MidiMessage::from_status_and_data(byte, &[]) {
+                        events.push(MidiEvent::Message(message)).ok();
+                    }
+                }
+                Some(_) => self.status = Some(byte),
+                // End of Exclusive without SysEx and undefined status bytes
+                None => {}
+            },
+        }
+
+        events
+    }
+
+    fn push_data(&mut self, byte: u8) -> Option<MidiEvent> {
+        if self.in_sysex {
+            return Some(MidiEvent::SysExByte(byte));
+        }
+
+        let status = self.status?;
+        let n_data = message_len(status)? - 1;
+
+        self.data[self.n_data] = byte;
+        self.n_data += 1;
+
+        if self.n_data < n_data {
+            return None;
+        }
+
+        self.n_data = 0;
+
+        // system common messages have no running status
+        if status >= MIDI_SYSEX {
+            self.status = None;
+        }
+
+        MidiMessage::from_status_and_data(status, &self.data[..n_data]).map(MidiEvent::Message)
+    }
+}
@@ -0,0 +1,117 @@
+use core::fmt;
+
+use heapless::Vec;
+
+use super::*;
+
+/// System Exclusive message with a payload of up to `N` bytes, without the framing `0xF0` and `0xF7`
+///
+/// Reassembles incoming SysEx from `MidiEvent`s. Longer messages are cut off and marked as truncated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SysExBuffer<const N: usize> {
+    payload: Vec<u8, N>,
+    truncated: bool,
+    receiving: bool,
+}
+
+impl<const N: usize> SysExBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            payload: Vec::new(),
+            truncated: false,
+            receiving: false,
+        }
+    }
+
+    /// Returns `None` if the payload is too long or contains status bytes
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        if !payload.iter().all(|byte| is_data_byte(*byte)) {
+            return None;
+        }
+
+        Some(Self {
+            payload: Vec::from_slice(payload).ok()?,
+            truncated: false,
+            receiving: false,
+        })
+    }
+
+    /// Must be called with every SysEx event of the parser, returns true once a SysEx has been received completely
+    ///
+    /// SysEx interrupted by another status byte is discarded.
+    pub fn push(&mut self, event: &MidiEvent) -> bool {
+        match *event {
+            MidiEvent::SysExStart => {
+                self.clear();
+                self.receiving = true;
+            }
+
+            MidiEvent::SysExByte(byte) if self.receiving => {
+                self.truncated |= self.payload.push(byte).is_err();
+            }
+
+            MidiEvent::SysExEnd { complete } if self.receiving => {
+                self.receiving = false;
+
+                if complete {
+                    return true;
+                }
+
+                self.clear();
+            }
+
+            _ => {}
+        }
+
+        false
+    }
+
+    pub fn clear(&mut self) {
+        self.payload.clear();
+        self.truncated = false;
+        self.receiving = false;
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// More bytes have been received than fit into the buffer
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Number of bytes on the wire including `0xF0` and `0xF7`
+    pub fn n_bytes(&self) -> usize {
+        self.payload.len() + 2
+    }
+
+    /// Appends the whole message including its framing, returns false if it does not fit into `bytes`
+    pub fn write_to<const M: usize>(&self, bytes: &mut Vec<u8, M>) -> bool {
+        if bytes.capacity() - bytes.len() < self.n_bytes() {
+            return false;
+        }
+
+        bytes.push(MIDI_SYSEX).ok();
+        bytes.extend_from_slice(&self.payload).ok();
+        bytes.push(MIDI_SYSEX_END).ok();
+
+        true
+    }
+}
+
+impl<const N: usize> fmt::Display for SysExBuffer<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SysEx {MIDI_SYSEX:02X}")?;
+
+        for byte in &self.payload {
+            write!(f, " {byte:02X}")?;
+        }
+
+        if self.truncated {
+            f.write_str(" ..")?;
+        }
+
+        write!(f, " {MIDI_SYSEX_END:02X}")
+    }
+}
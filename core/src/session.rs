@@ -0,0 +1,152 @@
+use core::fmt;
+
+use crate::smf::ByteSink;
+
+/// Every session starts with these bytes
+const SESSION_MAGIC: &[u8; 4] = b"UCYS";
+
+/// Layout version of sessions that are written, older versions have to stay readable
+pub const SESSION_VERSION: u8 = 2;
+
+/// Why a session could not be loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    /// the data does not start with a session header
+    NotASession,
+    /// written by a newer version of uncycle
+    UnsupportedVersion(u8),
+    /// the data is cut off or contains invalid values
+    Malformed,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::NotASession => write!(f, "not an uncycle session"),
+            SessionError::UnsupportedVersion(version) => {
+                write!(f, "session version {} is not supported", version)
+            }
+            SessionError::Malformed => write!(f, "session is damaged"),
+        }
+    }
+}
+
+/// Encodes a session as compact little endian binary, small enough to be kept in flash
+pub struct SessionWriter<'a, 'b> {
+    out: &'a mut ByteSink<'b>,
+}
+
+impl<'a, 'b> SessionWriter<'a, 'b> {
+    /// Starts a session of the current version
+    pub fn new(out: &'a mut ByteSink<'b>) -> Self {
+        out(SESSION_MAGIC);
+        out(&[SESSION_VERSION]);
+
+        Self { out }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        (self.out)(&[value]);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        (self.out)(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        (self.out)(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        (self.out)(&value.to_le_bytes());
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    /// Up to 255 bytes preceded by their length
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        let len = bytes.len().min(u8::MAX as usize);
+
+        self.u8(len as u8);
+        (self.out)(&bytes[..len]);
+    }
+}
+
+/// Decodes what `SessionWriter` has written
+pub struct SessionReader<'a> {
+    bytes: &'a [u8],
+    version: u8,
+}
+
+impl<'a> SessionReader<'a> {
+    /// Checks the header, the version of the session can be asked for afterwards
+    pub fn new(bytes: &'a [u8]) -> Result<Self, SessionError> {
+        let Some(bytes) = bytes.strip_prefix(SESSION_MAGIC) else {
+            return Err(SessionError::NotASession);
+        };
+
+        let mut reader = Self { bytes, version: 0 };
+        let version = reader.u8()?;
+
+        if version == 0 || version > SESSION_VERSION {
+            return Err(SessionError::UnsupportedVersion(version));
+        }
+
+        reader.version = version;
+
+        Ok(reader)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SessionError> {
+        let (bytes, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(SessionError::Malformed)?;
+        self.bytes = rest;
+
+        Ok(*bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, SessionError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, SessionError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, SessionError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, SessionError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, SessionError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SessionError::Malformed),
+        }
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], SessionError> {
+        let len = self.u8()? as usize;
+
+        if self.bytes.len() < len {
+            return Err(SessionError::Malformed);
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(bytes)
+    }
+}
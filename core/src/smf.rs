@@ -0,0 +1,411 @@
+use core::fmt;
+
+use crate::{
+    clock::PPQN,
+    looper::{LoopEvent, SUBTICKS_PER_TICK},
+    midi::{is_data_byte, message_len, MidiMessage, MIDI_SYSEX, MIDI_SYSEX_END},
+};
+
+/// Resolution of exported files in ticks per quarter note, a multiple of the MIDI clock so that steps stay exact
+pub const SMF_PPQ: u16 = 480;
+
+const META_EVENT: u8 = 0xFF;
+const META_TRACK_NAME: u8 = 0x03;
+const META_END_OF_TRACK: u8 = 0x2F;
+const META_TEMPO: u8 = 0x51;
+const META_TIME_SIGNATURE: u8 = 0x58;
+
+const TRACK_NAME: &[u8] = b"uncycle";
+
+/// Receives the file in chunks of bytes
+pub type ByteSink<'a> = dyn FnMut(&[u8]) + 'a;
+
+/// Why a Standard MIDI File could not be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmfError {
+    /// the file does not start with a header chunk
+    NotAnSmf,
+    /// Type 2 files consist of independent patterns instead of one song
+    UnsupportedFormat,
+    /// a chunk or an event is cut off or invalid
+    Malformed,
+    /// there is no event that can be looped
+    Empty,
+}
+
+impl fmt::Display for SmfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmfError::NotAnSmf => write!(f, "not a Standard MIDI File"),
+            SmfError::UnsupportedFormat => write!(f, "Type 2 files are not supported"),
+            SmfError::Malformed => write!(f, "file is damaged"),
+            SmfError::Empty => write!(f, "file has nothing that can be looped"),
+        }
+    }
+}
+
+/// Event of a track, `time` is counted from the start of the file in sub-ticks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmfEvent {
+    Message {
+        time: u64,
+        message: MidiMessage,
+    },
+    /// only reported, its content is skipped
+    SysEx {
+        time: u64,
+    },
+    EndOfTrack {
+        time: u64,
+    },
+}
+
+/// What has been imported from a Standard MIDI File
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SmfImport {
+    /// events that are part of the recording, a note with its note on and off counts as one
+    pub n_events: usize,
+    /// events that cannot be looped, like program changes or SysEx
+    pub n_unsupported: usize,
+    /// events that did not fit into the recording, notes are counted once as well
+    pub n_dropped: usize,
+    /// the file is longer than the longest loop, so its end has been cut off
+    pub truncated: bool,
+    pub loop_steps: u16,
+}
+
+/// Layout of a Standard MIDI File
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmfFormat {
+    /// Type 0, tempo and events share one track
+    SingleTrack,
+    /// Type 1, a tempo track followed by a track with the events
+    MultiTrack,
+}
+
+/// Standard MIDI File of one loop cycle
+///
+/// Looper times are converted from sub-ticks to `SMF_PPQ`, the track ends with the loop so that it can be looped in
+/// a DAW as is.
+pub struct Smf<'a> {
+    pub format: SmfFormat,
+    pub bpm: f32,
+    /// channel the events are written on
+    pub channel: u8,
+    /// in sub-ticks
+    pub loop_len: u32,
+    /// ordered by loop time in sub-ticks
+    pub events: &'a [(u32, LoopEvent)],
+}
+
+impl Smf<'_> {
+    /// Hands the file to `out`
+    pub fn write(&self, out: &mut ByteSink<'_>) {
+        let n_tracks: u16 = match self.format {
+            SmfFormat::SingleTrack => 1,
+            SmfFormat::MultiTrack => 2,
+        };
+
+        out(b"MThd");
+        out(&6u32.to_be_bytes());
+        out(&(n_tracks - 1).to_be_bytes());
+        out(&n_tracks.to_be_bytes());
+        out(&SMF_PPQ.to_be_bytes());
+
+        match self.format {
+            SmfFormat::SingleTrack => write_track(out, &|out| {
+                self.write_tempo(out);
+                self.write_events(out);
+            }),
+            SmfFormat::MultiTrack => {
+                write_track(out, &|out| {
+                    self.write_tempo(out);
+                    write_end_of_track(out, self.end());
+                });
+                write_track(out, &|out| self.write_events(out));
+            }
+        }
+    }
+
+    /// Track name, tempo and a 4/4 time signature at the start
+    fn write_tempo(&self, out: &mut ByteSink<'_>) {
+        let us_per_quarter = (60_000_000.0 / self.bpm + 0.5) as u32;
+
+        write_meta(out, 0, META_TRACK_NAME, TRACK_NAME);
+        write_meta(out, 0, META_TEMPO, &us_per_quarter.to_be_bytes()[1..]);
+        // 4/4 with a metronome click every quarter and 8 32nds per quarter
+        write_meta(out, 0, META_TIME_SIGNATURE, &[4, 2, PPQN as u8, 8]);
+    }
+
+    /// Events followed by the end of the loop
+    fn write_events(&self, out: &mut ByteSink<'_>) {
+        let mut last = 0;
+
+        for (time, event) in self.events {
+            let ticks = self.to_ticks(*time);
+
+            for message in event.to_messages(self.channel) {
+                write_var_len(out, ticks - last);
+                out(&message.to_bytes());
+                last = ticks;
+            }
+        }
+
+        write_end_of_track(out, self.end() - last);
+    }
+
+    /// Loop length in file ticks
+    fn end(&self) -> u32 {
+        self.to_ticks(self.loop_len)
+    }
+
+    /// Rounds a loop time in sub-ticks to the nearest file tick
+    fn to_ticks(&self, time: u32) -> u32 {
+        let subticks_per_quarter = (PPQN * SUBTICKS_PER_TICK) as u64;
+
+        ((time as u64 * SMF_PPQ as u64 + subticks_per_quarter / 2) / subticks_per_quarter) as u32
+    }
+}
+
+/// Writes a track chunk, `body` is called twice since the chunk starts with its length
+fn write_track(out: &mut ByteSink<'_>, body: &dyn Fn(&mut ByteSink<'_>)) {
+    let mut len = 0u32;
+    body(&mut |bytes: &[u8]| len += bytes.len() as u32);
+
+    out(b"MTrk");
+    out(&len.to_be_bytes());
+    body(out);
+}
+
+fn write_meta(out: &mut ByteSink<'_>, delta: u32, kind: u8, data: &[u8]) {
+    write_var_len(out, delta);
+    out(&[META_EVENT, kind]);
+    write_var_len(out, data.len() as u32);
+    out(data);
+}
+
+fn write_end_of_track(out: &mut ByteSink<'_>, delta: u32) {
+    write_meta(out, delta, META_END_OF_TRACK, &[]);
+}
+
+/// Variable length quantity with 7 bits per byte, most significant first and the top bit set on all but the last
+fn write_var_len(out: &mut ByteSink<'_>, value: u32) {
+    let mut bytes = [0u8; 5];
+    let mut i = bytes.len() - 1;
+    let mut value = value;
+
+    bytes[i] = (value & 0x7F) as u8;
+    value >>= 7;
+
+    while value > 0 {
+        i -= 1;
+        bytes[i] = (value & 0x7F) as u8 | 0x80;
+        value >>= 7;
+    }
+
+    out(&bytes[i..]);
+}
+
+/// Reads the events of every track in a Standard MIDI File, one track after another
+///
+/// Musical times are kept as they are, whatever the tempo of the file. Files that count in SMPTE frames are mapped
+/// onto `bpm` instead. Meta events other than the end of a track are skipped.
+pub fn read_smf(
+    bytes: &[u8],
+    bpm: f32,
+    on_event: &mut dyn FnMut(SmfEvent),
+) -> Result<(), SmfError> {
+    if !bytes.starts_with(b"MThd") {
+        return Err(SmfError::NotAnSmf);
+    }
+
+    let mut chunks = Chunks(bytes);
+
+    let header = match chunks.next() {
+        Some(Ok((b"MThd", header))) if header.len() >= 6 => header,
+        Some(Err(e)) => return Err(e),
+        _ => return Err(SmfError::NotAnSmf),
+    };
+
+    if u16::from_be_bytes([header[0], header[1]]) > 1 {
+        return Err(SmfError::UnsupportedFormat);
+    }
+
+    let division = Division::new(u16::from_be_bytes([header[4], header[5]]), bpm)?;
+
+    for chunk in chunks {
+        // unknown chunks have to be ignored
+        if let (b"MTrk", track) = chunk? {
+            read_track(track, &division, on_event)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits a file into chunk types and their data
+struct Chunks<'a>(&'a [u8]);
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Result<(&'a [u8; 4], &'a [u8]), SmfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let Some((kind, rest)) = self.0.split_first_chunk::<4>() else {
+            self.0 = &[];
+            return Some(Err(SmfError::Malformed));
+        };
+        let Some((len, rest)) = rest.split_first_chunk::<4>() else {
+            self.0 = &[];
+            return Some(Err(SmfError::Malformed));
+        };
+
+        let len = u32::from_be_bytes(*len) as usize;
+
+        if rest.len() < len {
+            self.0 = &[];
+            return Some(Err(SmfError::Malformed));
+        }
+
+        self.0 = &rest[len..];
+
+        Some(Ok((kind, &rest[..len])))
+    }
+}
+
+/// How file ticks relate to time
+enum Division {
+    TicksPerQuarter(u16),
+    /// sub-ticks per file tick at the tempo the file is mapped onto
+    Smpte(f32),
+}
+
+impl Division {
+    fn new(division: u16, bpm: f32) -> Result<Self, SmfError> {
+        if division & 0x8000 == 0 {
+            if division == 0 {
+                return Err(SmfError::Malformed);
+            }
+
+            return Ok(Division::TicksPerQuarter(division));
+        }
+
+        // the frame rate is stored negated
+        let frames_per_second = match ((division >> 8) as i8).checked_neg() {
+            Some(24) => 24.0,
+            Some(25) => 25.0,
+            // drop frame
+            Some(29) => 29.97,
+            Some(30) => 30.0,
+            _ => return Err(SmfError::Malformed),
+        };
+        let ticks_per_frame = (division & 0xFF) as f32;
+
+        if ticks_per_frame == 0.0 {
+            return Err(SmfError::Malformed);
+        }
+
+        let subticks_per_second = bpm / 60.0 * (PPQN * SUBTICKS_PER_TICK) as f32;
+
+        Ok(Division::Smpte(
+            subticks_per_second / (frames_per_second * ticks_per_frame),
+        ))
+    }
+
+    fn to_subticks(&self, ticks: u64) -> u64 {
+        match *self {
+            Division::TicksPerQuarter(ppq) => {
+                ticks * (PPQN * SUBTICKS_PER_TICK) as u64 / ppq as u64
+            }
+            Division::Smpte(subticks_per_tick) => (ticks as f32 * subticks_per_tick) as u64,
+        }
+    }
+}
+
+fn read_track(
+    mut track: &[u8],
+    division: &Division,
+    on_event: &mut dyn FnMut(SmfEvent),
+) -> Result<(), SmfError> {
+    let mut ticks = 0u64;
+    let mut running_status = None;
+
+    while !track.is_empty() {
+        let delta;
+        (delta, track) = read_var_len(track)?;
+        ticks += delta as u64;
+
+        let time = division.to_subticks(ticks);
+        let &first = track.first().ok_or(SmfError::Malformed)?;
+
+        match first {
+            META_EVENT => {
+                let &kind = track.get(1).ok_or(SmfError::Malformed)?;
+                let (len, data) = read_var_len(&track[2..])?;
+                track = data.get(len as usize..).ok_or(SmfError::Malformed)?;
+                running_status = None;
+
+                if kind == META_END_OF_TRACK {
+                    on_event(SmfEvent::EndOfTrack { time });
+                    return Ok(());
+                }
+            }
+
+            MIDI_SYSEX | MIDI_SYSEX_END => {
+                let (len, data) = read_var_len(&track[1..])?;
+                track = data.get(len as usize..).ok_or(SmfError::Malformed)?;
+                running_status = None;
+
+                on_event(SmfEvent::SysEx { time });
+            }
+
+            _ => {
+                let status = if is_data_byte(first) {
+                    running_status.ok_or(SmfError::Malformed)?
+                } else {
+                    track = &track[1..];
+                    first
+                };
+
+                // only channel messages are allowed in between the other events
+                if status >= MIDI_SYSEX {
+                    return Err(SmfError::Malformed);
+                }
+
+                let n_data = message_len(status).ok_or(SmfError::Malformed)? - 1;
+                let data = track.get(..n_data).ok_or(SmfError::Malformed)?;
+
+                if !data.iter().all(|byte| is_data_byte(*byte)) {
+                    return Err(SmfError::Malformed);
+                }
+
+                let message =
+                    MidiMessage::from_status_and_data(status, data).ok_or(SmfError::Malformed)?;
+                track = &track[n_data..];
+                running_status = Some(status);
+
+                on_event(SmfEvent::Message { time, message });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a variable length quantity of at most 4 bytes and returns it with the rest of `bytes`
+fn read_var_len(bytes: &[u8]) -> Result<(u32, &[u8]), SmfError> {
+    let mut value = 0u32;
+
+    for (i, byte) in bytes.iter().take(4).enumerate() {
+        value = value << 7 | (byte & 0x7F) as u32;
+
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+
+    Err(SmfError::Malformed)
+}
@@ -0,0 +1,92 @@
+use uncycle_core::{devices::TR8, prelude::*};
+
+/// Channel 10 of the TR-8
+const DEVICE_CHANNEL: u8 = 9;
+const OTHER_CHANNEL: u8 = 0;
+
+/// Lets time pass in steps of 100µs like the output thread does and returns everything that has been sent
+fn run(core: &mut UncycleCore, now: &mut u64, duration: u64) -> Vec<u8> {
+    let mut sent = Vec::new();
+    let end = *now + duration;
+
+    while *now < end {
+        *now += 100;
+        core.update_time(*now);
+        sent.extend(core.midi_tx_callback());
+    }
+
+    sent
+}
+
+fn running_core(now: &mut u64) -> UncycleCore {
+    let mut core = UncycleCore::new(120.0);
+    core.set_device(SupportedDevice::TR8(TR8::default()));
+    core.start_stop_sequence();
+    run(&mut core, now, 1_000);
+
+    core
+}
+
+#[test]
+fn state_is_kept_per_channel() {
+    let mut core = UncycleCore::new(120.0);
+
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | DEVICE_CHANNEL, 24, 100]);
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | OTHER_CHANNEL, 24, 10]);
+    core.midi_rx_callback(&[MIDI_NOTE_ON | OTHER_CHANNEL, 36, 127]);
+
+    assert_eq!(core.get_cc_val_of(DEVICE_CHANNEL, 24), 100);
+    assert_eq!(core.get_cc_val_of(OTHER_CHANNEL, 24), 10);
+    assert!(core.find_active_note(OTHER_CHANNEL, 36));
+    assert!(!core.find_active_note(DEVICE_CHANNEL, 36));
+
+    core.midi_rx_callback(&[MIDI_NOTE_ON | OTHER_CHANNEL, 36, 0]);
+    assert!(!core.find_active_note(OTHER_CHANNEL, 36));
+}
+
+#[test]
+fn only_the_device_channel_is_recorded_and_played_back() {
+    let mut now = 0;
+    let mut core = running_core(&mut now);
+
+    core.start_recording();
+    run(&mut core, &mut now, 100_000);
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | DEVICE_CHANNEL, 24, 100]);
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | OTHER_CHANNEL, 29, 50]);
+
+    // 32 steps at 120 BPM take 4s, then the loop is played back once
+    let sent = run(&mut core, &mut now, 6_000_000);
+
+    let played: Vec<&[u8]> = sent
+        .windows(3)
+        .filter(|bytes| bytes[0] & 0xF0 == MIDI_CONTORL_CHANGE)
+        .collect();
+
+    assert_eq!(played, [&[MIDI_CONTORL_CHANGE | DEVICE_CHANNEL, 24, 100]]);
+}
+
+#[test]
+fn the_generic_device_loops_any_cc_on_its_channel() {
+    let mut now = 0;
+    let mut core = UncycleCore::new(120.0);
+    core.set_device(SupportedDevice::Generic(GenericDevice::new(OTHER_CHANNEL)));
+    core.start_stop_sequence();
+    run(&mut core, &mut now, 1_000);
+
+    core.start_recording();
+    run(&mut core, &mut now, 100_000);
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | OTHER_CHANNEL, 3, 100]);
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | DEVICE_CHANNEL, 24, 50]);
+
+    assert_eq!(core.last_cc_of(OTHER_CHANNEL)[3], Some(100));
+    assert_eq!(core.last_cc_of(OTHER_CHANNEL)[24], None);
+
+    let sent = run(&mut core, &mut now, 6_000_000);
+
+    let played: Vec<&[u8]> = sent
+        .windows(3)
+        .filter(|bytes| bytes[0] & 0xF0 == MIDI_CONTORL_CHANGE)
+        .collect();
+
+    assert_eq!(played, [&[MIDI_CONTORL_CHANGE | OTHER_CHANNEL, 3, 100]]);
+}
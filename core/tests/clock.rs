@@ -0,0 +1,205 @@
+use uncycle_core::prelude::*;
+
+/// Time in between two pulses at `bpm` in µs
+fn period_us(bpm: f32) -> u64 {
+    (60_000_000.0 / (bpm * PPQN as f32)) as u64
+}
+
+/// Follows an external clock and runs the TR-8
+fn following_core() -> UncycleCore {
+    let mut core = UncycleCore::new(120.0);
+    core.set_device(SupportedDevice::from_id("roland-tr8").unwrap());
+    core.set_clock_source(ClockSource::External);
+
+    core
+}
+
+/// Receives `n_pulses` at `bpm` with `receive` and returns the time after the last one
+fn send_clock(
+    core: &mut UncycleCore,
+    mut now: u64,
+    bpm: f32,
+    n_pulses: u32,
+    receive: fn(&mut UncycleCore, &[u8]),
+) -> u64 {
+    for _ in 0..n_pulses {
+        now += period_us(bpm);
+        core.update_time(now);
+        receive(core, &[MIDI_CLOCK]);
+    }
+
+    now
+}
+
+#[test]
+fn received_clock_sets_the_tempo() {
+    let mut core = following_core();
+
+    core.midi_rx_callback(&[MIDI_START]);
+    assert!(core.device.as_ref().unwrap().is_running());
+
+    send_clock(&mut core, 0, 90.0, 4 * PPQN, UncycleCore::midi_rx_callback);
+    assert!(
+        (core.get_bpm() - 90.0).abs() < 0.5,
+        "bpm = {}",
+        core.get_bpm()
+    );
+
+    core.midi_rx_callback(&[MIDI_STOP]);
+    assert!(!core.device.as_ref().unwrap().is_running());
+}
+
+#[test]
+fn received_clock_is_ignored_with_an_internal_clock() {
+    let mut core = following_core();
+    core.set_clock_source(ClockSource::Internal);
+
+    core.midi_rx_callback(&[MIDI_START]);
+    send_clock(&mut core, 0, 90.0, 4 * PPQN, UncycleCore::midi_rx_callback);
+
+    assert_eq!(core.get_bpm(), 120.0);
+    assert!(!core.device.as_ref().unwrap().is_running());
+}
+
+#[test]
+fn clock_from_another_port_is_forwarded_to_the_device() {
+    let mut core = following_core();
+    core.set_clock_thru(true);
+
+    core.clock_rx_callback(&[MIDI_START]);
+    let now = send_clock(&mut core, 0, 90.0, 4 * PPQN, UncycleCore::clock_rx_callback);
+
+    assert!(
+        (core.get_bpm() - 90.0).abs() < 0.5,
+        "bpm = {}",
+        core.get_bpm()
+    );
+    assert_eq!(&core.midi_tx_callback()[..3], &[MIDI_CLOCK; 3]);
+
+    // the device itself is not the clock master
+    send_clock(
+        &mut core,
+        now,
+        140.0,
+        4 * PPQN,
+        UncycleCore::midi_rx_callback,
+    );
+    assert!(
+        (core.get_bpm() - 90.0).abs() < 0.5,
+        "bpm = {}",
+        core.get_bpm()
+    );
+    assert!(core.midi_tx_callback().is_empty());
+}
+
+/// Polls the output like the output thread does from `from` to `to` µs and returns when each CC number is sent
+fn run(core: &mut UncycleCore, from: u64, to: u64) -> Vec<(u64, u8)> {
+    let mut sent = Vec::new();
+    let mut now = from;
+
+    while now < to {
+        now += 250;
+        core.update_time(now);
+
+        let bytes = core.midi_tx_callback();
+
+        for (i, byte) in bytes.iter().enumerate() {
+            if byte & 0xF0 == MIDI_CONTORL_CHANGE {
+                sent.push((now, bytes[i + 1]));
+            }
+        }
+    }
+
+    sent
+}
+
+/// Time in between the first message of `first` and the next message of `second`
+fn distance(sent: &[(u64, u8)], first: u8, second: u8) -> u64 {
+    let (i, (start, _)) = sent
+        .iter()
+        .enumerate()
+        .find(|(_, (_, cc_num))| *cc_num == first)
+        .unwrap();
+    let (end, _) = sent[i..]
+        .iter()
+        .find(|(_, cc_num)| *cc_num == second)
+        .unwrap();
+
+    end - start
+}
+
+#[test]
+fn recorded_automation_follows_tempo_changes() {
+    let mut core = UncycleCore::new(120.0);
+    core.set_device(SupportedDevice::from_id("roland-tr8").unwrap());
+    core.start_stop_sequence();
+    run(&mut core, 0, 10_000);
+
+    // two CCs half a loop of 32 steps, i.e. 2 s, apart
+    core.start_recording();
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | 9, 24, 10]);
+    run(&mut core, 10_000, 2_010_000);
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | 9, 29, 10]);
+
+    let sent = run(&mut core, 2_010_000, 8_010_000);
+    assert!(distance(&sent, 24, 29).abs_diff(2_000_000) < 25_000);
+
+    // half the tempo, twice the distance
+    core.decrease_bpm_by(60.0);
+
+    let sent = run(&mut core, 8_010_000, 24_010_000);
+    assert!(distance(&sent, 24, 29).abs_diff(4_000_000) < 50_000);
+}
+
+/// Receives `n_pulses` at 120 BPM, polls the output after each one and half way to the next, returns the pulses
+/// after which `cc_num` is sent
+fn pulses_sending(core: &mut UncycleCore, now: &mut u64, n_pulses: u32, cc_num: u8) -> Vec<u32> {
+    let mut pulses = Vec::new();
+
+    for pulse in 1..=n_pulses {
+        core.midi_rx_callback(&[MIDI_CLOCK]);
+
+        for _ in 0..2 {
+            let bytes = core.midi_tx_callback();
+
+            if bytes
+                .windows(2)
+                .any(|message| message[0] & 0xF0 == MIDI_CONTORL_CHANGE && message[1] == cc_num)
+            {
+                pulses.push(pulse);
+            }
+
+            *now += period_us(120.0) / 2;
+            core.update_time(*now);
+        }
+    }
+
+    pulses
+}
+
+#[test]
+fn received_start_plays_the_loop_from_its_beginning() {
+    let mut core = following_core();
+    let mut now = 0;
+    let loop_pulses = 32 * PPQN / 4;
+
+    core.midi_rx_callback(&[MIDI_START]);
+    pulses_sending(&mut core, &mut now, 10, 24);
+
+    // the loop starts right at the 11th pulse
+    core.midi_rx_callback(&[MIDI_CLOCK]);
+    core.start_recording();
+    core.midi_rx_callback(&[MIDI_CONTORL_CHANGE | 9, 24, 10]);
+    pulses_sending(&mut core, &mut now, loop_pulses - 1, 24);
+
+    assert_eq!(pulses_sending(&mut core, &mut now, loop_pulses, 24), [1]);
+
+    // the clock master starts over in the middle of the loop
+    pulses_sending(&mut core, &mut now, 7, 24);
+    core.midi_rx_callback(&[MIDI_STOP]);
+    core.midi_rx_callback(&[MIDI_START]);
+
+    // the loop starts at the first pulse, but the tempo is only known again and the position moves past it with
+    // the second one
+    assert_eq!(pulses_sending(&mut core, &mut now, loop_pulses, 24), [2]);
+}
@@ -0,0 +1,81 @@
+//! Helpers shared by the integration tests, each test crate only uses some of them
+#![allow(dead_code)]
+
+use uncycle_core::prelude::*;
+
+/// Sixteenth in sub-ticks
+pub const STEP: u64 = (PPQN / 4 * SUBTICKS_PER_TICK) as u64;
+/// Default loop of 32 steps
+pub const LOOP_LEN: u64 = 32 * STEP;
+
+/// Deterministic pseudo random numbers, so that failures are reproducible
+pub struct Lcg(pub u32);
+
+impl Lcg {
+    pub fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        self.0 >> 8
+    }
+
+    /// uniformly distributed in `0..max`
+    pub fn below(&mut self, max: u32) -> u8 {
+        (self.next() % max) as u8
+    }
+
+    pub fn chance(&mut self, percent: u32) -> bool {
+        self.next() % 100 < percent
+    }
+
+    /// polling interval uniformly distributed in `1..=max`
+    pub fn interval(&mut self, max: u64) -> u64 {
+        self.next() as u64 % max + 1
+    }
+}
+
+/// Polls the looper like the output thread does up to `end` and returns what has been played with its time
+pub fn play(
+    core: &mut UncycleCore,
+    mut now: u64,
+    end: u64,
+    mut interval: impl FnMut() -> u64,
+) -> Vec<(u64, LoopEvent)> {
+    let mut played = Vec::new();
+
+    while now < end {
+        now = (now + interval()).min(end);
+
+        core.looper.handle_eol(now);
+        for event in core.looper.play_back_recording(now) {
+            played.push((now, *event));
+        }
+    }
+
+    played
+}
+
+/// How often each lane has been played
+pub fn lane_counts(played: &[(u64, LoopEvent)]) -> [u32; 128] {
+    let mut counts = [0; 128];
+
+    for (_, event) in played {
+        if let LoopEvent::Param(change) = event {
+            counts[change.param.lane() as usize] += 1;
+        }
+    }
+
+    counts
+}
+
+/// A loop of CC 24 at 0 and 2 steps with a note from step 1 to 3, recorded at 120 BPM
+pub fn recorded_core() -> UncycleCore {
+    let mut core = UncycleCore::new(120.0);
+    core.looper.start_recording(0);
+
+    core.looper
+        .record_cc(2 * STEP, &[MIDI_CONTORL_CHANGE, 24, 100]);
+    core.looper.record_note_on(STEP, 36, 127);
+    core.looper.record_note_off(3 * STEP, 36);
+    core.looper.record_cc(0, &[MIDI_CONTORL_CHANGE, 24, 10]);
+
+    core
+}
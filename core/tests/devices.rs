@@ -0,0 +1,324 @@
+use strum::IntoEnumIterator;
+use uncycle_core::devices::{
+    bundled::{ROLAND_TR8, ROLAND_TR8S},
+    tr8::*,
+    tr8s::*,
+};
+use uncycle_core::prelude::*;
+
+const SYNTH: DeviceDefinition = DeviceDefinition {
+    id: "test-synth",
+    name: "Synth",
+    manufacturer: "Test",
+    port_name: "SYNTH",
+    channel: 2,
+    notes: &[NoteDefinition {
+        note: 36,
+        name: "KICK",
+    }],
+    params: &[
+        ParamDefinition {
+            cc: 74,
+            name: "CUTOFF",
+            group: "FILTER",
+            min: 0,
+            max: 127,
+            default: 127,
+        },
+        ParamDefinition {
+            cc: 20,
+            name: "WAVE",
+            group: "OSC",
+            min: 0,
+            max: 3,
+            default: 0,
+        },
+    ],
+    identity: Some(IdentityMatch {
+        manufacturer: ManufacturerId::Extended(0x21, 0x09),
+        family: 0x0102,
+        member: Some(3),
+    }),
+};
+
+static SYNTH_STATIC: DeviceDefinition = SYNTH;
+
+#[test]
+fn definitions_are_validated() {
+    assert_eq!(SYNTH.validate(), Ok(()));
+
+    let definition = DeviceDefinition { id: "", ..SYNTH };
+    assert_eq!(definition.validate(), Err(DefinitionError::InvalidText));
+
+    let definition = DeviceDefinition {
+        channel: 16,
+        ..SYNTH
+    };
+    assert_eq!(definition.validate(), Err(DefinitionError::InvalidChannel));
+
+    let definition = DeviceDefinition {
+        notes: &[NoteDefinition {
+            note: 128,
+            name: "",
+        }],
+        ..SYNTH
+    };
+    assert_eq!(
+        definition.validate(),
+        Err(DefinitionError::InvalidNote(128))
+    );
+
+    let definition = DeviceDefinition {
+        params: &[ParamDefinition {
+            cc: 7,
+            name: "VOLUME",
+            group: "",
+            min: 10,
+            max: 10,
+            default: 10,
+        }],
+        ..SYNTH
+    };
+    assert_eq!(definition.validate(), Err(DefinitionError::InvalidParam(7)));
+
+    let definition = DeviceDefinition {
+        params: &[ParamDefinition {
+            cc: 7,
+            name: "VOLUME",
+            group: "",
+            min: 10,
+            max: 20,
+            default: 0,
+        }],
+        ..SYNTH
+    };
+    assert_eq!(definition.validate(), Err(DefinitionError::InvalidParam(7)));
+
+    let identity = |manufacturer, family, member| DeviceDefinition {
+        identity: Some(IdentityMatch {
+            manufacturer,
+            family,
+            member,
+        }),
+        ..SYNTH
+    };
+    let roland = ManufacturerId::Short(0x41);
+
+    // both bytes of family and member can use all 7 bits
+    assert_eq!(identity(roland, 0x7F7F, Some(0x4000)).validate(), Ok(()));
+
+    for invalid in [
+        identity(roland, 0x0080, None),
+        identity(roland, 0x8000, None),
+        identity(roland, 0x0102, Some(0x0180)),
+        identity(ManufacturerId::Short(0), 0x0102, None),
+        identity(ManufacturerId::Extended(0x21, 0x80), 0x0102, None),
+    ] {
+        assert_eq!(invalid.validate(), Err(DefinitionError::InvalidIdentity));
+    }
+}
+
+#[test]
+fn built_in_devices_describe_their_params_and_notes() {
+    for device in SupportedDevice::iter() {
+        let params = device.params();
+        let notes = device.notes();
+
+        assert!(!params.is_empty());
+        assert!(params.iter().all(|param| param.is_valid()));
+        assert!(notes.iter().all(|note| note.note < 128));
+
+        for (i, param) in params.iter().enumerate() {
+            assert!(params[..i].iter().all(|other| other.cc != param.cc));
+            assert_eq!(device.param(param.cc), Some(param));
+        }
+    }
+
+    let tr8 = SupportedDevice::from_id("roland-tr8").unwrap();
+    let bd_level = tr8.param(24).unwrap();
+
+    assert_eq!((bd_level.group, bd_level.name), ("BD", "LEVEL"));
+    assert_eq!(
+        tr8.notes()[0],
+        NoteDefinition {
+            note: 36,
+            name: "BD"
+        }
+    );
+    assert_eq!(tr8.param(0), None);
+
+    let tr8s = SupportedDevice::from_id("roland-tr8s").unwrap();
+    let sd_ctrl = tr8s.param(97).unwrap();
+
+    assert_eq!((sd_ctrl.group, sd_ctrl.name), ("SD", "CTRL"));
+    let shuffle = tr8s.param(9).unwrap();
+
+    assert_eq!((shuffle.group, shuffle.name), ("KIT", "SHUFFLE"));
+    let bd_attack = tr8s.param(21).unwrap();
+
+    assert_eq!((bd_attack.group, bd_attack.name), ("BD", "ATTACK"));
+    assert_eq!(tr8s.params().len(), 4 * tr8s.notes().len() + 4 + 7);
+}
+
+#[test]
+fn the_tr8_is_described_by_its_bundled_definition() {
+    assert_eq!(ROLAND_TR8.validate(), Ok(()));
+
+    let tr8 = SupportedDevice::from_id(ROLAND_TR8.id).unwrap();
+
+    assert_eq!(tr8.name_to_str(), ROLAND_TR8.name);
+    assert_eq!(tr8.port_name_to_str(), ROLAND_TR8.port_name);
+    assert_eq!(tr8.rx_channel(), 9);
+    assert_eq!(tr8.params(), ROLAND_TR8.params);
+    assert_eq!(tr8.notes(), ROLAND_TR8.notes);
+    assert_eq!(tr8.identity(), ROLAND_TR8.identity);
+
+    assert_eq!(TR_8_CC_PARAMS_1ST_ROW[0], (20, "TUNE"));
+    assert_eq!(TR_8_CC_PARAMS_2ND_ROW[3], (28, "DECAY"));
+    assert_eq!(TR_8_CC_FADER[10], (88, "RC"));
+    assert_eq!(TR_8_PARAM_INSTRUMENTS[1], "BD");
+    assert_eq!(TR_8_NOTES[14..], [54, 0]);
+}
+
+#[test]
+fn the_tr8s_is_described_by_its_bundled_definition() {
+    assert_eq!(ROLAND_TR8S.validate(), Ok(()));
+
+    let tr8s = SupportedDevice::from_id(ROLAND_TR8S.id).unwrap();
+
+    assert_eq!(tr8s.name_to_str(), ROLAND_TR8S.name);
+    assert_eq!(tr8s.port_name_to_str(), ROLAND_TR8S.port_name);
+    assert_eq!(tr8s.rx_channel(), 9);
+    assert_eq!(tr8s.params(), ROLAND_TR8S.params);
+    assert_eq!(tr8s.notes(), ROLAND_TR8S.notes);
+    assert!(tr8s.identity().is_some());
+
+    assert_eq!(TR_8S_CC_TUNE[1], 25);
+    assert_eq!(TR_8S_CC_CTRL[10], 110);
+    assert_eq!(TR_8S_CC_LEVEL[0], 24);
+    assert_eq!(TR_8S_EXTRA_PARAMS.map(|param| param.cc), [21, 22, 26, 27]);
+    assert_eq!(TR_8S_KIT_PARAMS[0].name, "SHUFFLE");
+    assert_eq!(TR_8S_INSTRUMENT_NAMES[10], "RC");
+}
+
+#[test]
+fn params_start_with_their_default() {
+    let mut core = UncycleCore::new(120.0);
+    core.add_device_definition(&SYNTH_STATIC).unwrap();
+    core.set_device(core.find_device("test-synth").unwrap());
+
+    assert_eq!(core.get_cc_val_of(2, 74), 127);
+    assert_eq!(core.get_cc_val_of(2, 7), 0);
+    assert_eq!(core.get_cc_val_of(3, 74), 0);
+
+    core.update_cc(2, 74, 10);
+    assert_eq!(core.get_cc_val_of(2, 74), 10);
+}
+
+#[test]
+fn defined_devices_follow_the_built_in_ones() {
+    let mut core = UncycleCore::new(120.0);
+    core.add_device_definition(&SYNTH_STATIC).unwrap();
+
+    let ids: Vec<_> = core
+        .available_devices()
+        .map(|device| device.id_to_str().to_string())
+        .collect();
+
+    assert_eq!(ids, ["roland-tr8", "roland-tr8s", "generic", "test-synth"]);
+
+    let device = core.find_device("test-synth").unwrap();
+    assert_eq!(device.rx_channel(), 2);
+    assert_eq!(device.tx_channel(), 2);
+    assert_eq!(device.port_name_to_str(), "SYNTH");
+    assert_eq!(device.params(), SYNTH.params);
+    assert_eq!(device.notes(), SYNTH.notes);
+}
+
+#[test]
+fn ids_are_unique() {
+    static TR8: DeviceDefinition = DeviceDefinition {
+        id: "roland-tr8",
+        ..SYNTH
+    };
+
+    let mut core = UncycleCore::new(120.0);
+    core.add_device_definition(&SYNTH_STATIC).unwrap();
+
+    assert_eq!(
+        core.add_device_definition(&SYNTH_STATIC),
+        Err(DefinitionError::DuplicateId)
+    );
+    assert_eq!(
+        core.add_device_definition(&TR8),
+        Err(DefinitionError::DuplicateId)
+    );
+}
+
+#[test]
+fn defined_devices_are_restored_from_sessions() {
+    let mut core = UncycleCore::new(120.0);
+    core.add_device_definition(&SYNTH_STATIC).unwrap();
+    core.set_device(core.find_device("test-synth").unwrap());
+
+    let mut session = Vec::new();
+    core.save_session(&mut |bytes| session.extend_from_slice(bytes));
+
+    let mut loaded = UncycleCore::new(120.0);
+    loaded.add_device_definition(&SYNTH_STATIC).unwrap();
+    loaded.load_session(&session).unwrap();
+    assert_eq!(loaded.device.unwrap().id_to_str(), "test-synth");
+
+    // without the definition the device is unknown
+    let mut loaded = UncycleCore::new(120.0);
+    loaded.load_session(&session).unwrap();
+    assert!(loaded.device.is_none());
+}
+
+#[test]
+fn devices_are_identified_by_their_identity_reply() {
+    let mut core = UncycleCore::new(120.0);
+    core.add_device_definition(&SYNTH_STATIC).unwrap();
+
+    let reply = [
+        0x7E, 0x10, 0x06, 0x02, 0x41, 0x5C, 0x03, 0x00, 0x00, 0, 1, 0, 0,
+    ];
+    let identity = Identity::from_reply(&reply).unwrap();
+    assert_eq!(core.identify(&identity).unwrap().id_to_str(), "roland-tr8");
+
+    let reply = [
+        0x7E, 0x10, 0x06, 0x02, 0x41, 0x45, 0x04, 0x00, 0x00, 0, 1, 0, 0,
+    ];
+    let identity = Identity::from_reply(&reply).unwrap();
+    assert_eq!(core.identify(&identity).unwrap().id_to_str(), "roland-tr8s");
+
+    let reply = [
+        0x7E, 0x00, 0x06, 0x02, 0x00, 0x21, 0x09, 0x02, 0x01, 0x03, 0x00, 1, 2, 3, 4,
+    ];
+    let identity = Identity::from_reply(&reply).unwrap();
+    assert_eq!(core.identify(&identity).unwrap().id_to_str(), "test-synth");
+
+    // another model of the same family
+    let reply = [
+        0x7E, 0x00, 0x06, 0x02, 0x00, 0x21, 0x09, 0x02, 0x01, 0x04, 0x00, 1, 2, 3, 4,
+    ];
+    let identity = Identity::from_reply(&reply).unwrap();
+    assert!(core.identify(&identity).is_none());
+}
+
+#[test]
+fn ports_are_matched_by_the_whole_port_name() {
+    let tr8 = "TR-8:TR-8 MIDI 1 20:0";
+    let tr8s = "TR-8S:TR-8S MIDI 1 24:0";
+
+    assert!(is_device_port(tr8, "TR-8"));
+    assert!(!is_device_port(tr8s, "TR-8"));
+    assert!(is_device_port(tr8s, "TR-8S"));
+    assert!(!is_device_port(tr8, "TR-8S"));
+
+    // how other systems name the ports
+    assert!(is_device_port("2- TR-8", "TR-8"));
+    assert!(is_device_port("TR-8S", "TR-8S"));
+    assert!(!is_device_port("TR-8S", "TR-8"));
+    assert!(!is_device_port("TR-8", ""));
+}
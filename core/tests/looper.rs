@@ -0,0 +1,423 @@
+mod common;
+
+use common::*;
+use uncycle_core::prelude::*;
+
+fn cc(cc_num: u8) -> MidiMsg {
+    [MIDI_CONTORL_CHANGE, cc_num, 64]
+}
+
+/// Records one loop starting at song position 0 with a message of a different CC number at each time
+fn record(core: &mut UncycleCore, times: &[u64]) {
+    core.looper.start_recording(0);
+
+    for (i, time) in times.iter().enumerate() {
+        core.looper.record_cc(*time, &cc(i as u8));
+    }
+}
+
+#[test]
+fn nothing_is_played_while_recording() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[0, STEP, LOOP_LEN - 1]);
+
+    let counts = lane_counts(&play(&mut core, 0, LOOP_LEN - 1, || 100));
+    assert!(counts.iter().all(|count| *count == 0));
+}
+
+#[test]
+fn events_on_loop_boundaries_play_once_per_cycle() {
+    let times = [0, 1, STEP, LOOP_LEN / 2, LOOP_LEN - STEP, LOOP_LEN - 1];
+
+    // intervals that divide the loop, ones that do not and ones hitting the boundaries exactly
+    for interval in [1, 7, 100, 999, STEP, LOOP_LEN / 3, LOOP_LEN - 1] {
+        let mut core = UncycleCore::new(120.0);
+        record(&mut core, &times);
+
+        let counts = lane_counts(&play(&mut core, 0, 5 * LOOP_LEN, || interval));
+
+        for (i, count) in counts.iter().take(times.len()).enumerate() {
+            assert_eq!(*count, 4, "interval {interval}: event {i}");
+        }
+    }
+}
+
+#[test]
+fn jittered_polling_plays_every_event_once_per_cycle() {
+    let times: Vec<u64> = (0..LOOP_LEN).step_by(491).take(100).collect();
+
+    for seed in 0..8 {
+        let mut core = UncycleCore::new(120.0);
+        record(&mut core, &times);
+
+        let mut lcg = Lcg(seed);
+        let counts = lane_counts(&play(&mut core, 0, 9 * LOOP_LEN, || lcg.interval(3 * STEP)));
+
+        for (i, count) in counts.iter().take(times.len()).enumerate() {
+            assert_eq!(*count, 8, "seed {seed}: event {i}");
+        }
+    }
+}
+
+#[test]
+fn window_longer_than_loop_plays_everything_once() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[0, LOOP_LEN / 2, LOOP_LEN - 1]);
+
+    play(&mut core, 0, LOOP_LEN, || 10);
+    let counts = lane_counts(&play(&mut core, LOOP_LEN, 4 * LOOP_LEN, || 3 * LOOP_LEN));
+
+    assert_eq!(&counts[..3], &[1, 1, 1]);
+}
+
+#[test]
+fn stale_time_does_not_play_again() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[LOOP_LEN / 2]);
+
+    let counts = lane_counts(&play(&mut core, 0, 2 * LOOP_LEN, || 50));
+    assert_eq!(counts[0], 1);
+
+    // a timestamp from before the last call
+    assert!(core.looper.play_back_recording(LOOP_LEN).is_empty());
+    assert!(core.looper.play_back_recording(2 * LOOP_LEN).is_empty());
+}
+
+#[test]
+fn doubled_loop_plays_recording_twice() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[0, STEP, LOOP_LEN - 1]);
+
+    play(&mut core, 0, LOOP_LEN, || 100);
+    core.looper.set_loop_steps(64);
+
+    let counts = lane_counts(&play(&mut core, LOOP_LEN, 3 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..3], &[2, 2, 2]);
+}
+
+#[test]
+fn pressing_record_during_a_one_shot_overdub_keeps_one_layer() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[0]);
+    play(&mut core, 0, LOOP_LEN, || 100);
+
+    core.looper.start_recording(LOOP_LEN);
+    core.looper.record_cc(LOOP_LEN + STEP, &cc(1));
+
+    // nothing to toggle in one shot mode
+    core.looper.start_recording(LOOP_LEN + 2 * STEP);
+    core.looper.record_cc(LOOP_LEN + 3 * STEP, &cc(2));
+
+    play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100);
+    assert_eq!(core.looper.n_layers(), 2);
+
+    core.looper.undo();
+
+    let counts = lane_counts(&play(&mut core, 2 * LOOP_LEN, 3 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..3], &[1, 0, 0]);
+}
+
+/// Times at which a lane is part of the loop
+fn lane_times(core: &UncycleCore, lane: u8) -> Vec<u32> {
+    core.looper
+        .loop_events()
+        .iter()
+        .filter_map(|(time, event)| match event {
+            LoopEvent::Param(change) if change.param.lane() == lane => Some(*time),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn rerecording_a_lane_can_be_undone() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[STEP, 2 * STEP]);
+    play(&mut core, 0, LOOP_LEN, || 100);
+
+    core.looper.rerecord_lane(LOOP_LEN, 0);
+    core.looper.record_cc(LOOP_LEN + 3 * STEP, &cc(0));
+    // other lanes are not recorded
+    core.looper.record_cc(LOOP_LEN + 4 * STEP, &cc(1));
+    play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100);
+
+    assert_eq!(lane_times(&core, 0), [3 * STEP as u32]);
+    assert_eq!(lane_times(&core, 1), [2 * STEP as u32]);
+
+    core.looper.undo();
+    assert_eq!(lane_times(&core, 0), [STEP as u32]);
+
+    core.looper.redo();
+    assert_eq!(lane_times(&core, 0), [3 * STEP as u32]);
+}
+
+#[test]
+fn cleared_lanes_stay_silent() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[STEP, 2 * STEP]);
+    play(&mut core, 0, LOOP_LEN, || 100);
+
+    core.looper.clear_lane(0);
+
+    let counts = lane_counts(&play(&mut core, LOOP_LEN, 3 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..2], &[0, 2]);
+}
+
+#[test]
+fn muted_and_soloed_lanes() {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[STEP, 2 * STEP, 3 * STEP]);
+    play(&mut core, 0, LOOP_LEN, || 100);
+
+    core.looper.toggle_mute_lane(0);
+    let counts = lane_counts(&play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..3], &[0, 1, 1]);
+
+    // soloing overrides muting
+    core.looper.toggle_solo_lane(0);
+    core.looper.toggle_solo_lane(1);
+    let counts = lane_counts(&play(&mut core, 2 * LOOP_LEN, 3 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..3], &[1, 1, 0]);
+
+    // one lane is still soloed
+    core.looper.toggle_solo_lane(0);
+    assert!(!core.looper.lane(0).soloed);
+    let counts = lane_counts(&play(&mut core, 3 * LOOP_LEN, 4 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..3], &[0, 1, 0]);
+
+    // back to muting once nothing is soloed
+    core.looper.toggle_solo_lane(1);
+    let counts = lane_counts(&play(&mut core, 4 * LOOP_LEN, 5 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..3], &[0, 1, 1]);
+}
+
+/// Records CC 0 at `times` and starts a replacing overdub right after the first cycle
+fn overdub_replacing(core: &mut UncycleCore, times: &[u64]) {
+    core.looper.set_overdub_mode(OverdubMode::Replace);
+    core.looper.start_recording(0);
+
+    for time in times {
+        core.looper.record_cc(*time, &cc(0));
+    }
+
+    play(core, 0, LOOP_LEN, || 100);
+    core.looper.start_recording(LOOP_LEN);
+}
+
+#[test]
+fn punching_in_replaces_only_the_touched_span() {
+    let mut core = UncycleCore::new(120.0);
+    overdub_replacing(&mut core, &[STEP, 8 * STEP, 16 * STEP]);
+
+    // a knob turned from step 7 to a bit after step 8
+    for time in [7 * STEP, 7 * STEP + STEP / 2, 8 * STEP + STEP / 4] {
+        core.looper.record_cc(LOOP_LEN + time, &cc(0));
+    }
+    play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100);
+
+    let expected = [
+        STEP,
+        7 * STEP,
+        7 * STEP + STEP / 2,
+        8 * STEP + STEP / 4,
+        16 * STEP,
+    ];
+    assert_eq!(lane_times(&core, 0), expected.map(|time| time as u32));
+
+    // undoing the pass brings back what has been replaced
+    core.looper.undo();
+    let expected = [STEP, 8 * STEP, 16 * STEP];
+    assert_eq!(lane_times(&core, 0), expected.map(|time| time as u32));
+}
+
+#[test]
+fn recorded_messages_are_held_back_while_a_lane_is_touched() {
+    let mut core = UncycleCore::new(120.0);
+    overdub_replacing(&mut core, &[8 * STEP, 16 * STEP]);
+
+    // touched right before step 8, let go before step 16
+    let touch = LOOP_LEN + 8 * STEP - STEP / 2;
+    play(&mut core, LOOP_LEN, touch, || 100);
+    core.looper.record_cc(touch, &cc(0));
+
+    // the touch itself and step 16, but not step 8
+    let counts = lane_counts(&play(&mut core, touch, 2 * LOOP_LEN, || 100));
+    assert_eq!(counts[0], 2);
+
+    // the touch did not reach step 8, so it plays again
+    let counts = lane_counts(&play(&mut core, 2 * LOOP_LEN, 3 * LOOP_LEN, || 100));
+    assert_eq!(counts[0], 3);
+}
+
+#[test]
+fn parameters_sharing_a_lane_are_replaced_on_their_own() {
+    let pressure = |note| ParamChange {
+        param: Param::PolyPressure(note),
+        value: 64,
+    };
+    let nrpn = |number| ParamChange {
+        param: Param::Nrpn(number),
+        value: 64,
+    };
+
+    let mut core = UncycleCore::new(120.0);
+    core.looper.set_overdub_mode(OverdubMode::Replace);
+    core.looper.start_recording(0);
+
+    for change in [pressure(36), pressure(38), nrpn(1), nrpn(2)] {
+        core.looper.record_param(8 * STEP, change);
+    }
+    play(&mut core, 0, LOOP_LEN, || 100);
+    core.looper.start_recording(LOOP_LEN);
+
+    // one note and one NRPN touched over step 8
+    let touch = LOOP_LEN + 7 * STEP;
+    play(&mut core, LOOP_LEN, touch, || 100);
+
+    for time in [touch, touch + STEP / 2, touch + STEP] {
+        core.looper.record_param(time, pressure(36));
+        core.looper.record_param(time, nrpn(1));
+    }
+
+    // the others are not held back
+    let played = play(&mut core, touch, 2 * LOOP_LEN, || 100);
+    let n_played = |change| {
+        played
+            .iter()
+            .filter(|(_, event)| *event == LoopEvent::Param(change))
+            .count()
+    };
+
+    assert_eq!(n_played(pressure(38)), 1);
+    assert_eq!(n_played(nrpn(2)), 1);
+
+    let events = core.looper.loop_events();
+    let times_of = |change| {
+        events
+            .iter()
+            .filter(|(_, event)| *event == LoopEvent::Param(change))
+            .map(|(time, _)| *time)
+            .collect::<Vec<_>>()
+    };
+    let touched = [7 * STEP, 7 * STEP + STEP / 2, 8 * STEP].map(|time| time as u32);
+
+    assert_eq!(times_of(pressure(36)), touched);
+    assert_eq!(times_of(nrpn(1)), touched);
+    assert_eq!(times_of(pressure(38)), [8 * STEP as u32]);
+    assert_eq!(times_of(nrpn(2)), [8 * STEP as u32]);
+}
+
+#[test]
+fn continuous_mode_overdubs_until_toggled_off() {
+    let mut core = UncycleCore::new(120.0);
+    core.looper.set_rec_mode(RecMode::Continuous);
+    record(&mut core, &[STEP]);
+    play(&mut core, 0, LOOP_LEN, || 100);
+
+    // the record head stays down, every cycle is a pass of its own
+    assert!(core.looper.overdub);
+    core.looper.record_cc(LOOP_LEN + 2 * STEP, &cc(1));
+    play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100);
+    core.looper.record_cc(2 * LOOP_LEN + 3 * STEP, &cc(2));
+    play(&mut core, 2 * LOOP_LEN, 3 * LOOP_LEN, || 100);
+
+    assert!(core.looper.overdub);
+    assert_eq!(core.looper.n_layers(), 3);
+
+    core.looper.start_recording(3 * LOOP_LEN);
+    assert!(!core.looper.overdub);
+
+    core.looper.record_cc(3 * LOOP_LEN + 4 * STEP, &cc(3));
+    let counts = lane_counts(&play(&mut core, 3 * LOOP_LEN, 4 * LOOP_LEN, || 100));
+    assert_eq!(&counts[..4], &[1, 1, 1, 0]);
+
+    core.looper.start_recording(4 * LOOP_LEN);
+    assert!(core.looper.overdub);
+}
+
+/// Records CC 0 at `first` and CC 1 at `second` and plays the first cycle
+fn recorded_loop(first: u64, second: u64) -> UncycleCore {
+    let mut core = UncycleCore::new(120.0);
+    record(&mut core, &[first, second]);
+    play(&mut core, 0, LOOP_LEN, || 100);
+
+    core
+}
+
+#[test]
+fn halving_keeps_the_first_half() {
+    let mut core = recorded_loop(STEP, 20 * STEP);
+    core.half_loop_len();
+
+    assert_eq!(core.looper.loop_steps, 16);
+    assert_eq!(lane_times(&core, 0), [STEP as u32]);
+    assert!(lane_times(&core, 1).is_empty());
+}
+
+#[test]
+fn halving_can_keep_the_second_half() {
+    let mut core = recorded_loop(STEP, 20 * STEP);
+    core.looper.set_shrink_mode(ShrinkMode::KeepEnd);
+    core.half_loop_len();
+
+    assert!(lane_times(&core, 0).is_empty());
+    assert_eq!(lane_times(&core, 1), [4 * STEP as u32]);
+}
+
+#[test]
+fn loops_can_have_any_length_within_limits() {
+    let mut core = recorded_loop(STEP, 10 * STEP);
+
+    core.set_loop_step_len(12);
+    assert_eq!(lane_times(&core, 0), [STEP as u32]);
+    assert_eq!(lane_times(&core, 1), [10 * STEP as u32]);
+
+    // repeated as long as it fits
+    core.set_loop_step_len(30);
+    assert_eq!(
+        lane_times(&core, 0),
+        [STEP, 13 * STEP, 25 * STEP].map(|time| time as u32)
+    );
+    assert_eq!(
+        lane_times(&core, 1),
+        [10 * STEP, 22 * STEP].map(|time| time as u32)
+    );
+
+    core.set_loop_step_len(1);
+    assert_eq!(core.looper.loop_steps, LOOPER_MIN_LEN);
+
+    core.set_loop_step_len(u16::MAX);
+    assert_eq!(core.looper.loop_steps, LOOPER_MAX_LEN);
+}
+
+#[test]
+fn undoing_stops_a_pass_that_has_not_recorded_yet() {
+    for rec_mode in [RecMode::OneShot, RecMode::Continuous] {
+        let mut core = UncycleCore::new(120.0);
+        core.looper.set_rec_mode(rec_mode);
+        record(&mut core, &[STEP]);
+        play(&mut core, 0, LOOP_LEN, || 100);
+
+        if rec_mode == RecMode::OneShot {
+            core.looper.start_recording(LOOP_LEN);
+        }
+        core.looper.record_cc(LOOP_LEN + 2 * STEP, &cc(1));
+        play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100);
+
+        // the next pass is running, but nothing has been recorded in it
+        if rec_mode == RecMode::OneShot {
+            core.looper.start_recording(2 * LOOP_LEN);
+        }
+        assert!(core.looper.overdub);
+
+        core.looper.undo();
+        core.looper.record_cc(2 * LOOP_LEN + 3 * STEP, &cc(2));
+
+        assert!(!core.looper.overdub);
+        assert_eq!(core.looper.n_undone_layers(), 1);
+
+        core.looper.redo();
+        assert_eq!(lane_times(&core, 1), [2 * STEP as u32]);
+        assert!(lane_times(&core, 2).is_empty());
+    }
+}
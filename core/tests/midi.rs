@@ -0,0 +1,81 @@
+use uncycle_core::prelude::*;
+
+#[test]
+fn messages_have_correct_lengths() {
+    let cases: [(&[u8], usize); 8] = [
+        (&[0x91, 60, 100], 3),
+        (&[0xC2, 5], 2),
+        (&[0xD3, 90], 2),
+        (&[0xE4, 0x00, 0x40], 3),
+        (&[0xF1, 0x23], 2),
+        (&[0xF2, 0x10, 0x01], 3),
+        (&[0xF6], 1),
+        (&[0xF8], 1),
+    ];
+
+    for (bytes, len) in cases {
+        let msg = MidiMessage::from_bytes(bytes).unwrap();
+        assert_eq!(msg.n_bytes(), len, "{bytes:02X?}");
+        assert_eq!(msg.to_bytes().as_slice(), bytes);
+    }
+}
+
+#[test]
+fn channel_voice_messages_carry_their_channel() {
+    let msg = MidiMessage::from_bytes(&[0xBA, 74, 127]).unwrap();
+
+    assert_eq!(
+        msg,
+        MidiMessage::ControlChange {
+            channel: 10,
+            control: 74,
+            value: 127
+        }
+    );
+    assert_eq!(msg.channel(), Some(10));
+    assert_eq!(MidiMessage::Start.channel(), None);
+
+    let bend = MidiMessage::from_bytes(&[0xE0, 0x00, 0x40]).unwrap();
+    assert_eq!(
+        bend,
+        MidiMessage::PitchBend {
+            channel: 0,
+            value: PITCH_BEND_CENTER
+        }
+    );
+}
+
+#[test]
+fn every_valid_message_round_trips() {
+    for status in 0x80..=0xFF_u8 {
+        let Some(len) = message_len(status) else {
+            continue;
+        };
+
+        for data in [0x00, 0x01, 0x40, 0x7F] {
+            let bytes = [status, data, 0x7F - data];
+            let msg = MidiMessage::from_bytes(&bytes).unwrap();
+
+            assert_eq!(msg.to_bytes().as_slice(), &bytes[..len]);
+            assert_eq!(MidiMessage::from_bytes(&msg.to_bytes()), Some(msg));
+        }
+    }
+}
+
+#[test]
+fn rejects_incomplete_and_invalid_messages() {
+    assert_eq!(MidiMessage::from_bytes(&[]), None);
+    assert_eq!(MidiMessage::from_bytes(&[0x90, 60]), None);
+    assert_eq!(MidiMessage::from_bytes(&[0x90, 60, 0x80]), None);
+    assert_eq!(MidiMessage::from_bytes(&[60, 100]), None);
+    assert_eq!(
+        MidiMessage::from_bytes(&[MIDI_SYSEX, 0x41, MIDI_SYSEX_END]),
+        None
+    );
+    assert_eq!(MidiMessage::from_bytes(&[0xF4]), None);
+
+    // velocity 0 is a Note Off
+    assert!(MidiMessage::from_bytes(&[0x90, 60, 0])
+        .unwrap()
+        .is_note_off());
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::*;
+use uncycle_core::prelude::*;
+
+const NOTE_ON: LoopEvent = LoopEvent::NoteOn {
+    note: 36,
+    velocity: 100,
+};
+const NOTE_OFF: LoopEvent = LoopEvent::NoteOff { note: 36 };
+
+/// Times at which `event` has been played
+fn times_of(played: &[(u64, LoopEvent)], event: LoopEvent) -> Vec<u64> {
+    played
+        .iter()
+        .filter(|(_, played)| *played == event)
+        .map(|(time, _)| *time)
+        .collect()
+}
+
+/// Records one loop starting at song position 0 with a note of length `len` at `time`
+fn record_note(core: &mut UncycleCore, note: u8, time: u64, len: u64) {
+    core.looper.start_recording(0);
+    core.looper.record_note_on(time, note, 100);
+    core.looper.record_note_off(time + len, note);
+}
+
+#[test]
+fn notes_are_played_with_their_length() {
+    let mut core = UncycleCore::new(120.0);
+    record_note(&mut core, 36, STEP, 2 * STEP);
+
+    play(&mut core, 0, LOOP_LEN, || 100);
+    let played = play(&mut core, LOOP_LEN, 3 * LOOP_LEN, || 100);
+
+    let note_ons = times_of(&played, NOTE_ON);
+    let note_offs = times_of(&played, NOTE_OFF);
+
+    assert_eq!(note_ons.len(), 2);
+    assert_eq!(note_offs.len(), 2);
+
+    for (on, off) in note_ons.iter().zip(note_offs.iter()) {
+        assert!((2 * STEP..2 * STEP + 100).contains(&(off - on)));
+    }
+}
+
+#[test]
+fn notes_held_across_the_loop_end_are_released() {
+    let mut core = UncycleCore::new(120.0);
+    record_note(&mut core, 36, LOOP_LEN - STEP, 2 * STEP);
+
+    play(&mut core, 0, LOOP_LEN, || 100);
+    let played = play(&mut core, LOOP_LEN, 2 * LOOP_LEN + 4 * STEP, || 100);
+
+    let note_on = times_of(&played, NOTE_ON)[0];
+    let note_off = times_of(&played, NOTE_OFF)[0];
+
+    assert!((2 * LOOP_LEN - STEP..2 * LOOP_LEN - STEP + 100).contains(&note_on));
+    assert!(note_off >= note_on + 2 * STEP);
+}
+
+#[test]
+fn held_notes_are_not_played_back() {
+    let mut core = UncycleCore::new(120.0);
+    core.looper.start_recording(0);
+    core.looper.record_note_on(STEP, 36, 100);
+
+    play(&mut core, 0, LOOP_LEN, || 100);
+    assert!(play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100).is_empty());
+
+    // the length is known as soon as the note is released
+    core.looper.record_note_off(2 * LOOP_LEN + 2 * STEP, 36);
+    let played = play(&mut core, 2 * LOOP_LEN, 4 * LOOP_LEN, || 100);
+
+    assert_eq!(times_of(&played, NOTE_ON).len(), 2);
+    assert_eq!(times_of(&played, NOTE_OFF).len(), 2);
+}
+
+#[test]
+fn deleting_the_recording_releases_sounding_notes() {
+    let mut core = UncycleCore::new(120.0);
+    record_note(&mut core, 36, 0, LOOP_LEN / 2);
+
+    play(&mut core, 0, LOOP_LEN + STEP, || 100);
+    core.looper.delete_recording();
+
+    let played = play(&mut core, LOOP_LEN + STEP, LOOP_LEN + 2 * STEP, || 100);
+    assert_eq!(played, [(LOOP_LEN + STEP + 100, NOTE_OFF)]);
+}
+
+#[test]
+fn releasing_notes_ends_them_early() {
+    let mut core = UncycleCore::new(120.0);
+    record_note(&mut core, 36, 0, LOOP_LEN / 2);
+
+    play(&mut core, 0, LOOP_LEN + STEP, || 100);
+    core.looper.release_notes();
+
+    let played = play(&mut core, LOOP_LEN + STEP, 2 * LOOP_LEN, || 100);
+    assert_eq!(played, [(LOOP_LEN + STEP + 100, NOTE_OFF)]);
+}
+
+#[test]
+fn notes_share_the_quantisation_grid() {
+    let mut core = UncycleCore::new(120.0);
+    core.looper.set_quantize(Quantize::Sixteenth);
+    record_note(&mut core, 36, STEP + 10, STEP);
+
+    play(&mut core, 0, LOOP_LEN, || 100);
+    let played = play(&mut core, LOOP_LEN, 2 * LOOP_LEN, || 100);
+
+    let note_on = times_of(&played, NOTE_ON)[0];
+    assert!((LOOP_LEN + STEP..LOOP_LEN + STEP + 100).contains(&note_on));
+}
+
+#[test]
+fn notes_moved_forward_by_the_grid_keep_their_length() {
+    let mut core = UncycleCore::new(120.0);
+    core.looper.set_quantize(Quantize::Sixteenth);
+    record_note(&mut core, 36, STEP - 300, 200);
+
+    let step = STEP as u32;
+    assert_eq!(
+        core.looper.loop_events().as_slice(),
+        [(step, NOTE_ON), (step + 200, NOTE_OFF)]
+    );
+
+    // snapped to the start of the loop
+    core.looper.delete_recording();
+    record_note(&mut core, 36, LOOP_LEN - 300, 200);
+
+    assert_eq!(
+        core.looper.loop_events().as_slice(),
+        [(0, NOTE_ON), (200, NOTE_OFF)]
+    );
+}
+
+#[test]
+fn notes_without_length_are_released_after_they_are_played() {
+    let mut core = UncycleCore::new(120.0);
+    record_note(&mut core, 36, STEP, 0);
+
+    let step = STEP as u32;
+    assert_eq!(
+        core.looper.loop_events().as_slice(),
+        [(step, NOTE_ON), (step + 1, NOTE_OFF)]
+    );
+}
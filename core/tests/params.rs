@@ -0,0 +1,251 @@
+use uncycle_core::{devices::TR8, prelude::*};
+
+/// Channel 10 of the TR-8
+const DEVICE_CHANNEL: u8 = 9;
+
+fn cc(control: u8, value: u8) -> MidiMessage {
+    MidiMessage::ControlChange {
+        channel: DEVICE_CHANNEL,
+        control,
+        value,
+    }
+}
+
+#[test]
+fn lsb_refines_the_msb_it_follows() {
+    let mut decoder = ParamDecoder::new();
+
+    assert_eq!(
+        decoder.decode(1, 0x40),
+        Decoded::Change(ParamChange::cc(1, 0x40))
+    );
+    assert_eq!(
+        decoder.decode(33, 0x05),
+        Decoded::Refine(ParamChange {
+            param: Param::Cc14(1),
+            value: 0x40 << 7 | 0x05,
+        })
+    );
+
+    // devices may only send the LSB if the MSB did not change
+    decoder.decode(7, 100);
+    assert_eq!(
+        decoder.decode(33, 0x06),
+        Decoded::Change(ParamChange {
+            param: Param::Cc14(1),
+            value: 0x40 << 7 | 0x06,
+        })
+    );
+}
+
+#[test]
+fn lsb_without_msb_is_a_plain_cc() {
+    let mut decoder = ParamDecoder::new();
+
+    assert_eq!(
+        decoder.decode(52, 10),
+        Decoded::Change(ParamChange::cc(52, 10))
+    );
+    assert_eq!(
+        decoder.decode(80, 10),
+        Decoded::Change(ParamChange::cc(80, 10))
+    );
+}
+
+#[test]
+fn nrpn_and_rpn_are_decoded_from_data_entry() {
+    let mut decoder = ParamDecoder::new();
+    let nrpn = Param::Nrpn(3 << 7 | 17);
+
+    assert_eq!(decoder.decode(CC_NRPN_MSB, 3), Decoded::Incomplete);
+    assert_eq!(decoder.decode(CC_NRPN_LSB, 17), Decoded::Incomplete);
+    assert_eq!(
+        decoder.decode(CC_DATA_ENTRY_MSB, 2),
+        Decoded::Change(ParamChange {
+            param: nrpn,
+            value: 2 << 7
+        })
+    );
+    assert_eq!(
+        decoder.decode(CC_DATA_ENTRY_LSB, 1),
+        Decoded::Refine(ParamChange {
+            param: nrpn,
+            value: 2 << 7 | 1
+        })
+    );
+
+    // pitch bend sensitivity, then RPN null
+    decoder.decode(CC_RPN_MSB, 0);
+    decoder.decode(CC_RPN_LSB, 0);
+    assert_eq!(
+        decoder.decode(CC_DATA_ENTRY_MSB, 12),
+        Decoded::Change(ParamChange {
+            param: Param::Rpn(0),
+            value: 12 << 7
+        })
+    );
+
+    decoder.decode(CC_RPN_MSB, 0x7F);
+    decoder.decode(CC_RPN_LSB, 0x7F);
+    assert_eq!(
+        decoder.decode(CC_DATA_ENTRY_MSB, 12),
+        Decoded::Change(ParamChange::cc(CC_DATA_ENTRY_MSB, 12))
+    );
+}
+
+#[test]
+fn messages_are_sent_in_order() {
+    let cc14 = ParamChange {
+        param: Param::Cc14(1),
+        value: 0x40 << 7 | 0x05,
+    };
+    assert_eq!(
+        cc14.to_messages(DEVICE_CHANNEL),
+        [cc(1, 0x40), cc(33, 0x05)]
+    );
+
+    let nrpn = ParamChange {
+        param: Param::Nrpn(3 << 7 | 17),
+        value: 2 << 7 | 1,
+    };
+    assert_eq!(
+        nrpn.to_messages(DEVICE_CHANNEL),
+        [
+            cc(CC_NRPN_MSB, 3),
+            cc(CC_NRPN_LSB, 17),
+            cc(CC_DATA_ENTRY_MSB, 2),
+            cc(CC_DATA_ENTRY_LSB, 1)
+        ]
+    );
+}
+
+#[test]
+fn nrpn_is_played_back_as_one_group() {
+    let mut now = 0;
+    let mut core = UncycleCore::new(120.0);
+    core.set_high_res_params(true);
+    core.set_device(SupportedDevice::TR8(TR8::default()));
+    core.start_stop_sequence();
+
+    let mut run = |core: &mut UncycleCore, duration: u64| {
+        let mut sent = Vec::new();
+        let end = now + duration;
+
+        while now < end {
+            now += 100;
+            core.update_time(now);
+            sent.extend(core.midi_tx_callback());
+        }
+
+        sent
+    };
+
+    run(&mut core, 1_000);
+    core.start_recording();
+    run(&mut core, 100_000);
+
+    let nrpn = [
+        cc(CC_NRPN_MSB, 3),
+        cc(CC_NRPN_LSB, 17),
+        cc(CC_DATA_ENTRY_MSB, 2),
+        cc(CC_DATA_ENTRY_LSB, 1),
+    ];
+    for message in &nrpn {
+        core.midi_rx_callback(&message.to_bytes());
+    }
+
+    // 32 steps at 120 BPM take 4s, then the loop is played back once
+    let sent = run(&mut core, 6_000_000);
+
+    let expected: Vec<u8> = nrpn.iter().flat_map(|message| message.to_bytes()).collect();
+    let played: Vec<u8> = sent
+        .into_iter()
+        .filter(|byte| !is_real_time(*byte))
+        .collect();
+
+    assert_eq!(played, expected);
+}
+
+#[test]
+fn gestures_are_sent_as_their_own_messages() {
+    let bend = ParamChange {
+        param: Param::PitchBend,
+        value: PITCH_BEND_CENTER + 100,
+    };
+    assert_eq!(
+        bend.to_messages(DEVICE_CHANNEL),
+        [MidiMessage::PitchBend {
+            channel: DEVICE_CHANNEL,
+            value: PITCH_BEND_CENTER + 100,
+        }]
+    );
+
+    let pressure = ParamChange {
+        param: Param::PolyPressure(36),
+        value: 90,
+    };
+    assert_eq!(
+        pressure.to_messages(DEVICE_CHANNEL),
+        [MidiMessage::PolyAftertouch {
+            channel: DEVICE_CHANNEL,
+            note: 36,
+            pressure: 90,
+        }]
+    );
+
+    assert_eq!(Param::PitchBend.lane(), LANE_PITCH_BEND);
+    assert_eq!(Param::ChannelPressure.lane(), LANE_CHANNEL_PRESSURE);
+    assert_eq!(Param::PolyPressure(36).lane(), LANE_POLY_PRESSURE);
+}
+
+#[test]
+fn dense_streams_are_thinned_to_one_message_per_pulse() {
+    let pulse = SUBTICKS_PER_TICK as u64;
+    let mut core = UncycleCore::new(120.0);
+    core.looper.start_recording(0);
+
+    // a pitch bend sweep with 8 messages per clock pulse
+    for i in 0..64 {
+        let change = ParamChange {
+            param: Param::PitchBend,
+            value: PITCH_BEND_CENTER + i,
+        };
+        core.looper.record_param(i as u64 * pulse / 8, change);
+    }
+
+    assert_eq!(core.looper.recorded.len(), 8);
+    assert!(core.looper.lanes_with_automation()[LANE_PITCH_BEND as usize]);
+
+    // the end of the gesture is kept
+    let loop_len = 32 * (PPQN / 4) as u64 * pulse;
+    let mut played = Vec::new();
+    let mut now = 0;
+    while now < 2 * loop_len {
+        now = (now + 100).min(2 * loop_len);
+        core.looper.handle_eol(now);
+        played.extend(core.looper.play_back_recording(now).iter().copied());
+    }
+
+    assert_eq!(played.len(), 8);
+    assert_eq!(
+        played.last(),
+        Some(&LoopEvent::Param(ParamChange {
+            param: Param::PitchBend,
+            value: PITCH_BEND_CENTER + 63,
+        }))
+    );
+}
+
+#[test]
+fn aftertouch_of_other_channels_is_not_recorded() {
+    let mut core = UncycleCore::new(120.0);
+    core.set_device(SupportedDevice::TR8(TR8::default()));
+    core.looper.start_recording(0);
+
+    core.midi_rx_callback(&[MIDI_CHANNEL_AFTERTOUCH | DEVICE_CHANNEL, 80]);
+    core.midi_rx_callback(&[MIDI_CHANNEL_AFTERTOUCH, 80]);
+
+    let automated = core.looper.lanes_with_automation();
+    assert!(automated[LANE_CHANNEL_PRESSURE as usize]);
+    assert_eq!(core.looper.recorded.len(), 1);
+}
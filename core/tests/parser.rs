@@ -0,0 +1,203 @@
+mod common;
+
+use common::*;
+use uncycle_core::prelude::*;
+
+const REAL_TIME: [u8; 6] = [
+    MIDI_CLOCK,
+    MIDI_START,
+    MIDI_CONTINUE,
+    MIDI_STOP,
+    MIDI_ACTIVE_SENSING,
+    MIDI_SYSTEM_RESET,
+];
+
+fn parse(parser: &mut MidiParser, bytes: &[u8]) -> Vec<MidiEvent> {
+    bytes.iter().flat_map(|byte| parser.push(*byte)).collect()
+}
+
+fn msg(bytes: &[u8]) -> MidiEvent {
+    MidiEvent::Message(MidiMessage::from_bytes(bytes).unwrap())
+}
+
+#[test]
+fn running_status_reuses_last_channel_status() {
+    let mut parser = MidiParser::new();
+    let events = parse(&mut parser, &[0x90, 60, 100, 62, 100, 64, 0]);
+
+    assert_eq!(
+        events,
+        [
+            msg(&[0x90, 60, 100]),
+            msg(&[0x90, 62, 100]),
+            msg(&[0x90, 64, 0])
+        ]
+    );
+}
+
+#[test]
+fn system_common_cancels_running_status() {
+    let mut parser = MidiParser::new();
+    let events = parse(&mut parser, &[0xB0, 1, 2, 0xF3, 5, 3, 4]);
+
+    assert_eq!(events, [msg(&[0xB0, 1, 2]), msg(&[0xF3, 5])]);
+}
+
+#[test]
+fn real_time_bytes_pass_through_other_messages() {
+    let mut parser = MidiParser::new();
+    let events = parse(&mut parser, &[0xE0, MIDI_CLOCK, 0x00, MIDI_STOP, 0x40]);
+
+    assert_eq!(
+        events,
+        [
+            msg(&[MIDI_CLOCK]),
+            msg(&[MIDI_STOP]),
+            msg(&[0xE0, 0x00, 0x40])
+        ]
+    );
+}
+
+#[test]
+fn sysex_is_framed() {
+    let mut parser = MidiParser::new();
+    let events = parse(
+        &mut parser,
+        &[MIDI_SYSEX, 0x41, MIDI_CLOCK, 0x10, MIDI_SYSEX_END],
+    );
+
+    assert_eq!(
+        events,
+        [
+            MidiEvent::SysExStart,
+            MidiEvent::SysExByte(0x41),
+            msg(&[MIDI_CLOCK]),
+            MidiEvent::SysExByte(0x10),
+            MidiEvent::SysExEnd { complete: true },
+        ]
+    );
+}
+
+#[test]
+fn status_byte_interrupts_sysex() {
+    let mut parser = MidiParser::new();
+
+    let events = parse(&mut parser, &[MIDI_SYSEX, 0x41, 0x90, 60, 100]);
+    assert_eq!(
+        events,
+        [
+            MidiEvent::SysExStart,
+            MidiEvent::SysExByte(0x41),
+            MidiEvent::SysExEnd { complete: false },
+            msg(&[0x90, 60, 100]),
+        ]
+    );
+
+    let events = parse(&mut parser, &[MIDI_SYSEX, MIDI_TUNE_REQUEST]);
+    assert_eq!(
+        events,
+        [
+            MidiEvent::SysExStart,
+            MidiEvent::SysExEnd { complete: false },
+            msg(&[MIDI_TUNE_REQUEST]),
+        ]
+    );
+}
+
+/// Random valid stream with running status, interleaved real-time bytes and SysEx is parsed back exactly
+#[test]
+fn random_streams_are_parsed_back() {
+    for seed in 0..32 {
+        let mut lcg = Lcg(seed);
+        let mut bytes = Vec::new();
+        let mut expected = Vec::new();
+        let mut running_status = None;
+
+        for _ in 0..500 {
+            if lcg.chance(10) {
+                bytes.push(MIDI_SYSEX);
+                expected.push(MidiEvent::SysExStart);
+
+                for _ in 0..lcg.below(16) {
+                    let data = lcg.below(0x80);
+                    bytes.push(data);
+                    expected.push(MidiEvent::SysExByte(data));
+                }
+
+                bytes.push(MIDI_SYSEX_END);
+                expected.push(MidiEvent::SysExEnd { complete: true });
+                running_status = None;
+                continue;
+            }
+
+            let status = loop {
+                let status = 0x80 + lcg.below(0x80);
+                if status != MIDI_SYSEX && message_len(status).is_some() {
+                    break status;
+                }
+            };
+            let len = message_len(status).unwrap();
+            let data = [status, lcg.below(0x80), lcg.below(0x80)];
+            let message = MidiMessage::from_bytes(&data[..len]).unwrap();
+
+            let skip_status = running_status == Some(status) && lcg.chance(50);
+            let wire = if skip_status {
+                &data[1..len]
+            } else {
+                &data[..len]
+            };
+
+            for byte in wire {
+                if lcg.chance(20) {
+                    let real_time = REAL_TIME[lcg.below(REAL_TIME.len() as u32) as usize];
+                    bytes.push(real_time);
+                    expected.push(msg(&[real_time]));
+                }
+
+                bytes.push(*byte);
+            }
+
+            expected.push(MidiEvent::Message(message));
+
+            running_status = match status {
+                0x80..=0xEF => Some(status),
+                _ if message.is_real_time() => running_status,
+                _ => None,
+            };
+        }
+
+        let mut parser = MidiParser::new();
+        assert_eq!(parse(&mut parser, &bytes), expected, "seed {seed}");
+    }
+}
+
+/// Random garbage never panics and only yields valid messages and balanced SysEx frames
+#[test]
+fn random_bytes_yield_valid_events() {
+    for seed in 0..8 {
+        let mut lcg = Lcg(seed);
+        let mut parser = MidiParser::new();
+        let mut in_sysex = false;
+
+        for _ in 0..100_000 {
+            for event in parser.push(lcg.below(0x100)) {
+                match event {
+                    MidiEvent::Message(message) => {
+                        assert_eq!(MidiMessage::from_bytes(&message.to_bytes()), Some(message));
+                    }
+                    MidiEvent::SysExStart => {
+                        assert!(!in_sysex, "seed {seed}");
+                        in_sysex = true;
+                    }
+                    MidiEvent::SysExByte(byte) => {
+                        assert!(in_sysex && is_data_byte(byte), "seed {seed}");
+                    }
+                    MidiEvent::SysExEnd { .. } => {
+                        assert!(in_sysex, "seed {seed}");
+                        in_sysex = false;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+mod common;
+
+use common::*;
+use uncycle_core::prelude::*;
+
+fn save(core: &UncycleCore) -> Vec<u8> {
+    let mut session = Vec::new();
+    core.save_session(&mut |bytes| session.extend_from_slice(bytes));
+
+    session
+}
+
+/// The shared recording with other settings than the defaults, followed by an overdub that has been undone
+fn session_core() -> UncycleCore {
+    let mut core = recorded_core();
+    core.decrease_bpm_by(24.0);
+    core.set_clock_source(ClockSource::External);
+    core.set_high_res_params(true);
+    core.set_device(SupportedDevice::from_id("roland-tr8").unwrap());
+    core.looper.set_quantize(Quantize::Sixteenth);
+    core.looper.set_overdub_mode(OverdubMode::Replace);
+    core.looper.handle_eol(LOOP_LEN);
+
+    core.looper.start_recording(LOOP_LEN);
+    core.looper
+        .record_param(LOOP_LEN + 4 * STEP, ParamChange::cc(25, 10));
+    core.looper.handle_eol(2 * LOOP_LEN);
+    core.looper.undo();
+
+    core.looper.toggle_mute_lane(LANE_PITCH_BEND);
+
+    core
+}
+
+#[test]
+fn sessions_restore_settings_and_recording() {
+    let core = session_core();
+    let session = save(&core);
+
+    let mut loaded = UncycleCore::new(120.0);
+    loaded.load_session(&session).unwrap();
+
+    assert_eq!(loaded.get_bpm(), 96.0);
+    assert_eq!(loaded.get_clock_source(), ClockSource::External);
+    assert!(loaded.get_high_res_params());
+    assert_eq!(loaded.device.as_ref().unwrap().id_to_str(), "roland-tr8");
+    assert_eq!(loaded.looper.get_quantize(), Quantize::Sixteenth);
+    assert_eq!(loaded.looper.get_overdub_mode(), OverdubMode::Replace);
+    assert!(loaded.looper.lane(LANE_PITCH_BEND).muted);
+
+    assert_eq!(loaded.looper.loop_events(), core.looper.loop_events());
+    assert_eq!(save(&loaded), session);
+}
+
+#[test]
+fn undone_passes_can_be_redone_after_loading() {
+    let mut loaded = UncycleCore::new(120.0);
+    loaded.load_session(&save(&session_core())).unwrap();
+
+    assert_eq!(loaded.looper.n_layers(), 1);
+    assert_eq!(loaded.looper.n_undone_layers(), 1);
+
+    loaded.looper.redo();
+    let overdub = ParamChange::cc(25, 10);
+
+    assert!(loaded
+        .looper
+        .loop_events()
+        .contains(&(4 * STEP as u32, LoopEvent::Param(overdub))));
+}
+
+#[test]
+fn other_data_is_rejected() {
+    let mut core = UncycleCore::new(120.0);
+
+    assert_eq!(core.load_session(b""), Err(SessionError::NotASession));
+    assert_eq!(core.load_session(b"MThd"), Err(SessionError::NotASession));
+
+    let mut session = save(&session_core());
+    session[4] = SESSION_VERSION + 1;
+
+    assert_eq!(
+        core.load_session(&session),
+        Err(SessionError::UnsupportedVersion(SESSION_VERSION + 1))
+    );
+}
+
+#[test]
+fn damaged_sessions_change_nothing() {
+    let session = save(&session_core());
+    let mut core = UncycleCore::new(120.0);
+
+    for len in 5..session.len() {
+        assert_eq!(
+            core.load_session(&session[..len]),
+            Err(SessionError::Malformed)
+        );
+    }
+
+    assert_eq!(core.get_bpm(), 120.0);
+    assert!(core.device.is_none());
+    assert!(core.looper.loop_events().is_empty());
+}
+
+#[test]
+fn the_channel_of_the_generic_device_is_restored() {
+    let mut core = UncycleCore::new(120.0);
+    core.set_device(SupportedDevice::Generic(GenericDevice::new(5)));
+
+    let mut loaded = UncycleCore::new(120.0);
+    loaded.load_session(&save(&core)).unwrap();
+
+    let device = loaded.device.as_ref().unwrap();
+    assert_eq!(device.id_to_str(), "generic");
+    assert_eq!(device.tx_channel(), 5);
+}
+
+#[test]
+fn version_1_sessions_are_read() {
+    let core = session_core();
+    let mut session = save(&core);
+
+    // magic, version, bpm, clock source, high res and the device id
+    let channel = 4 + 1 + 4 + 1 + 1 + 1 + "roland-tr8".len();
+    session[4] = 1;
+    session.remove(channel);
+
+    let mut loaded = UncycleCore::new(120.0);
+    loaded.load_session(&session).unwrap();
+
+    assert_eq!(loaded.device.as_ref().unwrap().id_to_str(), "roland-tr8");
+    assert_eq!(loaded.looper.loop_events(), core.looper.loop_events());
+}
+
+/// A session without device and with a single recorded message, written field by field so that any value fits
+fn session_with(loop_steps: u16, n_layers: u8, message: &[u8]) -> Vec<u8> {
+    let mut session = Vec::new();
+
+    {
+        let mut out = |bytes: &[u8]| session.extend_from_slice(bytes);
+        let mut w = SessionWriter::new(&mut out);
+
+        w.f32(120.0);
+        w.u8(0);
+        w.bool(false);
+        w.bytes(&[]);
+        w.u8(0);
+
+        w.u16(loop_steps);
+        for _ in 0..4 {
+            w.u8(0);
+        }
+        for _ in 0..N_LANES {
+            w.u8(0);
+        }
+
+        w.u8(n_layers);
+        w.u8(n_layers);
+        w.u16(1);
+    }
+
+    session.extend_from_slice(message);
+    session
+}
+
+/// Time and layer 0, not replaced
+const MESSAGE_START: [u8; 6] = [0, 0, 0, 0, 0, 0xFF];
+const NOTE: u8 = 1;
+const PARAM: u8 = 0;
+
+fn note(note: u8, velocity: u8) -> Vec<u8> {
+    [
+        &MESSAGE_START[..],
+        &[NOTE, note, velocity, 0xFF, 0xFF, 0xFF, 0xFF],
+    ]
+    .concat()
+}
+
+fn param(kind: u8, number: u16, value: u16) -> Vec<u8> {
+    let mut message = [&MESSAGE_START[..], &[PARAM, kind]].concat();
+    message.extend_from_slice(&number.to_le_bytes());
+    message.extend_from_slice(&value.to_le_bytes());
+
+    message
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    let mut core = UncycleCore::new(120.0);
+    core.load_session(&session_with(32, 1, &note(36, 100)))
+        .unwrap();
+    core.looper.delete_recording();
+
+    let replaced_by_missing_pass = [0, 0, 0, 0, 0, 1, NOTE, 36, 100, 0xFF, 0xFF, 0xFF, 0xFF];
+
+    let sessions = [
+        session_with(32, 1, &note(200, 100)),
+        session_with(32, 1, &note(36, 128)),
+        session_with(32, 1, &param(0, 128, 0)),
+        session_with(32, 1, &param(0, 24, 128)),
+        session_with(32, 1, &param(1, 32, 0)),
+        session_with(32, 1, &param(6, 128, 0)),
+        session_with(32, 1, &replaced_by_missing_pass),
+        session_with(32, 40, &note(36, 100)),
+        session_with(300, 1, &note(36, 100)),
+    ];
+
+    for session in sessions {
+        assert_eq!(core.load_session(&session), Err(SessionError::Malformed));
+    }
+
+    assert!(core.looper.loop_events().is_empty());
+    assert_eq!(core.looper.n_layers(), 0);
+}
@@ -0,0 +1,296 @@
+mod common;
+
+use common::*;
+use uncycle_core::prelude::*;
+
+/// Sixteenth in file ticks
+const SMF_STEP: u32 = SMF_PPQ as u32 / 4;
+
+fn export(core: &UncycleCore, format: SmfFormat) -> Vec<u8> {
+    let mut file = Vec::new();
+    core.export_smf(format, &mut |bytes| file.extend_from_slice(bytes));
+
+    file
+}
+
+/// Splits a file into its chunks
+fn split_chunks(mut file: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+
+    while !file.is_empty() {
+        let len = u32::from_be_bytes(file[4..8].try_into().unwrap()) as usize;
+        chunks.push((&file[..4], &file[8..8 + len]));
+        file = &file[8 + len..];
+    }
+
+    chunks
+}
+
+/// Reads a variable length quantity and returns it with the remaining bytes
+fn var_len(bytes: &[u8]) -> (u32, &[u8]) {
+    let mut value = 0;
+
+    for (i, byte) in bytes.iter().enumerate() {
+        value = value << 7 | (byte & 0x7F) as u32;
+
+        if byte & 0x80 == 0 {
+            return (value, &bytes[i + 1..]);
+        }
+    }
+
+    panic!("unterminated variable length quantity");
+}
+
+/// Absolute times and bytes of the channel messages of a track, without meta events, and the time the track ends
+fn parse_track(mut track: &[u8]) -> (Vec<(u32, Vec<u8>)>, u32) {
+    let mut events = Vec::new();
+    let mut time = 0;
+
+    while !track.is_empty() {
+        let (delta, rest) = var_len(track);
+        time += delta;
+
+        if rest[0] == 0xFF {
+            let (len, data) = var_len(&rest[2..]);
+            track = &data[len as usize..];
+        } else {
+            let len = message_len(rest[0]).unwrap();
+            events.push((time, rest[..len].to_vec()));
+            track = &rest[len..];
+        }
+    }
+
+    (events, time)
+}
+
+fn channel_events(track: &[u8]) -> Vec<(u32, Vec<u8>)> {
+    parse_track(track).0
+}
+
+#[test]
+fn header_matches_the_format() {
+    let core = recorded_core();
+
+    let file = export(&core, SmfFormat::SingleTrack);
+    let chunks = split_chunks(&file);
+    assert_eq!(chunks[0], (&b"MThd"[..], &[0, 0, 0, 1, 0x01, 0xE0][..]));
+    assert_eq!(chunks.len(), 2);
+
+    let file = export(&core, SmfFormat::MultiTrack);
+    let chunks = split_chunks(&file);
+    assert_eq!(chunks[0], (&b"MThd"[..], &[0, 1, 0, 2, 0x01, 0xE0][..]));
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks[1..].iter().all(|(id, _)| *id == b"MTrk"));
+}
+
+#[test]
+fn tempo_follows_the_bpm() {
+    let core = recorded_core();
+    let file = export(&core, SmfFormat::SingleTrack);
+
+    // 500 000 µs per quarter at 120 BPM
+    let tempo = [0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20];
+    assert!(file.windows(tempo.len()).any(|window| window == tempo));
+}
+
+#[test]
+fn events_are_ordered_by_time() {
+    let core = recorded_core();
+    let file = export(&core, SmfFormat::MultiTrack);
+    let chunks = split_chunks(&file);
+
+    assert!(channel_events(chunks[1].1).is_empty());
+    assert_eq!(
+        channel_events(chunks[2].1),
+        [
+            (0, vec![MIDI_CONTORL_CHANGE, 24, 10]),
+            (SMF_STEP, vec![MIDI_NOTE_ON, 36, 127]),
+            (2 * SMF_STEP, vec![MIDI_CONTORL_CHANGE, 24, 100]),
+            (3 * SMF_STEP, vec![MIDI_NOTE_OFF, 36, 0]),
+        ]
+    );
+}
+
+#[test]
+fn track_ends_with_the_loop() {
+    let mut core = recorded_core();
+    core.looper.set_loop_steps(16);
+
+    let file = export(&core, SmfFormat::SingleTrack);
+    let track = split_chunks(&file)[1].1;
+
+    assert!(track.ends_with(&[0xFF, 0x2F, 0x00]));
+    assert_eq!(parse_track(track).1, 16 * SMF_STEP);
+}
+
+/// Type 0 file at 96 PPQ with a single track
+fn file_with_track(track: &[u8]) -> Vec<u8> {
+    let mut file = b"MThd".to_vec();
+    file.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0, 96]);
+    file.extend_from_slice(b"MTrk");
+    file.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    file.extend_from_slice(track);
+
+    file
+}
+
+#[test]
+fn exported_loop_is_imported_again() {
+    let core = recorded_core();
+    let file = export(&core, SmfFormat::MultiTrack);
+
+    let mut imported = UncycleCore::new(90.0);
+    let report = imported.import_smf(&file).unwrap();
+
+    // two CC and a note
+    assert_eq!(report.n_events, 3);
+    assert_eq!(report.n_unsupported, 0);
+    assert_eq!(report.loop_steps, 32);
+    assert_eq!(imported.looper.loop_events(), core.looper.loop_events());
+}
+
+#[test]
+fn running_status_and_unsupported_events() {
+    // a CC ramp with running status, a program change and a SysEx, ending after 4 quarters
+    let track = [
+        0x00, 0xB0, 24, 0, // CC 24 at 0
+        0x18, 24, 32, // running status, a 16th later
+        0x18, 24, 64, //
+        0x00, 0xC0, 5, // program change
+        0x00, 0xF0, 0x02, 0x7E, 0xF7, // SysEx
+        0x82, 0x50, 0xFF, 0x2F, 0x00, // end of track after 4 quarters
+    ];
+
+    let mut core = UncycleCore::new(120.0);
+    let report = core.import_smf(&file_with_track(&track)).unwrap();
+
+    assert_eq!(report.n_events, 3);
+    assert_eq!(report.n_unsupported, 2);
+    assert!(!report.truncated);
+    assert_eq!(report.loop_steps, 16);
+
+    let events = core.looper.loop_events();
+    assert_eq!(
+        events[1],
+        (STEP as u32, LoopEvent::Param(ParamChange::cc(24, 32)))
+    );
+}
+
+#[test]
+fn files_longer_than_the_longest_loop_are_cut_off() {
+    // 65 bars at 96 PPQ
+    let track = [
+        0x00, 0x90, 36, 100, //
+        0x81, 0x89, 0x40, 0x80, 36, 0, //
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let mut core = UncycleCore::new(120.0);
+    let report = core.import_smf(&file_with_track(&track)).unwrap();
+
+    assert!(report.truncated);
+    assert_eq!(report.loop_steps, 256);
+
+    // the note is held until the end of the loop
+    let events = core.looper.loop_events();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[1],
+        (256 * STEP as u32, LoopEvent::NoteOff { note: 36 })
+    );
+}
+
+#[test]
+fn invalid_files_are_rejected() {
+    let mut core = UncycleCore::new(120.0);
+
+    assert_eq!(core.import_smf(b"RIFF"), Err(SmfError::NotAnSmf));
+    assert_eq!(
+        core.import_smf(&file_with_track(&[0x00, 0x90, 36])),
+        Err(SmfError::Malformed)
+    );
+    assert_eq!(
+        core.import_smf(&file_with_track(&[0x00, 40, 100])),
+        Err(SmfError::Malformed)
+    );
+
+    // SMPTE divisions with a frame rate of -128 and 23
+    for frame_rate in [0x80, 0xE9] {
+        let mut file = file_with_track(&[0x00, 0xFF, 0x2F, 0x00]);
+        file[12] = frame_rate;
+        file[13] = 40;
+
+        assert_eq!(core.import_smf(&file), Err(SmfError::Malformed));
+    }
+}
+
+#[test]
+fn smpte_divisions_are_read() {
+    // 25 frames per second with 40 ticks each make a millisecond, the CC is half a second in
+    let mut file = file_with_track(&[
+        0x00, 0xB0, 24, 0, //
+        0x83, 0x74, 24, 64, //
+        0x83, 0x74, 0xFF, 0x2F, 0x00,
+    ]);
+    file[12] = -25_i8 as u8;
+    file[13] = 40;
+
+    let mut core = UncycleCore::new(120.0);
+    core.import_smf(&file).unwrap();
+
+    // a quarter at 120 BPM
+    assert_eq!(
+        core.looper.loop_events()[1],
+        (4 * STEP as u32, LoopEvent::Param(ParamChange::cc(24, 64)))
+    );
+}
+
+#[test]
+fn events_beyond_the_recording_are_dropped() {
+    // 5000 CC with running status, one tick apart
+    let mut track = vec![0x00, 0xB0, 24, 0];
+    for i in 1..5000 {
+        track.extend_from_slice(&[0x01, 24, (i % 128) as u8]);
+    }
+    track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+
+    let mut core = UncycleCore::new(120.0);
+    let report = core.import_smf(&file_with_track(&track)).unwrap();
+
+    assert!(report.n_events > 0);
+    assert_eq!(report.n_events + report.n_dropped, 5000);
+    assert_eq!(core.looper.loop_events().len(), report.n_events);
+}
+
+#[test]
+fn events_at_the_end_of_the_loop_are_dropped() {
+    // the second CC starts exactly when the 4 quarters of the file end
+    let track = [
+        0x00, 0xB0, 24, 0, //
+        0x83, 0x00, 24, 64, //
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let mut core = UncycleCore::new(120.0);
+    let report = core.import_smf(&file_with_track(&track)).unwrap();
+
+    assert_eq!(report.loop_steps, 16);
+    assert_eq!(report.n_events, 1);
+    assert_eq!(report.n_dropped, 1);
+}
+
+#[test]
+fn files_without_loopable_events_keep_the_recording() {
+    let mut core = recorded_core();
+    let events = core.looper.loop_events();
+
+    // only a program change
+    let track = [0x00, 0xC0, 5, 0x00, 0xFF, 0x2F, 0x00];
+
+    assert_eq!(
+        core.import_smf(&file_with_track(&track)),
+        Err(SmfError::Empty)
+    );
+    assert_eq!(core.looper.loop_events(), events);
+    assert_eq!(core.looper.n_layers(), 1);
+}
@@ -0,0 +1,143 @@
+use uncycle_core::prelude::*;
+
+/// Roland Identity Reply of some device
+const IDENTITY_REPLY: [u8; 15] = [
+    0xF0, 0x7E, 0x10, 0x06, 0x02, 0x41, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF7,
+];
+
+#[test]
+fn sysex_is_reassembled_from_chunks() {
+    let mut core = UncycleCore::new(120.0);
+
+    for chunk in IDENTITY_REPLY.chunks(4) {
+        core.midi_rx_callback(chunk);
+    }
+    core.midi_rx_callback(&[MIDI_CLOCK]);
+
+    let sysex = core.pop_received_sysex().unwrap();
+    assert_eq!(sysex.payload(), &IDENTITY_REPLY[1..14]);
+    assert_eq!(sysex.n_bytes(), IDENTITY_REPLY.len());
+    assert!(!sysex.is_truncated());
+
+    assert!(core.pop_received_sysex().is_none());
+}
+
+#[test]
+fn long_sysex_is_truncated() {
+    let mut buffer = SysExBuffer::<4>::new();
+    let mut parser = MidiParser::new();
+    let mut complete = false;
+
+    for byte in [MIDI_SYSEX, 1, 2, 3, 4, 5, 6, MIDI_SYSEX_END] {
+        for event in parser.push(byte) {
+            complete |= buffer.push(&event);
+        }
+    }
+
+    assert!(complete);
+    assert!(buffer.is_truncated());
+    assert_eq!(buffer.payload(), &[1, 2, 3, 4]);
+}
+
+#[test]
+fn interrupted_sysex_is_discarded() {
+    let mut core = UncycleCore::new(120.0);
+
+    core.midi_rx_callback(&[MIDI_SYSEX, 0x41, 0x10]);
+    core.midi_rx_callback(&[0xB0, 1, 2]);
+
+    assert!(core.pop_received_sysex().is_none());
+}
+
+#[test]
+fn queued_sysex_is_sent_one_at_a_time() {
+    let mut core = UncycleCore::new(120.0);
+
+    assert!(core.send_sysex(&IDENTITY_REPLY[1..14]));
+    assert!(core.send_sysex(&[0x7E, 0x7F, 0x06, 0x01]));
+
+    let sent = core.midi_tx_callback();
+    let start = sent.iter().position(|byte| *byte == MIDI_SYSEX).unwrap();
+    assert_eq!(&sent[start..start + IDENTITY_REPLY.len()], &IDENTITY_REPLY);
+    assert_eq!(sent.iter().filter(|byte| **byte == MIDI_SYSEX).count(), 1);
+
+    let sent = core.midi_tx_callback();
+    assert!(sent.ends_with(&[MIDI_SYSEX, 0x7E, 0x7F, 0x06, 0x01, MIDI_SYSEX_END]));
+
+    let sent = core.midi_tx_callback();
+    assert!(!sent.contains(&MIDI_SYSEX));
+}
+
+#[test]
+fn invalid_sysex_is_not_sent() {
+    let mut core = UncycleCore::new(120.0);
+
+    assert!(!core.send_sysex(&[0x41, 0x90]));
+    assert!(!core.send_sysex(&[0; SYSEX_LEN + 1]));
+    assert!(core.send_sysex(&[0; SYSEX_LEN]));
+}
+
+#[test]
+fn identity_replies_are_parsed() {
+    let identity = Identity::from_reply(&IDENTITY_REPLY[1..14]).unwrap();
+
+    assert_eq!(identity.device_id, 0x10);
+    assert_eq!(identity.manufacturer, MANUFACTURER_ROLAND);
+    assert_eq!(identity.family, 0);
+    assert_eq!(identity.member, 0);
+
+    let extended = [
+        0x7E, 0x7F, 0x06, 0x02, 0x00, 0x20, 0x6B, 0x02, 0x01, 0x04, 0x00, 1, 2, 3, 4,
+    ];
+    let identity = Identity::from_reply(&extended).unwrap();
+
+    assert_eq!(identity.manufacturer, ManufacturerId::Extended(0x20, 0x6B));
+    assert_eq!(identity.family, 0x0102);
+    assert_eq!(identity.member, 0x0004);
+    assert_eq!(identity.version, [1, 2, 3, 4]);
+}
+
+#[test]
+fn identity_replies_can_be_longer() {
+    let mut padded = IDENTITY_REPLY[1..14].to_vec();
+    padded.extend([0x00, 0x00]);
+
+    let identity = Identity::from_reply(&padded).unwrap();
+    assert_eq!(identity.device_id, 0x10);
+    assert_eq!(identity.manufacturer, MANUFACTURER_ROLAND);
+
+    let extended = [
+        0x7E, 0x7F, 0x06, 0x02, 0x00, 0x20, 0x6B, 0x02, 0x01, 0x04, 0x00, 1, 2, 3, 4, 0x7F,
+    ];
+    let identity = Identity::from_reply(&extended).unwrap();
+
+    assert_eq!(identity.family, 0x0102);
+    assert_eq!(identity.version, [1, 2, 3, 4]);
+
+    // an extended ID leaves no room for the version
+    assert!(Identity::from_reply(&extended[..14]).is_none());
+}
+
+#[test]
+fn other_sysex_is_no_identity_reply() {
+    // the request itself
+    assert!(Identity::from_reply(&IDENTITY_REQUEST_PAYLOAD).is_none());
+    // cut off
+    assert!(Identity::from_reply(&IDENTITY_REPLY[1..13]).is_none());
+    // Roland DT1
+    assert!(Identity::from_reply(&[0x41, 0x10, 0x00, 0x12, 0x01]).is_none());
+}
+
+#[test]
+fn identity_requests_address_every_device() {
+    let mut core = UncycleCore::new(120.0);
+    assert!(core.send_sysex(&IDENTITY_REQUEST_PAYLOAD));
+
+    let sent = core.midi_tx_callback();
+    let start = sent.iter().position(|byte| *byte == MIDI_SYSEX).unwrap();
+
+    assert_eq!(
+        &sent[start..start + 6],
+        &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]
+    );
+}
@@ -0,0 +1,163 @@
+use uncycle_core::prelude::*;
+
+/// Deterministic pseudo random jitter, so that failures are reproducible
+struct Jitter {
+    state: u32,
+    max_us: i64,
+}
+
+impl Jitter {
+    fn new(seed: u32, max_us: i64) -> Self {
+        Self {
+            state: seed,
+            max_us,
+        }
+    }
+
+    /// uniformly distributed in `-max_us..=max_us`
+    fn next(&mut self) -> i64 {
+        self.state = self
+            .state
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        let unit = (self.state >> 8) as i64 % (2 * self.max_us + 1);
+        unit - self.max_us
+    }
+}
+
+fn period_us(bpm: f64) -> f64 {
+    60_000_000.0 / (bpm * PPQN as f64)
+}
+
+/// Feeds `n_pulses` of a jittered pulse train starting at `start` (ideal time) and returns the ideal time after
+fn feed(est: &mut TempoEstimator, jitter: &mut Jitter, start: f64, bpm: f64, n_pulses: u32) -> f64 {
+    let period = period_us(bpm);
+    let mut ideal = start;
+
+    for _ in 0..n_pulses {
+        est.pulse((ideal as i64 + jitter.next()) as u64);
+        ideal += period;
+    }
+
+    ideal
+}
+
+#[test]
+fn no_tempo_without_pulses() {
+    let mut est = TempoEstimator::new();
+    assert_eq!(est.bpm(), None);
+    assert!(!est.is_locked(0));
+
+    est.pulse(1_000_000);
+    assert_eq!(est.bpm(), None);
+}
+
+#[test]
+fn exact_pulses_give_exact_tempo() {
+    let mut est = TempoEstimator::new();
+    let mut jitter = Jitter::new(0, 0);
+
+    feed(&mut est, &mut jitter, 1_000_000.0, 120.0, 48);
+
+    let bpm = est.bpm().unwrap();
+    assert!((bpm - 120.0).abs() < 0.01, "bpm = {bpm}");
+}
+
+#[test]
+fn jittered_pulses_settle_on_stable_tempo() {
+    for (seed, bpm) in [(1, 90.0), (2, 120.0), (3, 174.0)] {
+        let mut est = TempoEstimator::new();
+        let mut jitter = Jitter::new(seed, 500);
+
+        let mut ideal = feed(&mut est, &mut jitter, 1_000_000.0, bpm, 4 * PPQN);
+
+        // after settling, the estimate should not wander around
+        for _ in 0..16 * PPQN {
+            ideal = feed(&mut est, &mut jitter, ideal, bpm, 1);
+
+            let estimate = est.bpm().unwrap() as f64;
+            assert!(
+                (estimate - bpm).abs() < 0.5,
+                "seed {seed}: {estimate} != {bpm}"
+            );
+        }
+    }
+}
+
+#[test]
+fn detects_tempo_change_quickly() {
+    let mut est = TempoEstimator::new();
+    let mut jitter = Jitter::new(4, 300);
+
+    let ideal = feed(&mut est, &mut jitter, 1_000_000.0, 120.0, 8 * PPQN);
+    feed(&mut est, &mut jitter, ideal, 140.0, PPQN / 2);
+
+    let bpm = est.bpm().unwrap();
+    assert!((bpm - 140.0).abs() < 1.5, "bpm = {bpm}");
+}
+
+#[test]
+fn phase_stays_locked_without_drift() {
+    let bpm = 128.0;
+    let period = period_us(bpm);
+
+    let mut est = TempoEstimator::new();
+    let mut jitter = Jitter::new(5, 400);
+
+    let mut ideal = feed(&mut est, &mut jitter, 1_000_000.0, bpm, 2 * PPQN);
+
+    // a few minutes of clock, the phase must stay close to the ideal grid
+    for _ in 0..(400 * PPQN) {
+        ideal = feed(&mut est, &mut jitter, ideal, bpm, 1);
+
+        // halfway in between the last and the next ideal pulse
+        let now = (ideal - period / 2.0) as u64;
+        let phase = est.phase(now);
+        assert!((phase - 0.5).abs() < 0.05, "phase = {phase}");
+    }
+}
+
+#[test]
+fn phase_never_passes_next_pulse() {
+    let mut est = TempoEstimator::new();
+    let mut jitter = Jitter::new(6, 0);
+
+    let ideal = feed(&mut est, &mut jitter, 1_000_000.0, 120.0, PPQN);
+
+    assert!(est.phase(ideal as u64 + 1_000_000) < 1.0);
+}
+
+#[test]
+fn loses_lock_when_clock_stops() {
+    let mut est = TempoEstimator::new();
+    let mut jitter = Jitter::new(7, 200);
+
+    let ideal = feed(&mut est, &mut jitter, 1_000_000.0, 120.0, PPQN);
+    assert!(est.is_locked(ideal as u64));
+
+    assert!(!est.is_locked(ideal as u64 + 500_000));
+
+    est.reset();
+    assert_eq!(est.bpm(), None);
+    assert!(!est.is_locked(ideal as u64));
+}
+
+#[test]
+fn gaps_do_not_change_the_tempo() {
+    let mut est = TempoEstimator::new();
+    let mut jitter = Jitter::new(8, 200);
+
+    let ideal = feed(&mut est, &mut jitter, 1_000_000.0, 120.0, 4 * PPQN);
+    let resumed = ideal + 2_000_000.0;
+    let mut ideal = feed(&mut est, &mut jitter, resumed, 120.0, 1);
+
+    // the phase restarts at the first pulse after the gap
+    assert!(est.phase(resumed as u64 + 1_000) < 0.2);
+
+    for i in 0..PPQN {
+        ideal = feed(&mut est, &mut jitter, ideal, 120.0, 1);
+
+        let bpm = est.bpm().unwrap();
+        assert!((bpm - 120.0).abs() < 1.0, "pulse {i}: bpm = {bpm}");
+    }
+}
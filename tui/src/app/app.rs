@@ -1,4 +1,5 @@
-use super::{connection::setup_midi_socket, keybindings, log::Logger, menu::*, tabs::*};
+use super::{cli::add_units, connection::{detect_devices, setup_midi_socket, Units}, keybindings, log::Logger, menu::*, tabs::*};
+use crate::device;
 
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
@@ -9,9 +10,9 @@ use std::{
     time::Duration,
 };
 use uncycle_core::prelude::*;
-use crate::app::menu::Setting;
+use crate::app::{menu::Setting, Connection, SessionFile, SmfFiles};
 
-const DEFAULT_BPM: f32 = 120.0;
+pub const DEFAULT_BPM: f32 = 120.0;
 
 #[derive(PartialEq, Clone)]
 pub enum AppTab {
@@ -27,17 +28,35 @@ pub struct App {
     pub log: Arc<Mutex<Logger>>,
     pub tab: AppTab,
     pub menu: PopupMenu,
+    /// index into the lanes of the selected device
+    pub selected_lane: usize,
+    pub smf_files: SmfFiles,
+    pub session_file: SessionFile,
+    pub connection: Connection,
+    /// filled once devices have been detected
+    pub units: Arc<Mutex<Units>>,
     should_quit: bool,
 }
 
 impl App {
-    pub fn new(settings: Vec<Setting>) -> Self {
+    pub fn new(
+        core: UncycleCore,
+        settings: Vec<Setting>,
+        smf_files: SmfFiles,
+        session_file: SessionFile,
+        connection: Connection,
+    ) -> Self {
         Self {
             keybindings: Keybindings::new(),
-            core: Arc::new(Mutex::new(UncycleCore::new(DEFAULT_BPM))),
+            core: Arc::new(Mutex::new(core)),
             log: Arc::new(Mutex::new(Logger::new())),
             tab: AppTab::Main,
             menu: PopupMenu::new(settings),
+            selected_lane: 0,
+            smf_files,
+            session_file,
+            connection,
+            units: Arc::new(Mutex::new(Units::default())),
             should_quit: false,
         }
     }
@@ -53,8 +72,21 @@ impl App {
                 Action::ToggleSequence => self.core.lock().unwrap().start_stop_sequence(),
                 Action::StartRecording => self.core.lock().unwrap().start_recording(),
                 Action::DeleteRecording => self.core.lock().unwrap().delete_recording(),
+                Action::UndoRecording => self.core.lock().unwrap().undo_recording(),
+                Action::RedoRecording => self.core.lock().unwrap().redo_recording(),
+                Action::QuantizeRecording => self.core.lock().unwrap().quantize_recording(),
+                Action::ExportRecording => self.export_recording(),
+                Action::ImportRecording => self.import_recording(),
+                Action::PrevLane => self.cycle_lanes(false),
+                Action::NextLane => self.cycle_lanes(true),
+                Action::ClearLane => self.with_selected_lane(UncycleCore::clear_lane),
+                Action::MuteLane => self.with_selected_lane(UncycleCore::toggle_mute_lane),
+                Action::SoloLane => self.with_selected_lane(UncycleCore::toggle_solo_lane),
+                Action::RerecordLane => self.with_selected_lane(UncycleCore::rerecord_lane),
                 Action::HalfLoopLen => self.core.lock().unwrap().half_loop_len(),
                 Action::DoubleLoopLen => self.core.lock().unwrap().double_loop_len(),
+                Action::IncreaseLoopLen => self.core.lock().unwrap().increase_loop_len(),
+                Action::DecreaseLoopLen => self.core.lock().unwrap().decrease_loop_len(),
                 Action::ToggleMenu => self.toggle_tab_menu(PopupTab::Menu),
                 Action::ToggleHelp => self.toggle_tab_menu(PopupTab::Help),
                 Action::MenuMoveDown => match self.menu.settings.focus {
@@ -68,7 +100,10 @@ impl App {
                 Action::MenuEnter => self.menu.settings.switch_focus(),
                 Action::MenuExit => {
                     self.menu.settings.switch_focus();
-                    self.menu.settings.apply_current_setting(&mut self.core.lock().unwrap());
+
+                    if let Some(setting) = self.menu.settings.get_current_setting().cloned() {
+                        self.apply_setting(&setting);
+                    }
                 },
             }
         } else {
@@ -98,6 +133,133 @@ impl App {
         }
     }
 
+    fn device_lanes(&self) -> Vec<(u8, String)> {
+        match &self.core.lock().unwrap().device {
+            Some(device) => device::lanes(device.params()),
+            None => Vec::new(),
+        }
+    }
+
+    fn cycle_lanes(&mut self, forward: bool) {
+        let n_lanes = self.device_lanes().len();
+
+        if n_lanes == 0 {
+            return;
+        }
+
+        self.selected_lane = if forward {
+            (self.selected_lane + 1) % n_lanes
+        } else {
+            (self.selected_lane + n_lanes - 1) % n_lanes
+        };
+    }
+
+    fn with_selected_lane(&mut self, f: fn(&mut UncycleCore, u8)) {
+        if let Some((cc_num, _)) = self.device_lanes().get(self.selected_lane) {
+            f(&mut self.core.lock().unwrap(), *cc_num);
+        }
+    }
+
+    fn export_recording(&mut self) {
+        let mut file = Vec::new();
+        self.core
+            .lock()
+            .unwrap()
+            .export_smf(self.smf_files.format, &mut |bytes| file.extend_from_slice(bytes));
+
+        let message = match std::fs::write(&self.smf_files.export_path, file) {
+            Ok(()) => format!("Exported loop to {}", self.smf_files.export_path.display()),
+            Err(e) => format!("Unable to export loop: {}", e),
+        };
+
+        self.log.lock().unwrap().log_misc(message);
+    }
+
+    fn import_recording(&mut self) {
+        let path = &self.smf_files.import_path;
+
+        let file = match std::fs::read(path) {
+            Ok(file) => file,
+            Err(e) => {
+                self.log.lock().unwrap().log_misc(format!("Unable to import loop: {}", e));
+                return;
+            }
+        };
+
+        let result = self.core.lock().unwrap().import_smf(&file);
+        let mut log = self.log.lock().unwrap();
+
+        match result {
+            Ok(report) => {
+                log.log_misc(format!(
+                    "Imported {} events over {} steps from {}",
+                    report.n_events,
+                    report.loop_steps,
+                    path.display()
+                ));
+
+                if report.n_unsupported > 0 {
+                    log.log_misc(format!("Skipped {} unsupported events", report.n_unsupported));
+                }
+
+                if report.n_dropped > 0 {
+                    log.log_misc(format!("Dropped {} events, the recording is full", report.n_dropped));
+                }
+
+                if report.truncated {
+                    log.log_misc("File is longer than the longest loop and has been cut off".to_string());
+                }
+            }
+            Err(e) => log.log_misc(format!("Unable to import loop: {}", e)),
+        }
+    }
+
+    fn apply_setting(&mut self, setting: &Setting) {
+        match setting.apply {
+            Apply::Core { apply_fn, .. } => {
+                let mut core = self.core.lock().unwrap();
+                apply_fn(&mut core, setting);
+
+                // other settings may depend on this one, e.g. the channel on the device
+                self.menu.settings.read_settings(&core);
+            }
+            Apply::App(apply_fn) => apply_fn(self, setting),
+        }
+    }
+
+    pub fn save_session(&mut self) {
+        let mut file = Vec::new();
+        self.core
+            .lock()
+            .unwrap()
+            .save_session(&mut |bytes| file.extend_from_slice(bytes));
+
+        let message = match std::fs::write(&self.session_file.path, file) {
+            Ok(()) => format!("Saved session to {}", self.session_file.path.display()),
+            Err(e) => format!("Unable to save session: {}", e),
+        };
+
+        self.log.lock().unwrap().log_misc(message);
+    }
+
+    pub fn load_session(&mut self) {
+        let path = &self.session_file.path;
+
+        let result = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| self.core.lock().unwrap().load_session(&file).map_err(|e| e.to_string()));
+
+        let message = match result {
+            Ok(()) => {
+                self.menu.settings.read_settings(&self.core.lock().unwrap());
+                format!("Loaded session from {}", path.display())
+            }
+            Err(e) => format!("Unable to load session: {}", e),
+        };
+
+        self.log.lock().unwrap().log_misc(message);
+    }
+
     fn toggle_tab_menu(&mut self, tab: PopupTab) {
         if !(self.menu.is_active && self.menu.tab != tab) {
             self.menu.is_active ^= true;
@@ -108,13 +270,53 @@ impl App {
 }
 
 pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
-    setup_midi_socket(app.core.clone(), app.log.clone());
-
     // apply settings that have been chose from CLI
     for setting in &app.menu.settings.settings {
-        (setting.apply_fn)(&mut app.core.lock().unwrap(), &setting);
+        if let Apply::Core { apply_fn, .. } = setting.apply {
+            apply_fn(&mut app.core.lock().unwrap(), setting);
+        }
+    }
+
+    app.core.lock().unwrap().set_clock_thru(app.connection.clock_port.is_some());
+
+    // a session given on the CLI takes over from there, unless it doesn't exist yet
+    let session_loaded = app.session_file.persistent && app.session_file.path.exists();
+
+    if session_loaded {
+        app.load_session();
+    }
+
+    // detection takes a moment, so the app is shown first
+    terminal.draw(|f| ui(f, app))?;
+    let detected = detect_devices(&app.core, &app.log);
+
+    {
+        let mut core = app.core.lock().unwrap();
+
+        if let Some(device) = core.device.as_ref().filter(|device| !detected.iter().any(|d| device.id_to_str() == d.id.as_str())) {
+            let device_picked = app.connection.device_picked || session_loaded;
+            let ports_picked = app.connection.in_port.is_some() || app.connection.out_port.is_some();
+
+            // devices without an identity can't be detected, so they are kept as well
+            if device_picked || ports_picked || device.identity().is_none() {
+                app.log.lock().unwrap().log_misc(format!("{} {} has not been detected, looking for its port", device.manufacturer_to_str(), device.name_to_str()));
+            } else if let Some(device) = detected.first().and_then(|first| core.find_device(&first.id)) {
+                app.log.lock().unwrap().log_misc(format!("Selected {} {}", device.manufacturer_to_str(), device.name_to_str()));
+                core.set_device(device);
+            }
+        }
+
+        let mut units = app.units.lock().unwrap();
+        units.detected = detected;
+
+        add_units(&units, &core, &mut app.menu.settings.settings);
+
+        // the menu shows what has actually been applied, e.g. the fixed channel of a device
+        app.menu.settings.read_settings(&core);
     }
 
+    setup_midi_socket(app.core.clone(), app.log.clone(), app.units.clone(), app.connection.clone());
+
     while !app.should_quit {
         terminal.draw(|f| ui(f, app))?;
 
@@ -128,6 +330,10 @@ pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<
         }
     }
 
+    if app.session_file.persistent {
+        app.save_session();
+    }
+
     Ok(())
 }
 
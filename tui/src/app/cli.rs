@@ -1,23 +1,73 @@
-use std::{fmt::Debug, process::exit};
+use std::{fmt::Debug, path::PathBuf, process::exit};
 
-use clap::{Parser, ValueEnum};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
 
-use crate::app::{menu::Setting};
-use uncycle_core::{devices::{SupportedDevice, TR8}, prelude::{DeviceInterface, UncycleCore}};
+use crate::{app::{connection::{DetectedDevice, Units}, menu::{Apply, Setting}, App}, device::definition};
+use midir::{MidiIO, MidiInput, MidiOutput};
+use uncycle_core::prelude::{self, ClockSource, DeviceInterface, SmfFormat, SupportedDevice, UncycleCore};
 use strum::{EnumIter, IntoEnumIterator};
 
+const DEFAULT_SESSION_PATH: &str = "uncycle.session";
+const DEFAULT_DEVICE_DIR: &str = "devices";
+const DEFAULT_DEVICE: &str = "roland-tr8";
+
 #[derive(Debug, Parser)]
 #[command(version, long_about = None)]
 struct Cli {
     #[arg(short, long, value_enum, default_value_t = RecMode::OneShot)]
     rec_mode: RecMode,
 
-    #[arg(short, long, help = "Select desired device", default_value_t = SupportedDevice::TR8(TR8::default()))]
-    device: SupportedDevice,
+    #[arg(short, long, value_enum, help = "Add to or replace recorded CC when overdubbing", default_value_t = OverdubMode::Add)]
+    overdub_mode: OverdubMode,
+
+    #[arg(short, long, value_enum, help = "Snap recorded CC to a grid", default_value_t = Quantize::Off)]
+    quantize: Quantize,
+
+    #[arg(long, value_enum, help = "Part of the recording that is kept when the loop gets shorter", default_value_t = ShrinkMode::KeepStart)]
+    shrink_mode: ShrinkMode,
+
+    #[arg(long, value_enum, help = "Record 14 bit CC and (N)RPN as single parameter changes", default_value_t = HighRes::Off)]
+    high_res: HighRes,
+
+    #[arg(short, long, help = "Select desired device by its id, a detected one otherwise [default: roland-tr8]")]
+    device: Option<String>,
+
+    #[arg(long, help = "Directory with device definitions (*.toml), \"devices\" if it exists")]
+    device_dir: Option<PathBuf>,
+
+    #[arg(long, help = "MIDI channel of the generic device", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=16))]
+    channel: u8,
+
+    #[arg(long, help = "Connect to the MIDI input port containing this name instead of the one of the device")]
+    in_port: Option<String>,
+
+    #[arg(long, help = "Connect to the MIDI output port containing this name instead of the one of the device")]
+    out_port: Option<String>,
+
+    #[arg(short, long, value_enum, help = "Select MIDI clock source", default_value_t = ClockMode::Internal)]
+    clock: ClockMode,
+
+    #[arg(long, help = "With --clock external, follow the MIDI input port containing this name and forward its clock to the device")]
+    clock_port: Option<String>,
+
+    #[arg(long, help = "File the loop is exported to as Standard MIDI File", default_value = "uncycle.mid")]
+    export: PathBuf,
+
+    #[arg(long, help = "Standard MIDI File the loop is imported from", default_value = "uncycle.mid")]
+    import: PathBuf,
+
+    #[arg(long, value_enum, help = "Standard MIDI File type of the export", default_value_t = SmfType::Type1)]
+    smf_type: SmfType,
+
+    #[arg(long, help = "Session file that is loaded at startup and saved on quit")]
+    session: Option<PathBuf>,
 
     #[arg(required = false, short, long, help = "Lists all supported devices")]
     list_devices: bool,
 
+    #[arg(required = false, long, help = "Lists all MIDI ports")]
+    list_ports: bool,
+
 }
 
 #[derive(Debug, Clone, ValueEnum, EnumIter, PartialEq, Eq)]
@@ -25,35 +75,190 @@ pub enum RecMode {
     #[value(alias("one-shot"))]
     OneShot,
 
-    #[value(alias("continous"))]
+    #[value(alias("continous"), alias("continuous"))]
     Continous,
 }
 
-pub fn parse_cli() -> Vec<Setting> {
+#[derive(Debug, Clone, ValueEnum, EnumIter, PartialEq, Eq)]
+pub enum OverdubMode {
+    Add,
+    Replace,
+}
+
+#[derive(Debug, Clone, ValueEnum, EnumIter, PartialEq, Eq)]
+pub enum Quantize {
+    Off,
+    #[value(alias("1/16"))]
+    Sixteenth,
+    #[value(alias("1/32"))]
+    ThirtySecond,
+    #[value(alias("1/96"))]
+    Tick,
+}
+
+impl Quantize {
+    fn label(&self) -> &'static str {
+        match self {
+            Quantize::Off => "Off",
+            Quantize::Sixteenth => "1/16",
+            Quantize::ThirtySecond => "1/32",
+            Quantize::Tick => "1/96 (Tick)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, EnumIter, PartialEq, Eq)]
+pub enum ShrinkMode {
+    #[value(alias("first"))]
+    KeepStart,
+    #[value(alias("second"))]
+    KeepEnd,
+}
+
+#[derive(Debug, Clone, ValueEnum, EnumIter, PartialEq, Eq)]
+pub enum HighRes {
+    Off,
+    On,
+}
+
+#[derive(Debug, Clone, ValueEnum, EnumIter, PartialEq, Eq)]
+pub enum ClockMode {
+    Internal,
+    External,
+}
+
+#[derive(Debug, Clone, ValueEnum, PartialEq, Eq)]
+pub enum SmfType {
+    #[value(alias("0"))]
+    Type0,
+    #[value(alias("1"))]
+    Type1,
+}
+
+/// Where the loop is exported to and imported from
+#[derive(Debug, Clone)]
+pub struct SmfFiles {
+    pub export_path: PathBuf,
+    pub import_path: PathBuf,
+    pub format: SmfFormat,
+}
+
+/// Where the session is saved to and loaded from
+#[derive(Debug, Clone)]
+pub struct SessionFile {
+    pub path: PathBuf,
+    /// given on the command line, so it is loaded at startup and saved on quit
+    pub persistent: bool,
+}
+
+/// Device and MIDI ports picked by the user, which take precedence over detected ones
+#[derive(Debug, Clone, Default)]
+pub struct Connection {
+    /// given with --device, so it isn't replaced by a detected device
+    pub device_picked: bool,
+    pub in_port: Option<String>,
+    pub out_port: Option<String>,
+    /// clock master other than the device
+    pub clock_port: Option<String>,
+}
+
+/// Reads the CLI arguments and makes the device definitions they point to available to `core`
+pub fn parse_cli(core: &mut UncycleCore) -> (Vec<Setting>, SmfFiles, SessionFile, Connection) {
     let args = Cli::parse();
 
-    if args.list_devices == true {
-        show_supported_devices() // exits program
+    load_device_definitions(&args, core);
+
+    if args.list_devices {
+        show_supported_devices(core) // exits program
     }  
 
+    if args.list_ports {
+        show_ports() // exits program
+    }
+
     let mut settings = Vec::new();
 
     parse_mode(&args, &mut settings);
-    parse_device(&args, &mut settings);
+    parse_overdub_mode(&args, &mut settings);
+    parse_quantize(&args, &mut settings);
+    parse_shrink_mode(&args, &mut settings);
+    parse_high_res(&args, &mut settings);
+    parse_device(&args, core, &mut settings);
+    parse_channel(&args, &mut settings);
+    parse_clock(&args, &mut settings);
+
+    let session_file = SessionFile {
+        path: args.session.clone().unwrap_or(PathBuf::from(DEFAULT_SESSION_PATH)),
+        persistent: args.session.is_some(),
+    };
+
+    add_session(&session_file, &mut settings);
+
+    let smf_files = SmfFiles {
+        export_path: args.export.clone(),
+        import_path: args.import.clone(),
+        format: match args.smf_type {
+            SmfType::Type0 => SmfFormat::SingleTrack,
+            SmfType::Type1 => SmfFormat::MultiTrack,
+        },
+    };
 
-    settings
+    let connection = Connection {
+        device_picked: args.device.is_some(),
+        in_port: args.in_port.clone(),
+        out_port: args.out_port.clone(),
+        clock_port: args.clock_port.clone(),
+    };
+
+    (settings, smf_files, session_file, connection)
+}
+
+fn load_device_definitions(args: &Cli, core: &mut UncycleCore) {
+    let dir = args.device_dir.clone().unwrap_or(PathBuf::from(DEFAULT_DEVICE_DIR));
+
+    // the default directory is optional
+    if args.device_dir.is_none() && !dir.is_dir() {
+        return;
+    }
+
+    if let Err(e) = definition::load_dir(&dir, core) {
+        Cli::command().error(ErrorKind::Io, format!("{:#}", e)).exit();
+    }
 }
 
-fn show_supported_devices() -> ! {
+fn show_supported_devices(core: &UncycleCore) -> ! {
     println!("Supported devices:");
 
-    for device in SupportedDevice::iter() {
+    for device in core.available_devices() {
         println!("  {}", device.id_to_str());
     }
 
     exit(0);
 }
 
+fn show_ports() -> ! {
+    match (MidiInput::new("uncycle_list_input"), MidiOutput::new("uncycle_list_output")) {
+        (Ok(app_input), Ok(app_output)) => {
+            println!("MIDI input ports:");
+            print_port_names(&app_input);
+
+            println!("MIDI output ports:");
+            print_port_names(&app_output);
+
+            exit(0);
+        }
+        _ => Cli::command().error(ErrorKind::Io, "unable to access MIDI ports").exit(),
+    }
+}
+
+fn print_port_names<M: MidiIO>(midi_io: &M) {
+    for port in midi_io.ports() {
+        if let Ok(name) = midi_io.port_name(&port) {
+            println!("  {}", name);
+        }
+    }
+}
+
 fn parse_mode(args: &Cli, settings_vec: &mut Vec<Setting>) {
     let mut index = 0;
     let mut options = Vec::new();
@@ -70,43 +275,349 @@ fn parse_mode(args: &Cli, settings_vec: &mut Vec<Setting>) {
         Setting {
             name: "Recording".to_string(), 
             description: "Select Recording Mode".to_string(), 
-            options: options, 
+            options, 
             selected_option: index,
-            apply_fn: nop,
+            apply: Apply::Core { apply_fn: change_rec_mode, read_fn: read_rec_mode },
         }
     );
 }
 
-fn parse_device(args: &Cli, settings_vec: &mut Vec<Setting>) {
+fn parse_overdub_mode(args: &Cli, settings_vec: &mut Vec<Setting>) {
     let mut index = 0;
     let mut options = Vec::new();
 
-    for (i, device) in SupportedDevice::iter().enumerate() {
-        options.push(device.manufacturer_to_str().to_string() + " " + &device.name_to_str());
+    for (i, mode) in OverdubMode::iter().enumerate() {
+        options.push(format!("{:?}", mode));
+
+        if mode == args.overdub_mode {
+            index = i;
+        }
+    }
+
+    settings_vec.push(
+        Setting {
+            name: "Overdub".to_string(), 
+            description: "Add to the recording or replace touched CC lanes (punch-in)".to_string(), 
+            options, 
+            selected_option: index,
+            apply: Apply::Core { apply_fn: change_overdub_mode, read_fn: read_overdub_mode },
+        }
+    );
+}
 
-        if device.id_to_str().eq(&args.device.id_to_str()) {
+fn parse_quantize(args: &Cli, settings_vec: &mut Vec<Setting>) {
+    let mut index = 0;
+    let mut options = Vec::new();
+
+    for (i, quantize) in Quantize::iter().enumerate() {
+        options.push(quantize.label().to_string());
+
+        if quantize == args.quantize {
             index = i;
         }
     }
 
+    settings_vec.push(
+        Setting {
+            name: "Quantize".to_string(), 
+            description: "Snap recorded CC to a grid of MIDI clock pulses".to_string(), 
+            options, 
+            selected_option: index,
+            apply: Apply::Core { apply_fn: change_quantize, read_fn: read_quantize },
+        }
+    );
+}
+
+fn parse_shrink_mode(args: &Cli, settings_vec: &mut Vec<Setting>) {
+    let mut index = 0;
+    let mut options = Vec::new();
+
+    for (i, mode) in ShrinkMode::iter().enumerate() {
+        options.push(format!("{:?}", mode));
+
+        if mode == args.shrink_mode {
+            index = i;
+        }
+    }
+
+    settings_vec.push(
+        Setting {
+            name: "Shrink".to_string(), 
+            description: "Keep the start or the end of the recording when the loop gets shorter".to_string(), 
+            options, 
+            selected_option: index,
+            apply: Apply::Core { apply_fn: change_shrink_mode, read_fn: read_shrink_mode },
+        }
+    );
+}
+
+fn parse_high_res(args: &Cli, settings_vec: &mut Vec<Setting>) {
+    let mut index = 0;
+    let mut options = Vec::new();
+
+    for (i, high_res) in HighRes::iter().enumerate() {
+        options.push(format!("{:?}", high_res));
+
+        if high_res == args.high_res {
+            index = i;
+        }
+    }
+
+    settings_vec.push(
+        Setting {
+            name: "High-Res".to_string(), 
+            description: "Record 14 bit CC and (N)RPN as one parameter, may clash with devices using CC 32-63 on their own".to_string(), 
+            options, 
+            selected_option: index,
+            apply: Apply::Core { apply_fn: change_high_res, read_fn: read_high_res },
+        }
+    );
+}
+
+fn parse_device(args: &Cli, core: &UncycleCore, settings_vec: &mut Vec<Setting>) {
+    let id = args.device.as_deref().unwrap_or(DEFAULT_DEVICE);
+    let mut index = None;
+    let mut options = Vec::new();
+
+    for (i, device) in core.available_devices().enumerate() {
+        options.push(device.manufacturer_to_str().to_string() + " " + &device.name_to_str());
+
+        if device.id_to_str().eq(id) {
+            index = Some(i);
+        }
+    }
+
+    let Some(index) = index else {
+        Cli::command()
+            .error(ErrorKind::InvalidValue, format!("unknown device '{}', see --list-devices", id))
+            .exit();
+    };
+
     settings_vec.push(
         Setting {
             name: "Device".to_string(), 
             description: "Select Supported Device".to_string(), 
-            options: options, 
+            options, 
             selected_option: index,
-            apply_fn: change_device,
+            apply: Apply::Core { apply_fn: change_device, read_fn: read_device },
+        }
+    );
+}
+
+fn parse_channel(args: &Cli, settings_vec: &mut Vec<Setting>) {
+    let options = (1..=16).map(|channel: u8| channel.to_string()).collect();
+
+    settings_vec.push(
+        Setting {
+            name: "Channel".to_string(), 
+            description: "MIDI channel of the generic device".to_string(), 
+            options, 
+            selected_option: (args.channel - 1) as usize,
+            apply: Apply::Core { apply_fn: change_channel, read_fn: read_channel },
+        }
+    );
+}
+
+fn parse_clock(args: &Cli, settings_vec: &mut Vec<Setting>) {
+    let mut index = 0;
+    let mut options = Vec::new();
+
+    for (i, mode) in ClockMode::iter().enumerate() {
+        options.push(format!("{:?}", mode));
+
+        if mode == args.clock {
+            index = i;
+        }
+    }
+
+    settings_vec.push(
+        Setting {
+            name: "Clock".to_string(), 
+            description: "Send own MIDI clock or follow an external one".to_string(), 
+            options, 
+            selected_option: index,
+            apply: Apply::Core { apply_fn: change_clock, read_fn: read_clock },
+        }
+    );
+}
+
+fn add_session(session_file: &SessionFile, settings_vec: &mut Vec<Setting>) {
+    settings_vec.push(
+        Setting {
+            name: "Session".to_string(), 
+            description: format!("Save or load BPM, settings and the loop with {}", session_file.path.display()),
+            options: vec!["Save".to_string(), "Load".to_string()], 
+            selected_option: 0,
+            apply: Apply::App(change_session),
+        }
+    );
+}
+
+/// Lists every detected unit, so one of several identical devices can be picked
+pub fn add_units(units: &Units, core: &UncycleCore, settings_vec: &mut Vec<Setting>) {
+    if units.detected.is_empty() {
+        return;
+    }
+
+    let id = core.device.as_ref().map(|device| device.id_to_str().to_string());
+
+    settings_vec.push(
+        Setting {
+            name: "Unit".to_string(), 
+            description: "Connect to one of the detected devices".to_string(), 
+            options: units.detected.iter().map(DetectedDevice::label).collect(), 
+            selected_option: units.detected.iter().position(|unit| Some(&unit.id) == id.as_ref()).unwrap_or(0),
+            apply: Apply::App(change_unit),
         }
     );
 }
 
-fn nop(_core: &mut UncycleCore, _setting: &Setting) {}
+fn change_rec_mode(core: &mut UncycleCore, setting: &Setting) {
+    if let Some(mode) = RecMode::iter().nth(setting.selected_option) {
+        match mode {
+            RecMode::OneShot => core.looper.set_rec_mode(prelude::RecMode::OneShot),
+            RecMode::Continous => core.looper.set_rec_mode(prelude::RecMode::Continuous),
+        }
+    }
+}
 
 fn change_device(core: &mut UncycleCore, setting: &Setting) {
-     for device in SupportedDevice::iter() {
-        // very ugly... but works
-        if setting.options[setting.selected_option].eq(&(device.manufacturer_to_str().to_string() + " " + &device.name_to_str())) {
+    // options are listed in the same order
+    let device = core.available_devices().nth(setting.selected_option);
+
+    if let Some(device) = device {
+        core.set_device(device);
+    }
+}
+
+fn change_channel(core: &mut UncycleCore, setting: &Setting) {
+    // other devices have a fixed channel
+    if let Some(SupportedDevice::Generic(device)) = &mut core.device {
+        device.set_channel(setting.selected_option as u8);
+    }
+}
+
+fn change_overdub_mode(core: &mut UncycleCore, setting: &Setting) {
+    if let Some(mode) = OverdubMode::iter().nth(setting.selected_option) {
+        match mode {
+            OverdubMode::Add => core.looper.set_overdub_mode(prelude::OverdubMode::Add),
+            OverdubMode::Replace => core.looper.set_overdub_mode(prelude::OverdubMode::Replace),
+        }
+    }
+}
+
+fn change_quantize(core: &mut UncycleCore, setting: &Setting) {
+    if let Some(quantize) = Quantize::iter().nth(setting.selected_option) {
+        match quantize {
+            Quantize::Off => core.looper.set_quantize(prelude::Quantize::Off),
+            Quantize::Sixteenth => core.looper.set_quantize(prelude::Quantize::Sixteenth),
+            Quantize::ThirtySecond => core.looper.set_quantize(prelude::Quantize::ThirtySecond),
+            Quantize::Tick => core.looper.set_quantize(prelude::Quantize::Tick),
+        }
+    }
+}
+
+fn change_shrink_mode(core: &mut UncycleCore, setting: &Setting) {
+    if let Some(mode) = ShrinkMode::iter().nth(setting.selected_option) {
+        match mode {
+            ShrinkMode::KeepStart => core.looper.set_shrink_mode(prelude::ShrinkMode::KeepStart),
+            ShrinkMode::KeepEnd => core.looper.set_shrink_mode(prelude::ShrinkMode::KeepEnd),
+        }
+    }
+}
+
+fn change_high_res(core: &mut UncycleCore, setting: &Setting) {
+    if let Some(high_res) = HighRes::iter().nth(setting.selected_option) {
+        core.set_high_res_params(high_res == HighRes::On);
+    }
+}
+
+fn change_clock(core: &mut UncycleCore, setting: &Setting) {
+    if let Some(mode) = ClockMode::iter().nth(setting.selected_option) {
+        match mode {
+            ClockMode::Internal => core.set_clock_source(ClockSource::Internal),
+            ClockMode::External => core.set_clock_source(ClockSource::External),
+        }
+    }
+}
+
+fn change_unit(app: &mut App, setting: &Setting) {
+    let Some(unit) = app.units.lock().unwrap().select(setting.selected_option) else {
+        return;
+    };
+
+    let mut core = app.core.lock().unwrap();
+
+    if core.device.as_ref().is_none_or(|device| device.id_to_str() != unit.id.as_str()) {
+        if let Some(device) = core.find_device(&unit.id) {
             core.set_device(device);
         }
     }
-}
\ No newline at end of file
+
+    app.menu.settings.read_settings(&core);
+    app.log.lock().unwrap().log_misc(format!("Selected {}", unit.label()));
+}
+
+fn change_session(app: &mut App, setting: &Setting) {
+    match setting.selected_option {
+        0 => app.save_session(),
+        _ => app.load_session(),
+    }
+}
+
+/// Position of `value` among the options of its setting
+fn index_of<T: IntoEnumIterator + PartialEq>(value: T) -> usize {
+    T::iter().position(|option| option == value).unwrap_or(0)
+}
+
+fn read_rec_mode(core: &UncycleCore) -> usize {
+    index_of(match core.looper.get_rec_mode() {
+        prelude::RecMode::OneShot => RecMode::OneShot,
+        prelude::RecMode::Continuous => RecMode::Continous,
+    })
+}
+
+fn read_device(core: &UncycleCore) -> usize {
+    core.device
+        .as_ref()
+        .and_then(|selected| core.available_devices().position(|device| device.id_to_str() == selected.id_to_str()))
+        .unwrap_or(0)
+}
+
+fn read_channel(core: &UncycleCore) -> usize {
+    core.device.as_ref().map_or(0, |device| device.tx_channel() as usize)
+}
+
+fn read_overdub_mode(core: &UncycleCore) -> usize {
+    index_of(match core.looper.get_overdub_mode() {
+        prelude::OverdubMode::Add => OverdubMode::Add,
+        prelude::OverdubMode::Replace => OverdubMode::Replace,
+    })
+}
+
+fn read_quantize(core: &UncycleCore) -> usize {
+    index_of(match core.looper.get_quantize() {
+        prelude::Quantize::Off => Quantize::Off,
+        prelude::Quantize::Sixteenth => Quantize::Sixteenth,
+        prelude::Quantize::ThirtySecond => Quantize::ThirtySecond,
+        prelude::Quantize::Tick => Quantize::Tick,
+    })
+}
+
+fn read_shrink_mode(core: &UncycleCore) -> usize {
+    index_of(match core.looper.get_shrink_mode() {
+        prelude::ShrinkMode::KeepStart => ShrinkMode::KeepStart,
+        prelude::ShrinkMode::KeepEnd => ShrinkMode::KeepEnd,
+    })
+}
+
+fn read_high_res(core: &UncycleCore) -> usize {
+    index_of(if core.get_high_res_params() { HighRes::On } else { HighRes::Off })
+}
+
+fn read_clock(core: &UncycleCore) -> usize {
+    index_of(match core.get_clock_source() {
+        ClockSource::Internal => ClockMode::Internal,
+        ClockSource::External => ClockMode::External,
+    })
+}
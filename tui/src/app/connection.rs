@@ -4,32 +4,232 @@ use std::{
     time::{Duration, Instant},
 };
 
-use midir::{MidiIO, MidiInput, MidiOutput, MidiOutputConnection};
+use midir::{MidiIO, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
 use uncycle_core::prelude::*;
 
-use super::log::Logger;
+use super::{log::Logger, Connection};
 
-pub fn setup_midi_socket(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>) {
+/// How long every output port gets to answer an Identity Request
+const IDENTITY_REPLY_TIMEOUT: Duration = Duration::from_millis(300);
+
+pub fn setup_midi_socket(
+    core: Arc<Mutex<UncycleCore>>,
+    log: Arc<Mutex<Logger>>,
+    units: Arc<Mutex<Units>>,
+    connection: Connection,
+) {
     let core_arc_clone = Arc::clone(&core);
     let log_arc_clone = Arc::clone(&log);
+    let units_arc_clone = Arc::clone(&units);
 
     let now = Instant::now();
 
+    if let Some(clock_port) = connection.clock_port {
+        let core = Arc::clone(&core);
+        let log = Arc::clone(&log);
+
+        thread::spawn(move || {
+            midi_clock_thread(core, log, clock_port, now);
+        });
+    }
+
     thread::spawn(move || {
-        midi_input_thread(core, log, now);
+        midi_input_thread(core, log, units, connection.in_port, now);
     });
 
     thread::spawn(move || {
-        midi_output_thread(core_arc_clone, log_arc_clone, now);
+        midi_output_thread(
+            core_arc_clone,
+            log_arc_clone,
+            units_arc_clone,
+            connection.out_port,
+            now,
+        );
     });
 }
 
+/////////////////////////////////////////////////////////////////////
+// DEVICE DETECTION
+/////////////////////////////////////////////////////////////////////
+
+/// A device that answered an Identity Request, with the ports it has been found on
+#[derive(Clone, Debug)]
+pub struct DetectedDevice {
+    pub id: String,
+    pub name: String,
+    /// tells identical units apart
+    pub device_id: u8,
+    pub in_port: String,
+    pub out_port: String,
+}
+
+impl DetectedDevice {
+    pub fn label(&self) -> String {
+        format!(
+            "{} (ID 0x{:02X}) on {}",
+            self.name, self.device_id, self.in_port
+        )
+    }
+}
+
+/// Detected devices and the unit picked among them
+#[derive(Debug, Default)]
+pub struct Units {
+    pub detected: Vec<DetectedDevice>,
+    /// index into `detected`
+    selected: Option<usize>,
+}
+
+impl Units {
+    /// Picks the `i`th detected unit
+    pub fn select(&mut self, i: usize) -> Option<DetectedDevice> {
+        let unit = self.detected.get(i)?.clone();
+        self.selected = Some(i);
+
+        Some(unit)
+    }
+
+    /// The picked unit if it is a device with `id`, its first unit otherwise
+    fn unit_of(&self, id: &str) -> Option<&DetectedDevice> {
+        self.selected
+            .and_then(|i| self.detected.get(i))
+            .filter(|unit| unit.id == id)
+            .or_else(|| self.detected.iter().find(|unit| unit.id == id))
+    }
+}
+
+type IdentityReplies = Arc<Mutex<Vec<(String, Identity)>>>;
+
+/// Sends an Identity Request on one output port after another and listens for replies on every input port
+///
+/// Probing the ports one at a time pairs the in and out port of each unit, even if several identical ones are connected.
+pub fn detect_devices(
+    core: &Arc<Mutex<UncycleCore>>,
+    log: &Arc<Mutex<Logger>>,
+) -> Vec<DetectedDevice> {
+    let (n_in_ports, n_out_ports) = match (
+        MidiInput::new("uncycle_detect_input"),
+        MidiOutput::new("uncycle_detect_output"),
+    ) {
+        (Ok(app_input), Ok(app_output)) => (app_input.port_count(), app_output.port_count()),
+        _ => {
+            log.lock()
+                .unwrap()
+                .log_misc("Unable to detect devices".to_string());
+            return Vec::new();
+        }
+    };
+
+    let replies = IdentityReplies::default();
+
+    // connections are closed once detection is done
+    let _in_conns = (0..n_in_ports)
+        .filter_map(|i| listen_for_identity(i, &replies))
+        .collect::<Vec<_>>();
+
+    let mut request = vec![MIDI_SYSEX];
+    request.extend_from_slice(&IDENTITY_REQUEST_PAYLOAD);
+    request.push(MIDI_SYSEX_END);
+
+    let mut detected = Vec::new();
+
+    for i in 0..n_out_ports {
+        let Ok(app_output) = MidiOutput::new("uncycle_detect_output") else {
+            break;
+        };
+
+        let Some((port, out_port)) = nth_port(&app_output, i) else {
+            continue;
+        };
+
+        let Ok(mut conn) = app_output.connect(&port, "uncycle-detect-out") else {
+            continue;
+        };
+
+        replies.lock().unwrap().clear();
+
+        if conn.send(&request).is_err() {
+            continue;
+        }
+
+        thread::sleep(IDENTITY_REPLY_TIMEOUT);
+
+        for (in_port, identity) in replies.lock().unwrap().drain(..) {
+            let device = core.lock().unwrap().identify(&identity);
+
+            let Some(device) = device else {
+                log.lock().unwrap().log_misc(format!(
+                    "Unknown device on {}: {:?} family 0x{:04X} member 0x{:04X}",
+                    in_port, identity.manufacturer, identity.family, identity.member
+                ));
+                continue;
+            };
+
+            log.lock().unwrap().log_misc(format!(
+                "Detected {} {} (ID 0x{:02X}) on {} / {}",
+                device.manufacturer_to_str(),
+                device.name_to_str(),
+                identity.device_id,
+                in_port,
+                out_port
+            ));
+
+            detected.push(DetectedDevice {
+                id: device.id_to_str().to_string(),
+                name: format!("{} {}", device.manufacturer_to_str(), device.name_to_str()),
+                device_id: identity.device_id,
+                in_port,
+                out_port: out_port.clone(),
+            });
+        }
+    }
+
+    detected
+}
+
+/// Collects Identity Replies arriving on the `i`th input port
+fn listen_for_identity(
+    i: usize,
+    replies: &IdentityReplies,
+) -> Option<MidiInputConnection<(MidiParser, SysEx)>> {
+    let app_input = MidiInput::new("uncycle_detect_input").ok()?;
+    let (port, in_port) = nth_port(&app_input, i)?;
+    let replies = Arc::clone(replies);
+
+    app_input
+        .connect(
+            &port,
+            "uncycle-detect-in",
+            move |_timestamp, message, (parser, sysex)| {
+                for byte in message {
+                    for event in parser.push(*byte) {
+                        if !sysex.push(&event) {
+                            continue;
+                        }
+
+                        if let Some(identity) = Identity::from_reply(sysex.payload()) {
+                            replies.lock().unwrap().push((in_port.clone(), identity));
+                        }
+                    }
+                }
+            },
+            (MidiParser::new(), SysEx::new()),
+        )
+        .ok()
+}
+
 /////////////////////////////////////////////////////////////////////
 // MIDI INPUT
 /////////////////////////////////////////////////////////////////////
 
 /// Tries to reconnect to a specific input port every 1 second, based on chosen device
-pub fn midi_input_thread(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>, now: Instant) {
+pub fn midi_input_thread(
+    core: Arc<Mutex<UncycleCore>>,
+    log: Arc<Mutex<Logger>>,
+    units: Arc<Mutex<Units>>,
+    picked_port: Option<String>,
+    now: Instant,
+) {
     loop {
         let app_input = match midir::MidiInput::new("uncycle_midi_input") {
             Ok(app_input) => app_input,
@@ -42,7 +242,7 @@ pub fn midi_input_thread(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>,
         };
 
         if core.lock().unwrap().device.is_some() {
-            autoconnect_input(app_input, &core, now, &log);
+            autoconnect_input(app_input, &core, &units, picked_port.as_deref(), now, &log);
         }
 
         // no need to keep track of time, if no device is actually connected
@@ -53,11 +253,17 @@ pub fn midi_input_thread(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>,
 fn autoconnect_input(
     app_input: MidiInput,
     core: &Arc<Mutex<UncycleCore>>,
+    units: &Arc<Mutex<Units>>,
+    picked_port: Option<&str>,
     now: Instant,
     log: &Arc<Mutex<Logger>>,
 ) {
-    if let Ok(device_in_port) = get_device_port(&app_input, &core, &log) {
-        if let Ok(port_name) = get_port_name(&app_input, &device_in_port, &log) {
+    let detected_port = get_detected_port(core, units, |device| &device.in_port);
+
+    if let Ok(device_in_port) =
+        get_device_port(&app_input, core, picked_port, detected_port.as_deref(), log)
+    {
+        if let Ok(port_name) = get_port_name(&app_input, &device_in_port, log) {
             let log_rx_callback = log.clone();
             let core_rx_callback = core.clone();
 
@@ -76,7 +282,7 @@ fn autoconnect_input(
                 }
 
                 Ok(_conn) => {
-                    log_in_port(&log, port_name);
+                    log_in_port(log, port_name);
                     loop {
                         // higher precision time keeping
                         thread::sleep(Duration::from_millis(16));
@@ -84,6 +290,12 @@ fn autoconnect_input(
                         if core.lock().unwrap().kill_rx_conn {
                             continue;
                         }
+
+                        // reconnects to another unit
+                        if get_detected_port(core, units, |device| &device.in_port) != detected_port
+                        {
+                            break;
+                        }
                     }
                 }
             }
@@ -100,35 +312,158 @@ fn input_callback(
     // first handle midi logic
     let elapsed = now.elapsed().as_micros() as u64;
 
-    core.lock().unwrap().midi_rx_callback(message);
+    let mut received_sysex = Vec::new();
+
+    {
+        let mut locked = core.lock().unwrap();
+        locked.update_time(elapsed);
+        locked.midi_rx_callback(message);
+
+        while let Some(sysex) = locked.pop_received_sysex() {
+            received_sysex.push(sysex);
+        }
+    }
 
     // then handle logging
-    if let Some(msg) = parse_midi_message(message) {
-        let status = message[0];
-        let data1 = message[1];
-        let data2 = message[2];
-
-        match msg {
-            MIDI_NOTE_ON => log
-                .lock()
-                .unwrap()
-                .log_incoming_note(format!("NOTE ON:  {:02} {:02}", data1, data2)),
+    for sysex in received_sysex {
+        log.lock()
+            .unwrap()
+            .log_misc(format!("Received: {} ({} bytes)", sysex, sysex.n_bytes()));
+    }
 
-            MIDI_NOTE_OFF => {}
+    // SysEx, possibly split into several chunks, has been logged once it was complete
+    let Some(msg) = MidiMessage::from_bytes(message) else {
+        return;
+    };
 
-            MIDI_CONTORL_CHANGE => log.lock().unwrap().log_incoming_cc(format!(
-                "[{} ms {:3} ns] {} {}",
-                elapsed / 1000,
-                elapsed % 1000,
-                data1,
-                data2,
-            )),
+    match msg {
+        MidiMessage::Start | MidiMessage::Continue | MidiMessage::Stop => log
+            .lock()
+            .unwrap()
+            .log_misc(format!("Received: 0x{:02X} (MIDI {})", msg.status(), msg)),
+
+        // far too many to be logged
+        MidiMessage::TimingClock | MidiMessage::ActiveSensing => {}
+
+        MidiMessage::NoteOn {
+            channel,
+            note,
+            velocity,
+        } if !msg.is_note_off() => log.lock().unwrap().log_incoming_note(format!(
+            "NOTE ON:  {:02} {:02} (Ch {})",
+            note,
+            velocity,
+            channel + 1
+        )),
+
+        MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. } => {}
+
+        MidiMessage::ControlChange {
+            channel,
+            control,
+            value,
+        } => log.lock().unwrap().log_incoming_cc(format!(
+            "[{} ms {:3} ns] {} {} (Ch {})",
+            elapsed / 1000,
+            elapsed % 1000,
+            control,
+            value,
+            channel + 1,
+        )),
+
+        _ => log.lock().unwrap().log_misc(format!("MIDI: {}", msg)),
+    }
+}
 
-            _ => log
-                .lock()
-                .unwrap()
-                .log_misc(format!("MIDI: {:02X} {:02X} {:02X}", status, data1, data2)),
+/// Tries to reconnect to the port of the clock master every 1 second
+fn midi_clock_thread(
+    core: Arc<Mutex<UncycleCore>>,
+    log: Arc<Mutex<Logger>>,
+    clock_port: String,
+    now: Instant,
+) {
+    loop {
+        let app_input = match midir::MidiInput::new("uncycle_clock_input") {
+            Ok(app_input) => app_input,
+            Err(e) => {
+                log.lock()
+                    .unwrap()
+                    .log_misc(format!("Failed to create MIDI clock input: {}", e));
+                return;
+            }
+        };
+
+        // the input is used up by its connection, so another one watches the ports
+        let watch_input = match midir::MidiInput::new("uncycle_clock_watch") {
+            Ok(watch_input) => watch_input,
+            Err(e) => {
+                log.lock()
+                    .unwrap()
+                    .log_misc(format!("Failed to create MIDI clock input: {}", e));
+                return;
+            }
+        };
+
+        if let Ok(port) = get_device_port(&app_input, &core, Some(&clock_port), None, &log) {
+            let port_name = get_port_name(&app_input, &port, &log).unwrap_or_default();
+            let core_rx_callback = core.clone();
+            let log_rx_callback = log.clone();
+
+            match app_input.connect(
+                &port,
+                "uncycle-clock-in",
+                move |_timestamp, message, _| {
+                    clock_callback(message, now, &core_rx_callback, &log_rx_callback)
+                },
+                (),
+            ) {
+                Err(e) => {
+                    log.lock()
+                        .unwrap()
+                        .log_misc(format!("Unable to connect: {}", e));
+                }
+
+                Ok(_conn) => {
+                    log.lock()
+                        .unwrap()
+                        .log_misc(format!("Connected to clock port: {}", port_name));
+
+                    // reconnects once the port is back
+                    while port_name.is_empty() || has_port(&watch_input, &port_name) {
+                        thread::sleep(Duration::from_secs(1));
+                    }
+
+                    log.lock()
+                        .unwrap()
+                        .log_misc(format!("Lost clock port: {}", port_name));
+                }
+            }
         }
+
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn clock_callback(
+    message: &[u8],
+    now: Instant,
+    core: &Arc<Mutex<UncycleCore>>,
+    log: &Arc<Mutex<Logger>>,
+) {
+    {
+        let mut locked = core.lock().unwrap();
+        locked.update_time(now.elapsed().as_micros() as u64);
+        locked.clock_rx_callback(message);
+    }
+
+    if let Some(msg @ (MidiMessage::Start | MidiMessage::Continue | MidiMessage::Stop)) =
+        MidiMessage::from_bytes(message)
+    {
+        log.lock().unwrap().log_misc(format!(
+            "Received: 0x{:02X} (MIDI {}, clock port)",
+            msg.status(),
+            msg
+        ));
     }
 }
 
@@ -143,7 +478,13 @@ fn log_in_port(log: &Arc<Mutex<Logger>>, port_name: String) {
 /////////////////////////////////////////////////////////////////////
 
 /// Tries to reconnect to a specific output port every 1 second, based on chosen device
-pub fn midi_output_thread(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>, now: Instant) {
+pub fn midi_output_thread(
+    core: Arc<Mutex<UncycleCore>>,
+    log: Arc<Mutex<Logger>>,
+    units: Arc<Mutex<Units>>,
+    picked_port: Option<String>,
+    now: Instant,
+) {
     loop {
         let app_output = match midir::MidiOutput::new("uncycle_midi_output") {
             Ok(app_output) => app_output,
@@ -156,7 +497,7 @@ pub fn midi_output_thread(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>
         };
 
         if core.lock().unwrap().device.is_some() {
-            autoconnect_output(app_output, &core, now, &log);
+            autoconnect_output(app_output, &core, &units, picked_port.as_deref(), now, &log);
         }
 
         // no need to keep track of time, if no device is actually connected
@@ -167,11 +508,21 @@ pub fn midi_output_thread(core: Arc<Mutex<UncycleCore>>, log: Arc<Mutex<Logger>>
 fn autoconnect_output(
     app_output: MidiOutput,
     core: &Arc<Mutex<UncycleCore>>,
+    units: &Arc<Mutex<Units>>,
+    picked_port: Option<&str>,
     now: Instant,
     log: &Arc<Mutex<Logger>>,
 ) {
-    if let Ok(device_out_port) = get_device_port(&app_output, &core, &log) {
-        if let Ok(port_name) = get_port_name(&app_output, &device_out_port, &log) {
+    let detected_port = get_detected_port(core, units, |device| &device.out_port);
+
+    if let Ok(device_out_port) = get_device_port(
+        &app_output,
+        core,
+        picked_port,
+        detected_port.as_deref(),
+        log,
+    ) {
+        if let Ok(port_name) = get_port_name(&app_output, &device_out_port, log) {
             match app_output.connect(&device_out_port, "uncycle-midi-out") {
                 Err(e) => {
                     log.lock()
@@ -180,15 +531,22 @@ fn autoconnect_output(
                 }
 
                 Ok(mut conn) => {
-                    log_out_port(&log, port_name);
+                    log_out_port(log, port_name);
 
                     loop {
-                        output_callback(&mut conn, now, &core, &log);
+                        output_callback(&mut conn, now, core, log);
 
                         if core.lock().unwrap().kill_tx_conn {
                             continue;
                         }
 
+                        // reconnects to another unit
+                        if get_detected_port(core, units, |device| &device.out_port)
+                            != detected_port
+                        {
+                            break;
+                        }
+
                         // poll @ 1kHz, thread timing accuracy does not matter too much since we pass time as paramter to callback
                         // therefor poll rate is what we care about
                         thread::sleep(Duration::from_micros(100));
@@ -214,7 +572,7 @@ fn output_callback(
     let bytes;
 
     {
-        bytes = core.lock().unwrap().midi_tx_callback();
+        bytes = core.lock().unwrap().midi_tx_callback().to_vec();
     }
 
     // send MIDI outside of lock
@@ -249,6 +607,21 @@ fn output_callback(
             ));
         }
     }
+
+    // at most one SysEx is sent at a time
+    if let Some(start) = bytes.iter().position(|byte| *byte == MIDI_SYSEX) {
+        let payload = bytes[start + 1..]
+            .iter()
+            .take_while(|byte| **byte != MIDI_SYSEX_END)
+            .copied()
+            .collect::<Vec<u8>>();
+
+        if let Some(sysex) = SysEx::from_payload(&payload) {
+            log.lock()
+                .unwrap()
+                .log_misc(format!("Send: {} ({} bytes)", sysex, sysex.n_bytes()));
+        }
+    }
 }
 
 fn log_out_port(log: &Arc<Mutex<Logger>>, port_name: String) {
@@ -261,17 +634,35 @@ fn log_out_port(log: &Arc<Mutex<Logger>>, port_name: String) {
 // HELPERS
 /////////////////////////////////////////////////////////////////////
 
-fn get_device_name(core: &Arc<Mutex<UncycleCore>>) -> Option<String> {
-    if let Some(device) = &core.lock().unwrap().device {
-        Some(device.name_to_str().to_string())
-    } else {
-        None
-    }
+fn get_port_name_match(core: &Arc<Mutex<UncycleCore>>) -> Option<String> {
+    core.lock()
+        .unwrap()
+        .device
+        .as_ref()
+        .map(|device| device.port_name_to_str().to_string())
 }
 
+/// Port of the picked unit of the chosen device, or of its first detected unit
+fn get_detected_port(
+    core: &Arc<Mutex<UncycleCore>>,
+    units: &Arc<Mutex<Units>>,
+    port: fn(&DetectedDevice) -> &String,
+) -> Option<String> {
+    let id = core.lock().unwrap().device.as_ref()?.id_to_str();
+
+    units
+        .lock()
+        .unwrap()
+        .unit_of(&id)
+        .map(|unit| port(unit).clone())
+}
+
+/// Takes the port picked by the user, then the one the device has been detected on, otherwise looks for its port name
 fn get_device_port<M: MidiIO>(
     app_input: &M,
     core: &Arc<Mutex<UncycleCore>>,
+    picked_port: Option<&str>,
+    detected_port: Option<&str>,
     log: &Arc<Mutex<Logger>>,
 ) -> Result<M::Port, ()> {
     let other_in_ports = app_input.ports();
@@ -283,10 +674,42 @@ fn get_device_port<M: MidiIO>(
         return Err(());
     }
 
-    if let Some(name) = get_device_name(&core).as_ref() {
-        for (i, port) in other_in_ports.iter().enumerate() {
-            if app_input.port_name(port).unwrap().contains(name) {
-                return Ok(other_in_ports[i].clone());
+    // a picked port is looked for by any part of its name, a device by its whole port name
+    let detected_port = detected_port.filter(|_| picked_port.is_none());
+    let name_match = picked_port
+        .map(str::to_string)
+        .or_else(|| get_port_name_match(core));
+
+    if let Some(name) = detected_port {
+        for port in &other_in_ports {
+            if app_input
+                .port_name(port)
+                .is_ok_and(|port_name| port_name == name)
+            {
+                return Ok(port.clone());
+            }
+        }
+    }
+
+    if name_match.as_ref().is_some_and(String::is_empty) {
+        log.lock()
+            .unwrap()
+            .log_misc("Pick the ports of the device with --in-port and --out-port".to_string());
+        return Err(());
+    }
+
+    if let Some(name) = name_match.as_ref() {
+        let is_match = |port_name: &str| match picked_port {
+            Some(_) => port_name.contains(name.as_str()),
+            None => is_device_port(port_name, name),
+        };
+
+        for port in &other_in_ports {
+            if app_input
+                .port_name(port)
+                .is_ok_and(|port_name| is_match(&port_name))
+            {
+                return Ok(port.clone());
             }
         }
 
@@ -298,6 +721,22 @@ fn get_device_port<M: MidiIO>(
     Err(())
 }
 
+fn nth_port<M: MidiIO>(midi_io: &M, i: usize) -> Option<(M::Port, String)> {
+    let port = midi_io.ports().get(i)?.clone();
+    let name = midi_io.port_name(&port).ok()?;
+
+    Some((port, name))
+}
+
+/// Connections stay open when their port disappears, e.g. when a device is unplugged
+fn has_port<M: MidiIO>(midi_io: &M, name: &str) -> bool {
+    midi_io.ports().iter().any(|port| {
+        midi_io
+            .port_name(port)
+            .is_ok_and(|port_name| port_name == name)
+    })
+}
+
 fn get_port_name<M: MidiIO>(
     app_input: &M,
     device_in_port: &M::Port,
@@ -17,8 +17,21 @@ pub enum Action {
     ToggleSequence,
     StartRecording,
     DeleteRecording,
+    UndoRecording,
+    RedoRecording,
+    QuantizeRecording,
+    ExportRecording,
+    ImportRecording,
+    PrevLane,
+    NextLane,
+    ClearLane,
+    MuteLane,
+    SoloLane,
+    RerecordLane,
     DoubleLoopLen,
     HalfLoopLen,
+    IncreaseLoopLen,
+    DecreaseLoopLen,
     ToggleMenu,
     ToggleHelp,
     MenuMoveDown,
@@ -60,6 +73,61 @@ impl Keybindings {
                 description: "Delete recording",
                 action: Action::DeleteRecording,
             },
+            KeyAction {
+                key: KeyCode::Char('u'),
+                description: "Undo last record/overdub pass",
+                action: Action::UndoRecording,
+            },
+            KeyAction {
+                key: KeyCode::Char('r'),
+                description: "Redo last undone pass",
+                action: Action::RedoRecording,
+            },
+            KeyAction {
+                key: KeyCode::Char('g'),
+                description: "Quantize recording to grid",
+                action: Action::QuantizeRecording,
+            },
+            KeyAction {
+                key: KeyCode::Char('w'),
+                description: "Export loop as MIDI file",
+                action: Action::ExportRecording,
+            },
+            KeyAction {
+                key: KeyCode::Char('i'),
+                description: "Import loop from MIDI file",
+                action: Action::ImportRecording,
+            },
+            KeyAction {
+                key: KeyCode::Char('h'),
+                description: "Select previous lane",
+                action: Action::PrevLane,
+            },
+            KeyAction {
+                key: KeyCode::Char('l'),
+                description: "Select next lane",
+                action: Action::NextLane,
+            },
+            KeyAction {
+                key: KeyCode::Char('x'),
+                description: "Clear selected lane",
+                action: Action::ClearLane,
+            },
+            KeyAction {
+                key: KeyCode::Char('z'),
+                description: "Mute selected lane",
+                action: Action::MuteLane,
+            },
+            KeyAction {
+                key: KeyCode::Char('o'),
+                description: "Solo selected lane",
+                action: Action::SoloLane,
+            },
+            KeyAction {
+                key: KeyCode::Char('e'),
+                description: "Re-record selected lane",
+                action: Action::RerecordLane,
+            },
             KeyAction {
                 key: KeyCode::Char('j'),
                 description: "Half Loop Length",
@@ -70,6 +138,16 @@ impl Keybindings {
                 description: "Double Loop Length",
                 action: Action::DoubleLoopLen,
             },
+            KeyAction {
+                key: KeyCode::Char('<'),
+                description: "Shorten Loop by one Step",
+                action: Action::DecreaseLoopLen,
+            },
+            KeyAction {
+                key: KeyCode::Char('>'),
+                description: "Lengthen Loop by one Step",
+                action: Action::IncreaseLoopLen,
+            },
             KeyAction {
                 key: KeyCode::Tab,
                 description: "Cycle Tabs",
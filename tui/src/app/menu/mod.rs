@@ -5,7 +5,7 @@ mod selection;
 
 pub use popup::{PopupTab, PopupMenu};
 pub use selection::FocusArea;
-pub use setting::Setting;
+pub use setting::{Apply, Setting};
 
 use selection::{NestedSelectionState};
 
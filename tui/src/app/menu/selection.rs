@@ -1,6 +1,6 @@
 use uncycle_core::prelude::UncycleCore;
 
-use super::setting::Setting;
+use super::setting::{Apply, Setting};
 
 #[derive(Debug, Clone)]
 pub struct NestedSelectionState {
@@ -93,9 +93,16 @@ impl NestedSelectionState {
         }
     }
 
-    pub fn apply_current_setting(&mut self, app: &mut UncycleCore) {
+    /// Selects the options the core is set to, e.g. after a session has been loaded
+    pub fn read_settings(&mut self, core: &UncycleCore) {
+        for setting in &mut self.settings {
+            if let Apply::Core { read_fn, .. } = setting.apply {
+                setting.selected_option = read_fn(core);
+            }
+        }
+
         if let Some(setting) = self.settings.get(self.selected_setting) {
-            (setting.apply_fn)(app, &setting);
+            self.selected_option = setting.selected_option;
         }
     }
 }
@@ -1,10 +1,24 @@
 use uncycle_core::prelude::UncycleCore;
 
+use crate::App;
+
 #[derive(Debug, Clone)]
 pub struct Setting {
     pub name: String,
     pub description: String,
     pub options: Vec<String>,
     pub selected_option: usize,
-    pub apply_fn: fn(&mut UncycleCore, &Setting),
-}
\ No newline at end of file
+    pub apply: Apply,
+}
+
+/// What happens when an option has been chosen
+#[derive(Debug, Clone, Copy)]
+pub enum Apply {
+    /// changes the core, `read_fn` returns the option the core is set to
+    Core {
+        apply_fn: fn(&mut UncycleCore, &Setting),
+        read_fn: fn(&UncycleCore) -> usize,
+    },
+    /// runs an action of the app, like saving the session
+    App(fn(&mut App, &Setting)),
+}
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod app;
 mod connection;
 mod keybindings;
@@ -8,4 +9,4 @@ mod widgets;
 mod cli;
 
 pub use app::*;
-pub use cli::parse_cli;
\ No newline at end of file
+pub use cli::{parse_cli, Connection, SessionFile, SmfFiles};
\ No newline at end of file
@@ -19,6 +19,9 @@ pub fn render_device_tab(f: &mut Frame, app: &App, area: Rect) {
     if let Some(device) = maybe_device {
         match device {
             SupportedDevice::TR8(_) => tr8::render(f, app, area),
+            SupportedDevice::TR8S(_) => tr8s::render(f, app, area),
+            SupportedDevice::Generic(_) => cc_grid::render(f, app, area, &device),
+            SupportedDevice::Defined(_) => defined::render(f, app, area, &device),
             // add new devices manually
         }
     } else {
@@ -3,7 +3,7 @@ use crate::app;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Stylize},
-    text::ToLine,
+    text::{Line, ToLine},
     Frame,
 };
 
@@ -14,6 +14,7 @@ pub fn render_main_tab(f: &mut Frame, app: &App, area: Rect) {
     let current_step;
     let loop_steps;
     let bpm;
+    let clock_source;
 
     {
         let mut locked = app.core.lock().unwrap();
@@ -21,6 +22,7 @@ pub fn render_main_tab(f: &mut Frame, app: &App, area: Rect) {
         current_step = locked.get_step_number() + 1;
         loop_steps = locked.looper.loop_steps;
         bpm = locked.get_bpm();
+        clock_source = locked.get_clock_source();
     }
 
     let chunks = Layout::default()
@@ -40,6 +42,11 @@ pub fn render_main_tab(f: &mut Frame, app: &App, area: Rect) {
 
     let record_state = app.core.lock().unwrap().looper.record;
     let overdub_state = app.core.lock().unwrap().looper.overdub;
+    let rec_mode = app.core.lock().unwrap().looper.get_rec_mode();
+    let overdub_mode = app.core.lock().unwrap().looper.get_overdub_mode();
+    let n_layers = app.core.lock().unwrap().looper.n_layers();
+    let n_undone_layers = app.core.lock().unwrap().looper.n_undone_layers();
+    let quantize = app.core.lock().unwrap().looper.get_quantize();
 
     let rec_text;
     let running_text;
@@ -67,7 +74,13 @@ pub fn render_main_tab(f: &mut Frame, app: &App, area: Rect) {
         rec_border_color = Color::DarkGray;
     }
 
-    let bpm_text = vec!["BPM".to_line().dark_gray(), bpm.to_line().magenta()];
+    let bpm_text = match clock_source {
+        ClockSource::Internal => vec!["BPM".to_line().dark_gray(), bpm.to_line().magenta()],
+        ClockSource::External => vec![
+            "Ext. BPM".to_line().dark_gray(),
+            Line::from(format!("{:.1}", bpm)).magenta(),
+        ],
+    };
 
     let recording_text = vec!["Status".to_line().dark_gray(), running_text, rec_text];
 
@@ -108,13 +121,36 @@ pub fn render_main_tab(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(app::widgets::border_rounded("", Color::Gray), higher[0]);
     f.render_widget(app::widgets::main_text(bpm_text), higher[0]);
 
+    let rec_mode_title = match (rec_mode, overdub_mode) {
+        (RecMode::OneShot, OverdubMode::Add) => " One Shot ",
+        (RecMode::OneShot, OverdubMode::Replace) => " One Shot | Replace ",
+        (RecMode::Continuous, OverdubMode::Add) => " Continuous ",
+        (RecMode::Continuous, OverdubMode::Replace) => " Continuous | Replace ",
+    };
+
     f.render_widget(
-        app::widgets::border_rounded("", rec_border_color),
+        app::widgets::border_rounded(rec_mode_title, rec_border_color),
         higher[1],
     );
     f.render_widget(app::widgets::main_text(recording_text), higher[1]);
 
-    f.render_widget(app::widgets::border_rounded("", Color::Gray), lower[0]);
+    let mut layers_title = if n_undone_layers > 0 {
+        format!(" Layers {}/{} ", n_layers, n_layers + n_undone_layers)
+    } else {
+        format!(" Layers {} ", n_layers)
+    };
+
+    match quantize {
+        Quantize::Off => {}
+        Quantize::Sixteenth => layers_title += "| Q 1/16 ",
+        Quantize::ThirtySecond => layers_title += "| Q 1/32 ",
+        Quantize::Tick => layers_title += "| Q 1/96 ",
+    }
+
+    f.render_widget(
+        app::widgets::border_rounded(&layers_title, Color::Gray),
+        lower[0],
+    );
     f.render_widget(app::widgets::main_text(rec_loop_text), lower[0]);
 
     f.render_widget(
@@ -0,0 +1,75 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Stylize,
+    widgets::{Block, BorderType, Paragraph},
+    Frame,
+};
+use uncycle_core::prelude::*;
+
+use super::{
+    lanes,
+    widgets::{LaneView, BG_COLOR},
+};
+use crate::app::App;
+
+const N_COLUMNS: usize = 8;
+const N_ROWS: usize = N_CC_NUMBERS / N_COLUMNS;
+
+/// Renders the last value of every CC number on the channel of the device, column by column
+pub fn render(f: &mut Frame, app: &App, area: Rect, device: &SupportedDevice) {
+    let channel = device.tx_channel();
+    let selected_lane = lanes(device.params()).get(app.selected_lane).cloned();
+
+    let lane_view;
+    let values;
+
+    {
+        let core = app.core.lock().unwrap();
+
+        lane_view = LaneView::read(&core, selected_lane.as_ref().map(|(cc_num, _)| *cc_num));
+        values = *core.last_cc_of(channel);
+    }
+
+    let lane_title = selected_lane
+        .map(|(cc_num, name)| lane_view.describe(cc_num, &name))
+        .unwrap_or_default();
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .green()
+        .title(format!(" {} (Ch {}) ", device.name_to_str(), channel + 1))
+        .title_bottom(lane_title)
+        .title_alignment(Alignment::Center)
+        .bg(BG_COLOR);
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, N_COLUMNS as u32); N_COLUMNS])
+        .margin(1)
+        .split(inner);
+
+    for (column, area) in columns.iter().enumerate() {
+        let cells = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Ratio(1, N_ROWS as u32); N_ROWS])
+            .split(*area);
+
+        for (row, cell) in cells.iter().enumerate() {
+            let cc_num = column * N_ROWS + row;
+            let value = match values[cc_num] {
+                Some(value) => format!("{:3}", value),
+                None => "  -".to_string(),
+            };
+
+            f.render_widget(
+                Paragraph::new(format!("CC {:3}  {}", cc_num, value))
+                    .centered()
+                    .style(lane_view.style(cc_num as u8)),
+                *cell,
+            );
+        }
+    }
+}
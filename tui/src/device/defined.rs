@@ -0,0 +1,124 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, BorderType, Paragraph},
+    Frame,
+};
+use uncycle_core::prelude::*;
+
+use super::{
+    lanes,
+    widgets::{knob, LaneView, BG_COLOR},
+};
+use crate::app::App;
+
+const KNOBS_PER_ROW: usize = 8;
+
+/// Renders the parameters of any device as rows of knobs, with its notes below
+pub fn render(f: &mut Frame, app: &App, area: Rect, device: &SupportedDevice) {
+    let params = device.params();
+    let notes = device.notes();
+    let selected_lane = lanes(params).get(app.selected_lane).cloned();
+
+    let lane_view;
+    let values: Vec<u8>;
+    let active_notes: Vec<bool>;
+
+    {
+        let mut core = app.core.lock().unwrap();
+        let channel = device.tx_channel();
+
+        lane_view = LaneView::read(&core, selected_lane.as_ref().map(|(cc_num, _)| *cc_num));
+        values = params
+            .iter()
+            .map(|param| core.get_cc_val_of(channel, param.cc))
+            .collect();
+        active_notes = notes
+            .iter()
+            .map(|note| core.find_active_note(channel, note.note))
+            .collect();
+    }
+
+    let lane_title = selected_lane
+        .map(|(cc_num, name)| lane_view.describe(cc_num, &name))
+        .unwrap_or_default();
+
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .green()
+        .title(format!(
+            " {} {} ",
+            device.manufacturer_to_str(),
+            device.name_to_str()
+        ))
+        .title_bottom(lane_title)
+        .title_alignment(Alignment::Center)
+        .bg(BG_COLOR);
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let notes_height = if notes.is_empty() { 0 } else { 3 };
+
+    let vert = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Fill(1), Constraint::Length(notes_height)])
+        .margin(1)
+        .split(inner);
+
+    render_params(f, vert[0], params, &values, &lane_view);
+    render_notes(f, vert[1], notes, &active_notes);
+}
+
+fn render_params(
+    f: &mut Frame,
+    area: Rect,
+    params: &[ParamDefinition],
+    values: &[u8],
+    lane_view: &LaneView,
+) {
+    let n_rows = params.len().div_ceil(KNOBS_PER_ROW);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, n_rows as u32); n_rows])
+        .split(area);
+
+    for (row, params) in params.chunks(KNOBS_PER_ROW).enumerate() {
+        let knobs = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, KNOBS_PER_ROW as u32); KNOBS_PER_ROW])
+            .split(rows[row]);
+
+        for (i, param) in params.iter().enumerate() {
+            let value = values[row * KNOBS_PER_ROW + i].clamp(param.min, param.max);
+            let pos = (value - param.min) as f64 / (param.max - param.min) as f64;
+
+            f.render_widget(knob(param.name, pos, lane_view.style(param.cc)), knobs[i]);
+        }
+    }
+}
+
+fn render_notes(f: &mut Frame, area: Rect, notes: &[NoteDefinition], is_active: &[bool]) {
+    let n_notes = notes.len();
+
+    let pads = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, n_notes as u32); n_notes])
+        .split(area);
+
+    for (i, note) in notes.iter().enumerate() {
+        let style = if is_active[i] {
+            Style::default().fg(BG_COLOR).bg(Color::Green)
+        } else {
+            Style::default().fg(Color::Green).bg(BG_COLOR)
+        };
+
+        let pad = Paragraph::new(note.name)
+            .centered()
+            .style(style)
+            .block(Block::bordered().border_type(BorderType::Rounded));
+
+        f.render_widget(pad, pads[i]);
+    }
+}
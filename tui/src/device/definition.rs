@@ -0,0 +1,154 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use uncycle_core::prelude::*;
+
+/// Device definition as written in a TOML file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefinitionFile {
+    id: String,
+    name: String,
+    manufacturer: String,
+    /// defaults to the name
+    port_name: Option<String>,
+    /// 1 to 16, like on the device
+    channel: u8,
+    #[serde(default)]
+    notes: Vec<NoteEntry>,
+    #[serde(default)]
+    params: Vec<ParamEntry>,
+    identity: Option<IdentityEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoteEntry {
+    note: u8,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParamEntry {
+    cc: u8,
+    name: String,
+    #[serde(default)]
+    group: String,
+    #[serde(default)]
+    min: u8,
+    #[serde(default = "max_value")]
+    max: u8,
+    /// defaults to `min`
+    default: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdentityEntry {
+    /// one or three bytes, as in the Identity Reply
+    manufacturer: Vec<u8>,
+    family: u16,
+    member: Option<u16>,
+}
+
+impl IdentityEntry {
+    fn to_match(&self) -> Result<IdentityMatch> {
+        let manufacturer = match self.manufacturer[..] {
+            [id] if id != 0 => ManufacturerId::Short(id),
+            [0, id_1, id_2] => ManufacturerId::Extended(id_1, id_2),
+            _ => bail!("manufacturer needs to be one byte or three bytes starting with 0"),
+        };
+
+        Ok(IdentityMatch {
+            manufacturer,
+            family: self.family,
+            member: self.member,
+        })
+    }
+}
+
+fn max_value() -> u8 {
+    127
+}
+
+impl DefinitionFile {
+    /// Definitions live as long as the program, so they are leaked once instead of being copied around
+    fn leak(self) -> Result<&'static DeviceDefinition> {
+        let identity = self
+            .identity
+            .as_ref()
+            .map(IdentityEntry::to_match)
+            .transpose()?;
+
+        let notes = self
+            .notes
+            .into_iter()
+            .map(|note| NoteDefinition {
+                note: note.note,
+                name: note.name.leak(),
+            })
+            .collect::<Vec<_>>();
+
+        let params = self
+            .params
+            .into_iter()
+            .map(|param| ParamDefinition {
+                cc: param.cc,
+                name: param.name.leak(),
+                group: param.group.leak(),
+                min: param.min,
+                max: param.max,
+                default: param.default.unwrap_or(param.min),
+            })
+            .collect::<Vec<_>>();
+
+        let port_name = self.port_name.unwrap_or_else(|| self.name.clone());
+
+        Ok(Box::leak(Box::new(DeviceDefinition {
+            id: self.id.leak(),
+            name: self.name.leak(),
+            manufacturer: self.manufacturer.leak(),
+            port_name: port_name.leak(),
+            // channel 0 turns into an invalid one
+            channel: self.channel.wrapping_sub(1),
+            notes: notes.leak(),
+            params: params.leak(),
+            identity,
+        })))
+    }
+}
+
+/// Reads a device definition from a TOML file
+fn load(path: &Path) -> Result<&'static DeviceDefinition> {
+    let text = fs::read_to_string(path)?;
+    let file: DefinitionFile = toml::from_str(&text)?;
+
+    file.leak()
+}
+
+/// Makes every `*.toml` definition in `dir` available, sorted by file name
+pub fn load_dir(dir: &Path, core: &mut UncycleCore) -> Result<()> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("Unable to read {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    paths.retain(|path| {
+        path.extension()
+            .is_some_and(|extension| extension == "toml")
+    });
+    paths.sort();
+
+    for path in paths {
+        let definition =
+            load(&path).with_context(|| format!("Unable to load {}", path.display()))?;
+
+        core.add_device_definition(definition)
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("Unable to add {}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -1 +1,28 @@
+pub mod cc_grid;
+pub mod defined;
+pub mod definition;
 pub mod tr8;
+pub mod tr8s;
+mod widgets;
+
+use uncycle_core::prelude::*;
+
+/// Looper lanes that can be selected, with a display name
+///
+/// Every device has its CC parameters followed by pitch bend and aftertouch, which don't depend on its params.
+pub fn lanes(params: &[ParamDefinition]) -> Vec<(u8, String)> {
+    let other_lanes = [
+        (LANE_PITCH_BEND, "Pitch Bend"),
+        (LANE_CHANNEL_PRESSURE, "Channel Pressure"),
+        (LANE_POLY_PRESSURE, "Poly Pressure"),
+    ];
+
+    params
+        .iter()
+        .map(|param| match param.group {
+            "" => (param.cc, param.name.to_string()),
+            group => (param.cc, format!("{} {}", group, param.name)),
+        })
+        .chain(other_lanes.map(|(lane, name)| (lane, name.to_string())))
+        .collect()
+}
@@ -1,33 +1,25 @@
-use uncycle_core::devices::tr8::*;
+use uncycle_core::{devices::tr8::*, prelude::DeviceInterface};
 
 use core::f64;
-use std::{
-    f64::consts::{FRAC_PI_3, PI},
-    str::FromStr,
-    usize,
-};
+use std::str::FromStr;
 
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     prelude::Stylize,
     style::{Color, Style},
     symbols::{self},
-    widgets::{
-        block::Position,
-        canvas::{self, Canvas},
-        Block, Widget,
-    },
+    widgets::{Block, Widget},
     Frame,
 };
 
+use super::{
+    lanes,
+    widgets::{fader, knob, LaneView, BG_COLOR},
+};
 use crate::app::App;
 
-const KNOB_TURN_RADIANS: f64 = -5.0 * FRAC_PI_3;
-const KNOB_TURN_OFFSET: f64 = PI + FRAC_PI_3;
 const GOLDEN_RATIO: f64 = 1.61803398875;
 
-const BG_COLOR: Color = Color::Black;
-
 pub fn render(f: &mut Frame, app: &App, area: Rect) {
     // MIDI data extraction
     ///////////////////////
@@ -37,24 +29,33 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
     let mut current_param_1st: [u8; TR_8_PARAM_ELEMS] = [0_u8; TR_8_PARAM_ELEMS];
     let mut current_param_2nd: [u8; TR_8_PARAM_ELEMS] = [0_u8; TR_8_PARAM_ELEMS];
 
+    let selected_lane = lanes(&TR_8_PARAMS).get(app.selected_lane).cloned();
+    let lane_view;
+
     {
         let mut core = app.core.lock().unwrap();
+        let channel = core
+            .device
+            .as_ref()
+            .map_or(TR_8_DEFAULT_CHANNEL, |device| device.tx_channel());
+
+        lane_view = LaneView::read(&core, selected_lane.as_ref().map(|(cc_num, _)| *cc_num));
 
         for i in 0..(TR_8_STEPS - 1) {
             match i {
                 0..TR_8_INTRUMENTS => {
-                    current_volume[i] = core.get_cc_val_of(TR_8_CC_FADER[i].0);
-                    current_active_steps[i] = core.find_active_note(TR_8_NOTES[i]);
-                    current_param_1st[i] = core.get_cc_val_of(TR_8_CC_PARAMS_1ST_ROW[i].0);
-                    current_param_2nd[i] = core.get_cc_val_of(TR_8_CC_PARAMS_2ND_ROW[i].0);
+                    current_volume[i] = core.get_cc_val_of(channel, TR_8_CC_FADER[i].0);
+                    current_active_steps[i] = core.find_active_note(channel, TR_8_NOTES[i]);
+                    current_param_1st[i] = core.get_cc_val_of(channel, TR_8_CC_PARAMS_1ST_ROW[i].0);
+                    current_param_2nd[i] = core.get_cc_val_of(channel, TR_8_CC_PARAMS_2ND_ROW[i].0);
                 }
                 TR_8_INTRUMENTS..TR_8_PARAM_ELEMS => {
-                    current_active_steps[i] = core.find_active_note(TR_8_NOTES[i]);
-                    current_param_1st[i] = core.get_cc_val_of(TR_8_CC_PARAMS_1ST_ROW[i].0);
-                    current_param_2nd[i] = core.get_cc_val_of(TR_8_CC_PARAMS_2ND_ROW[i].0);
+                    current_active_steps[i] = core.find_active_note(channel, TR_8_NOTES[i]);
+                    current_param_1st[i] = core.get_cc_val_of(channel, TR_8_CC_PARAMS_1ST_ROW[i].0);
+                    current_param_2nd[i] = core.get_cc_val_of(channel, TR_8_CC_PARAMS_2ND_ROW[i].0);
                 }
                 TR_8_PARAM_ELEMS..TR_8_STEPS => {
-                    current_active_steps[i] = core.find_active_note(TR_8_NOTES[i]);
+                    current_active_steps[i] = core.find_active_note(channel, TR_8_NOTES[i]);
                 }
                 _ => {}
             }
@@ -93,11 +94,17 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
         .margin(2)
         .split(tr8);
 
+    let lane_title = selected_lane
+        .map(|(cc_num, name)| lane_view.describe(cc_num, &name))
+        .unwrap_or_default();
+
     // Border outline
     f.render_widget(
         Block::bordered()
             .green()
             .border_set(symbols::border::QUADRANT_OUTSIDE)
+            .title_bottom(lane_title)
+            .title_alignment(Alignment::Center)
             .bg(BG_COLOR),
         area.clamp(tr8).inner(Margin::new(0, 0)),
     );
@@ -108,6 +115,7 @@ pub fn render(f: &mut Frame, app: &App, area: Rect) {
         &current_volume,
         &current_param_1st,
         &current_param_2nd,
+        &lane_view,
     );
     render_lines::<6>(f, vert[1]);
     render_lines::<3>(f, vert[2]);
@@ -125,6 +133,7 @@ fn render_instruments(
     volume: &[u8; TR_8_INTRUMENTS],
     param_1st: &[u8; TR_8_PARAM_ELEMS],
     param_2nd: &[u8; TR_8_PARAM_ELEMS],
+    lane_view: &LaneView,
 ) {
     // Split horizonzally
     let row = Layout::default()
@@ -169,19 +178,22 @@ fn render_instruments(
         // let wrap = i as f64 / knobs_1st_row.len() as f64;
         let pos_1st_row = param_1st[i] as f64 / 127.0_f64;
         let pos_2nd_row = param_2nd[i] as f64 / 127.0_f64;
+        let (cc_1st_row, title_1st_row) = TR_8_CC_PARAMS_1ST_ROW[i];
+        let (cc_2nd_row, title_2nd_row) = TR_8_CC_PARAMS_2ND_ROW[i];
         f.render_widget(
-            knob(TR_8_CC_PARAMS_1ST_ROW[i].1, pos_1st_row),
+            knob(title_1st_row, pos_1st_row, lane_view.style(cc_1st_row)),
             knobs_1st_row[i],
         );
         f.render_widget(
-            knob(TR_8_CC_PARAMS_2ND_ROW[i].1, pos_2nd_row),
+            knob(title_2nd_row, pos_2nd_row, lane_view.style(cc_2nd_row)),
             knobs_2nd_row[i],
         );
     }
 
     for i in 0..faders.len() {
         let pos = volume[i] as f64 / 127.0_f64;
-        f.render_widget(fader(TR_8_CC_FADER[i].1, pos), faders[i]);
+        let (cc_num, title) = TR_8_CC_FADER[i];
+        f.render_widget(fader(title, pos, lane_view.style(cc_num)), faders[i]);
     }
 }
 
@@ -218,62 +230,13 @@ fn render_steps(f: &mut Frame, area: Rect, is_active: &[bool; TR_8_STEPS]) {
 // Helper functions
 ///////////////////
 
-fn knob<'a>(title: &'a str, pos: f64) -> impl Widget + 'a {
-    Canvas::default()
-        .block(
-            Block::new()
-                .title(title)
-                .title_alignment(Alignment::Center)
-                .title_position(Position::Bottom)
-                .gray(),
-        )
-        .paint(move |ctx| {
-            ctx.draw(&canvas::Circle {
-                x: 0.0,
-                y: 0.0,
-                radius: 1.0,
-                color: Color::DarkGray,
-            });
-            ctx.draw(&canvas::Line::new(
-                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).cos() * 0.4,
-                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).sin() * 0.4,
-                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).cos(),
-                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).sin(),
-                Color::White,
-            ));
-        })
-        .marker(symbols::Marker::HalfBlock)
-        .x_bounds([-2.0, 2.0])
-        .y_bounds([-2.0, 2.0])
-        .background_color(BG_COLOR)
-}
-
-fn fader<'a>(title: &'a str, pos: f64) -> impl Widget + 'a {
-    Canvas::default()
-        .block(
-            Block::new()
-                .title_bottom(title)
-                .title_alignment(Alignment::Center)
-                .gray(),
-        )
-        .paint(move |ctx| {
-            ctx.draw(&canvas::Line::new(0.0, 0.0, 0.0, 1.0, Color::Green));
-            ctx.draw(&canvas::Line::new(-0.5, pos, 0.5, pos, Color::White));
-        })
-        .marker(symbols::Marker::HalfBlock)
-        .x_bounds([-1.0, 1.0])
-        .y_bounds([-0.1, 1.1])
-        .background_color(BG_COLOR)
-}
-
 fn tr8_step(step: usize, is_active: bool) -> impl Widget {
-    let c;
-    match step as u16 {
-        0..=3 => c = Color::from_str("#ff0000").unwrap_or(Color::Red),
-        4..=7 => c = Color::from_str("#FF5C00").unwrap_or(Color::Yellow),
-        8..=11 => c = Color::from_str("#ffcc00ff").unwrap_or(Color::LightYellow),
-        12..=u16::MAX => c = Color::White,
-    }
+    let c = match step as u16 {
+        0..=3 => Color::from_str("#ff0000").unwrap_or(Color::Red),
+        4..=7 => Color::from_str("#FF5C00").unwrap_or(Color::Yellow),
+        8..=11 => Color::from_str("#ffcc00ff").unwrap_or(Color::LightYellow),
+        12..=u16::MAX => Color::White,
+    };
 
     let b = Block::bordered()
         .border_set(symbols::border::ROUNDED)
@@ -281,8 +244,8 @@ fn tr8_step(step: usize, is_active: bool) -> impl Widget {
         .style(Style::default().fg(c));
 
     if !is_active {
-        return b.bg(c);
+        b.bg(c)
     } else {
-        return b;
+        b
     }
 }
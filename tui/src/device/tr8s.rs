@@ -0,0 +1,179 @@
+use uncycle_core::{
+    devices::tr8s::*,
+    prelude::{DeviceInterface, ParamDefinition},
+};
+
+use std::{rc::Rc, str::FromStr};
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    prelude::Stylize,
+    style::{Color, Style},
+    symbols,
+    widgets::{Block, Paragraph, Widget},
+    Frame,
+};
+
+use super::{
+    lanes,
+    widgets::{fader, knob, LaneView, BG_COLOR},
+};
+use crate::app::App;
+
+const N_KNOB_ROWS: usize = TR_8S_INSTRUMENT_PARAMS.len() - 1;
+
+/// Extra knobs of BD and SD followed by the kit, all in the top row
+const N_TOP_KNOBS: usize = TR_8S_N_EXTRA_PARAMS + TR_8S_N_KIT_PARAMS;
+
+pub fn render(f: &mut Frame, app: &App, area: Rect) {
+    // MIDI data extraction
+    ///////////////////////
+
+    let mut current_active_steps = [false; TR_8S_INSTRUMENTS];
+    let mut current_knobs = [[0_u8; TR_8S_INSTRUMENTS]; N_KNOB_ROWS];
+    let mut current_level = [0_u8; TR_8S_INSTRUMENTS];
+    let mut current_top = [0_u8; N_TOP_KNOBS];
+
+    let selected_lane = lanes(&TR_8S_PARAMS).get(app.selected_lane).cloned();
+    let lane_view;
+
+    {
+        let mut core = app.core.lock().unwrap();
+        let channel = core
+            .device
+            .as_ref()
+            .map_or(TR_8S_DEFAULT_CHANNEL, |device| device.tx_channel());
+
+        lane_view = LaneView::read(&core, selected_lane.as_ref().map(|(cc_num, _)| *cc_num));
+
+        for i in 0..TR_8S_INSTRUMENTS {
+            current_knobs[0][i] = core.get_cc_val_of(channel, TR_8S_CC_TUNE[i]);
+            current_knobs[1][i] = core.get_cc_val_of(channel, TR_8S_CC_DECAY[i]);
+            current_knobs[2][i] = core.get_cc_val_of(channel, TR_8S_CC_CTRL[i]);
+            current_level[i] = core.get_cc_val_of(channel, TR_8S_CC_LEVEL[i]);
+            current_active_steps[i] = core.find_active_note(channel, TR_8S_NOTES[i]);
+        }
+
+        for (value, param) in current_top.iter_mut().zip(top_params()) {
+            *value = core.get_cc_val_of(channel, param.cc);
+        }
+    }
+
+    // Vertical Rendering
+    /////////////////////
+
+    let lane_title = selected_lane
+        .map(|(cc_num, name)| lane_view.describe(cc_num, &name))
+        .unwrap_or_default();
+
+    let block = Block::bordered()
+        .green()
+        .border_set(symbols::border::QUADRANT_OUTSIDE)
+        .title_bottom(lane_title)
+        .title_alignment(Alignment::Center)
+        .bg(BG_COLOR);
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let vert = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            // extra knobs and kit
+            Constraint::Percentage(14),
+            // knobs
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+            // level
+            Constraint::Fill(1),
+            // instrument names
+            Constraint::Length(1),
+            // steps
+            Constraint::Percentage(15),
+        ])
+        .margin(1)
+        .split(inner);
+
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, N_TOP_KNOBS as u32); N_TOP_KNOBS])
+        .split(vert[0]);
+
+    for (i, param) in top_params().enumerate() {
+        let pos = current_top[i] as f64 / 127.0_f64;
+        let title = match param.group {
+            "KIT" => param.name.to_string(),
+            instrument => format!("{} {}", instrument, param.name),
+        };
+
+        f.render_widget(knob(&title, pos, lane_view.style(param.cc)), top[i]);
+    }
+
+    let ccs = [TR_8S_CC_TUNE, TR_8S_CC_DECAY, TR_8S_CC_CTRL];
+
+    for row in 0..N_KNOB_ROWS {
+        let knobs = columns(vert[row + 1]);
+
+        for i in 0..TR_8S_INSTRUMENTS {
+            let pos = current_knobs[row][i] as f64 / 127.0_f64;
+            let style = lane_view.style(ccs[row][i]);
+
+            f.render_widget(knob(TR_8S_INSTRUMENT_PARAMS[row], pos, style), knobs[i]);
+        }
+    }
+
+    let faders = columns(vert[4]);
+    let names = columns(vert[5]);
+    let steps = columns(vert[6]);
+
+    for i in 0..TR_8S_INSTRUMENTS {
+        let pos = current_level[i] as f64 / 127.0_f64;
+        let style = lane_view.style(TR_8S_CC_LEVEL[i]);
+
+        f.render_widget(fader("LEVEL", pos, style), faders[i]);
+        f.render_widget(
+            Paragraph::new(TR_8S_INSTRUMENT_NAMES[i]).centered().gray(),
+            names[i],
+        );
+        f.render_widget(
+            tr8s_step(i, current_active_steps[i]),
+            steps[i].inner(Margin::new(1, 0)),
+        );
+    }
+}
+
+// Helper functions
+///////////////////
+
+fn top_params() -> impl Iterator<Item = &'static ParamDefinition> {
+    TR_8S_EXTRA_PARAMS.iter().chain(TR_8S_KIT_PARAMS.iter())
+}
+
+/// One column per instrument
+fn columns(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, TR_8S_INSTRUMENTS as u32); TR_8S_INSTRUMENTS])
+        .split(area)
+}
+
+fn tr8s_step(instrument: usize, is_active: bool) -> impl Widget {
+    let c = match instrument {
+        0..=1 => Color::from_str("#ff0000").unwrap_or(Color::Red),
+        2..=5 => Color::from_str("#FF5C00").unwrap_or(Color::Yellow),
+        6..=8 => Color::from_str("#ffcc00ff").unwrap_or(Color::LightYellow),
+        _ => Color::White,
+    };
+
+    let b = Block::bordered()
+        .border_set(symbols::border::ROUNDED)
+        .border_style(Style::reset().fg(c).bg(BG_COLOR))
+        .style(Style::default().fg(c));
+
+    if !is_active {
+        b.bg(c)
+    } else {
+        b
+    }
+}
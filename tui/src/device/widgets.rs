@@ -0,0 +1,139 @@
+use std::f64::consts::{FRAC_PI_3, PI};
+
+use ratatui::{
+    layout::Alignment,
+    prelude::Stylize,
+    style::{Color, Style},
+    symbols,
+    widgets::{
+        block::Position,
+        canvas::{self, Canvas},
+        Block, Widget,
+    },
+};
+use uncycle_core::prelude::*;
+
+const KNOB_TURN_RADIANS: f64 = -5.0 * FRAC_PI_3;
+const KNOB_TURN_OFFSET: f64 = PI + FRAC_PI_3;
+
+pub const BG_COLOR: Color = Color::Black;
+
+/// Looper lane state of every CC number, used to highlight knobs and faders
+pub struct LaneView {
+    automated: [bool; N_LANES],
+    muted: [bool; N_LANES],
+    soloed: [bool; N_LANES],
+    selected: Option<u8>,
+}
+
+impl LaneView {
+    pub fn read(core: &UncycleCore, selected: Option<u8>) -> Self {
+        let mut muted = [false; N_LANES];
+        let mut soloed = [false; N_LANES];
+
+        for cc_num in 0..N_LANES as u8 {
+            muted[cc_num as usize] = core.looper.lane(cc_num).muted;
+            soloed[cc_num as usize] = core.looper.lane(cc_num).soloed;
+        }
+
+        Self {
+            automated: core.looper.lanes_with_automation(),
+            muted,
+            soloed,
+            selected,
+        }
+    }
+
+    pub fn style(&self, cc_num: u8) -> Style {
+        let i = cc_num as usize;
+
+        let style = if self.soloed[i] {
+            Style::default().fg(Color::Yellow)
+        } else if self.muted[i] {
+            Style::default().fg(Color::DarkGray)
+        } else if self.automated[i] {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+
+        if self.selected == Some(cc_num) {
+            style.reversed()
+        } else {
+            style
+        }
+    }
+
+    pub fn describe(&self, cc_num: u8, name: &str) -> String {
+        let i = cc_num as usize;
+        let mut text = if i < N_CC_NUMBERS {
+            format!(" Lane: {} (CC {})", name, cc_num)
+        } else {
+            format!(" Lane: {}", name)
+        };
+
+        if self.automated[i] {
+            text += " · automated";
+        }
+
+        if self.muted[i] {
+            text += " · muted";
+        }
+
+        if self.soloed[i] {
+            text += " · solo";
+        }
+
+        text + " "
+    }
+}
+
+pub fn knob<'a>(title: &'a str, pos: f64, title_style: Style) -> impl Widget + 'a {
+    Canvas::default()
+        .block(
+            Block::new()
+                .title(title)
+                .title_alignment(Alignment::Center)
+                .title_position(Position::Bottom)
+                .title_style(title_style)
+                .gray(),
+        )
+        .paint(move |ctx| {
+            ctx.draw(&canvas::Circle {
+                x: 0.0,
+                y: 0.0,
+                radius: 1.0,
+                color: Color::DarkGray,
+            });
+            ctx.draw(&canvas::Line::new(
+                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).cos() * 0.4,
+                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).sin() * 0.4,
+                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).cos(),
+                (pos * KNOB_TURN_RADIANS + KNOB_TURN_OFFSET).sin(),
+                Color::White,
+            ));
+        })
+        .marker(symbols::Marker::HalfBlock)
+        .x_bounds([-2.0, 2.0])
+        .y_bounds([-2.0, 2.0])
+        .background_color(BG_COLOR)
+}
+
+pub fn fader<'a>(title: &'a str, pos: f64, title_style: Style) -> impl Widget + 'a {
+    Canvas::default()
+        .block(
+            Block::new()
+                .title_bottom(title)
+                .title_alignment(Alignment::Center)
+                .title_style(title_style)
+                .gray(),
+        )
+        .paint(move |ctx| {
+            ctx.draw(&canvas::Line::new(0.0, 0.0, 0.0, 1.0, Color::Green));
+            ctx.draw(&canvas::Line::new(-0.5, pos, 0.5, pos, Color::White));
+        })
+        .marker(symbols::Marker::HalfBlock)
+        .x_bounds([-1.0, 1.0])
+        .y_bounds([-0.1, 1.1])
+        .background_color(BG_COLOR)
+}
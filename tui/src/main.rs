@@ -2,17 +2,19 @@ mod app;
 mod device;
 
 use anyhow::Result;
-use app::{run_app, App};
+use app::{run_app, App, DEFAULT_BPM};
 use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
+use uncycle_core::prelude::UncycleCore;
 
 use crate::app::parse_cli;
 
 fn main() -> Result<()> {
-    let settings = parse_cli();
+    let mut core = UncycleCore::new(DEFAULT_BPM);
+    let (settings, smf_files, session_file, connection) = parse_cli(&mut core);
 
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -23,7 +25,7 @@ fn main() -> Result<()> {
 
     terminal.clear()?;
 
-    let mut app = App::new(settings);
+    let mut app = App::new(core, settings, smf_files, session_file, connection);
 
     let result = run_app(&mut terminal, &mut app);
 